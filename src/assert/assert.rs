@@ -1,11 +1,12 @@
-use crate::utils::exit_with_code;
 use crate::{
-    assert::{ValueAssertions, ASSERTION_TYPES},
-    compile::{compile_string, CompiledString},
+    assert::{value_assertions::AssertFn, ValueAssertions, ASSERTION_TYPES},
+    compile::compile_string,
+    error::CapterError,
     workflow::WorkflowConfigAssertion,
 };
 use serde::Serialize;
 use serde_json::{json, Value};
+use std::path::Path;
 
 /// Assertion turns an expection string from the yaml workflows
 /// into a real assertion that can be used on a payload.
@@ -13,10 +14,12 @@ use serde_json::{json, Value};
 /// `- !expect status equal {{ env.STATUS }}` will be parsed and
 /// can then be used to assert "data" by calling `.assert(data)`.
 pub struct Assertion {
-    assertion_string: CompiledString,
-    /// inverts the test if true
-    /// `expected a to NOT be b`
-    not: bool,
+    /// The test with the real values, used to assert.
+    test: AssertionTest,
+    /// The same test, with the masked values.
+    masked_test: AssertionTest,
+    assert_fn: AssertFn,
+    is_masked: bool,
 }
 
 /// The result of an assertion. Can be serialized to JSON
@@ -33,6 +36,9 @@ pub struct AssertionResultData {
 pub struct AssertionData {
     pub status: Option<u16>,
     pub body: serde_json::Value,
+    pub text: Option<String>,
+    pub size: Option<usize>,
+    pub hash: Option<String>,
     pub headers: serde_json::Value,
     pub duration: i64,
 }
@@ -56,19 +62,43 @@ pub struct AssertionTest {
 impl Assertion {
     /// Create a new Assertion from a string.
     /// The format of the string is `status equal 200`,
+    ///
+    /// Returns an error if the templates can't be compiled,
+    /// or if the string isn't an assertion we know.
     pub fn from_assertion(
         assertion_string: &WorkflowConfigAssertion,
         workflow_data: &Value,
-    ) -> Assertion {
+    ) -> Result<Assertion, CapterError> {
         let (not, assertion_string) = match assertion_string {
-            WorkflowConfigAssertion::expect(val) => (false, compile_string(val, workflow_data)),
-            WorkflowConfigAssertion::expect_not(val) => (true, compile_string(val, workflow_data)),
+            WorkflowConfigAssertion::expect(val) => (false, compile_string(val, workflow_data)?),
+            WorkflowConfigAssertion::expect_not(val) => (true, compile_string(val, workflow_data)?),
         };
 
-        Assertion {
-            not,
-            assertion_string,
+        let test = parse_assertion_string(&assertion_string.raw, not)?;
+        let masked_test = parse_assertion_string(&assertion_string.masked, not)?;
+        let assert_fn = ValueAssertions::get(&test.test)?;
+
+        Ok(Assertion {
+            test,
+            masked_test,
+            assert_fn,
+            is_masked: assertion_string.raw != assertion_string.masked,
+        })
+    }
+
+    /// Schema files in `to_match_schema` are relative to `dir`,
+    /// the directory of the workflow.
+    pub fn relative_to(mut self, dir: &Path) -> Assertion {
+        if let ("to_match_schema", Value::String(path)) =
+            (self.test.test.as_str(), &self.test.value)
+        {
+            let path = path.trim();
+            if !path.starts_with('{') {
+                self.test.value = json!(dir.join(path).to_string_lossy());
+            }
         }
+
+        self
     }
 
     /// Assert on the data passed in. Returns
@@ -76,21 +106,17 @@ impl Assertion {
     /// to the user or sent to the webhook.
     pub fn assert(&self, assertion_data: &AssertionData) -> AssertionResultData {
         let assertion_data_json = json!(&assertion_data);
-        let assertion = parse_assertion_string(&self.assertion_string.raw, self.not);
 
         // create a path to the property and get the data
-        let path = format!("/{}", assertion.property.replace(".", "/"));
+        let path = format!("/{}", self.test.property.replace(".", "/"));
         let data = assertion_data_json.pointer(&path).unwrap_or(&Value::Null);
 
-        let assert_fn = ValueAssertions::get(&assertion.test);
-        let result = assert_fn(data, &assertion.value, self.not);
+        let result = (self.assert_fn)(data, &self.test.value, self.test.not);
         let passed = result.is_none();
 
-        let is_masked = self.assertion_string.raw != self.assertion_string.masked;
-
         AssertionResultData {
             assertion: self.get_masked_assertion_test(),
-            message: match is_masked {
+            message: match self.is_masked {
                 true => Some("Hidden because of mask".to_string()),
                 false => result,
             },
@@ -100,16 +126,19 @@ impl Assertion {
 
     /// Create a masked version of the `AssertionTest`.
     pub fn get_masked_assertion_test(&self) -> AssertionTest {
-        parse_assertion_string(&self.assertion_string.masked, self.not)
+        self.masked_test.to_owned()
     }
 }
 
 /// Parse an assertion string.
 ///
 /// It splits the string up and tries to figure out
-/// the different parts of an `AssertionTest`. Will exit
-/// if it can't parse the input.
-pub fn parse_assertion_string(assertion_string: &str, not: bool) -> AssertionTest {
+/// the different parts of an `AssertionTest`. Returns
+/// an error if it can't parse the input.
+pub fn parse_assertion_string(
+    assertion_string: &str,
+    not: bool,
+) -> Result<AssertionTest, CapterError> {
     let mut parts = assertion_string.split(' ').collect::<Vec<&str>>();
 
     // pull the property from the array
@@ -118,22 +147,22 @@ pub fn parse_assertion_string(assertion_string: &str, not: bool) -> AssertionTes
 
     // !expect x isArray
     if parts.len() == 1 && ASSERTION_TYPES.contains(&parts[0]) {
-        return AssertionTest {
+        return Ok(AssertionTest {
             test: parts[0].to_owned(),
             property: property.to_owned(),
             value: Value::Null,
             not,
-        };
+        });
     }
 
     // !expect x data.0.title isNotEmpty
     if parts.len() == 2 && ASSERTION_TYPES.contains(&parts[1]) {
-        return AssertionTest {
+        return Ok(AssertionTest {
             test: parts[1].to_owned(),
             property: format!("{}.{}", property, parts[0]),
             value: Value::Null,
             not,
-        };
+        });
     }
 
     // !expect x isAbove 5
@@ -142,12 +171,12 @@ pub fn parse_assertion_string(assertion_string: &str, not: bool) -> AssertionTes
         value.remove(0);
         let value = value.join(" ");
 
-        return AssertionTest {
+        return Ok(AssertionTest {
             test: parts[0].to_owned(),
             property: property.to_owned(),
             value: json!(value),
             not,
-        };
+        });
     };
 
     // !expect x data.0.id equal 0
@@ -157,20 +186,17 @@ pub fn parse_assertion_string(assertion_string: &str, not: bool) -> AssertionTes
         value.remove(0);
         let value = value.join(" ");
 
-        return AssertionTest {
+        return Ok(AssertionTest {
             test: parts[1].to_owned(),
             property: format!("{}.{}", property, parts[0]),
             value: json!(value),
             not,
-        };
+        });
     }
-    exit_with_code(
-        exitcode::CONFIG,
-        Some(&format!(
-            "Could not parse assertion: `{}`",
-            assertion_string
-        )),
-    )
+    Err(CapterError::Assertion(format!(
+        "Could not parse assertion: `{}`",
+        assertion_string
+    )))
 }
 
 #[cfg(test)]
@@ -189,6 +215,9 @@ mod tests {
             headers: json!({
                 "content-type": "application/json",
             }),
+            text: None,
+            size: None,
+            hash: None,
             duration: 500,
             status: Some(200),
         };
@@ -196,37 +225,71 @@ mod tests {
         let assertion = Assertion::from_assertion(
             &WorkflowConfigAssertion::expect("body.user.name to_equal ${{ env.name }}".to_string()),
             &data,
-        );
+        )
+        .unwrap();
         let result = assertion.assert(&assertion_data);
-        assert_eq!(result.passed, true);
+        assert!(result.passed);
 
         let assertion = Assertion::from_assertion(
             &WorkflowConfigAssertion::expect_not("body.user.name to_equal bad value".to_string()),
             &data,
-        );
+        )
+        .unwrap();
         let result = assertion.assert(&assertion_data);
-        assert_eq!(result.passed, true);
+        assert!(result.passed);
 
         let assertion = Assertion::from_assertion(
             &WorkflowConfigAssertion::expect("headers.nope to_be_undefined".to_string()),
             &data,
-        );
+        )
+        .unwrap();
         let result = assertion.assert(&assertion_data);
-        assert_eq!(result.passed, true);
+        assert!(result.passed);
 
         let assertion = Assertion::from_assertion(
             &WorkflowConfigAssertion::expect_not("body to_be_empty".to_string()),
             &data,
-        );
+        )
+        .unwrap();
         let result = assertion.assert(&assertion_data);
-        assert_eq!(result.passed, true);
+        assert!(result.passed);
 
         let assertion = Assertion::from_assertion(
             &WorkflowConfigAssertion::expect("body.name to_equal ${{ mask env.name }}".to_string()),
             &data,
-        );
+        )
+        .unwrap();
         let result = assertion.assert(&assertion_data);
-        assert_eq!(result.passed, false);
+        assert!(!result.passed);
         assert_eq!(result.message, Some("Hidden because of mask".to_string()));
     }
+
+    #[test]
+    fn test_invalid_assertion() {
+        let data = json!({});
+
+        let error = Assertion::from_assertion(
+            &WorkflowConfigAssertion::expect("status to_be 200".to_string()),
+            &data,
+        )
+        .err();
+        assert_eq!(
+            error,
+            Some(CapterError::Assertion(
+                "Could not parse assertion: `status to_be 200`".to_string()
+            ))
+        );
+
+        let error = Assertion::from_assertion(
+            &WorkflowConfigAssertion::expect("status to_be_ok".to_string()),
+            &data,
+        )
+        .err();
+        assert_eq!(
+            error,
+            Some(CapterError::Assertion(
+                "assertion not found: `to_be_ok`".to_string()
+            ))
+        );
+    }
 }
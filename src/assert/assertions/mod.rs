@@ -17,6 +17,7 @@ pub mod to_equal;
 pub mod to_exist;
 pub mod to_have_length;
 pub mod to_match;
+pub mod to_match_schema;
 
 pub use to_be_above::to_be_above;
 pub use to_be_array::to_be_array;
@@ -37,6 +38,7 @@ pub use to_equal::to_equal;
 pub use to_exist::to_exist;
 pub use to_have_length::to_have_length;
 pub use to_match::to_match;
+pub use to_match_schema::to_match_schema;
 
 pub mod prelude {
     pub use crate::assert::assertions::*;
@@ -3,7 +3,7 @@ use serde_json::Value;
 
 pub fn to_be_empty(a: &Value, _b: &Value, not: bool) -> Option<String> {
     if let Some(array) = a.as_array() {
-        let result = array.len() > 0;
+        let result = !array.is_empty();
         if utils::did_pass(result, not) {
             return Some(format!("expected {} {} be empty", a, utils::to(not)));
         }
@@ -11,7 +11,7 @@ pub fn to_be_empty(a: &Value, _b: &Value, not: bool) -> Option<String> {
     }
 
     if let Some(string) = a.as_str() {
-        let result = string.len() > 0;
+        let result = !string.is_empty();
         if utils::did_pass(result, not) {
             return Some(format!("expected {} {} be empty", a, utils::to(not)));
         }
@@ -26,7 +26,7 @@ pub fn to_be_empty(a: &Value, _b: &Value, not: bool) -> Option<String> {
         return None;
     }
 
-    return Some(format!("expected a string, array or object"));
+    Some("expected a string, array or object".to_string())
 }
 
 #[cfg(test)]
@@ -3,7 +3,7 @@ use serde_json::Value;
 
 pub fn to_be_true(a: &Value, _b: &Value, not: bool) -> Option<String> {
     if let Some(val) = a.as_bool() {
-        let result = val == true;
+        let result = val;
         if utils::did_pass(result, not) {
             return None;
         }
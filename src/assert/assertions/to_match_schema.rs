@@ -0,0 +1,122 @@
+use crate::assert::{schema::validate_schema, utils};
+use serde_json::Value;
+use std::fs::read_to_string;
+
+/// Validate `a` against the JSON Schema in `b`.
+///
+/// `b` can either be a path to a `.json` or `.yml` file with the schema,
+/// relative to the workflow (see `Assertion::relative_to`),
+/// or the schema itself, like `${{ schemas.user }}`.
+pub fn to_match_schema(a: &Value, b: &Value, not: bool) -> Option<String> {
+    let schema = match load_schema(b) {
+        Ok(schema) => schema,
+        Err(message) => return Some(message),
+    };
+
+    let errors = validate_schema(a, &schema, &schema);
+    if utils::did_pass(errors.is_empty(), not) {
+        return None;
+    }
+
+    if not {
+        return Some("expected value to not match schema".to_string());
+    }
+
+    let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+    Some(format!(
+        "expected value to match schema, but got {} error{}:\n{}",
+        errors.len(),
+        match errors.len() {
+            1 => "",
+            _ => "s",
+        },
+        errors.join("\n")
+    ))
+}
+
+fn load_schema(value: &Value) -> Result<Value, String> {
+    let string = match value {
+        Value::String(string) => string.trim(),
+        Value::Object(_) | Value::Bool(_) => return Ok(value.to_owned()),
+        _ => return Err(format!("expected {} to be a schema", value)),
+    };
+
+    // inline schemas are compiled in to JSON
+    if string.starts_with('{') {
+        return serde_json::from_str(string)
+            .map_err(|err| format!("could not parse schema: {}", err));
+    }
+
+    let content = read_to_string(string)
+        .map_err(|err| format!("could not read schema {}: {}", string, err))?;
+
+    let schema = match string.ends_with(".json") {
+        true => serde_json::from_str(&content).map_err(|err| err.to_string()),
+        false => serde_yaml::from_str(&content).map_err(|err| err.to_string()),
+    };
+
+    schema.map_err(|err| format!("could not parse schema {}: {}", string, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::{env::temp_dir, fs::write};
+
+    #[test]
+    fn test_pass() {
+        let schema = json!({ "type": "object", "required": ["id"] }).to_string();
+        assert_eq!(
+            to_match_schema(&json!({ "id": 1 }), &json!(schema), false),
+            None
+        );
+        assert_eq!(to_match_schema(&json!({}), &json!(schema), true), None);
+    }
+
+    #[test]
+    fn test_fail() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "name"],
+            "properties": { "id": { "type": "integer" } }
+        })
+        .to_string();
+
+        assert_eq!(
+            to_match_schema(&json!({ "id": "1" }), &json!(schema), false),
+            Some(
+                "expected value to match schema, but got 2 errors:\n\
+                 /: missing required property `name`\n\
+                 /id: expected integer but got string"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            to_match_schema(&json!({ "id": 1, "name": "" }), &json!(schema), true),
+            Some("expected value to not match schema".to_string())
+        );
+    }
+
+    #[test]
+    fn test_schema_file() {
+        let path = temp_dir().join("capter_to_match_schema.yml");
+        write(&path, "type: array\nitems:\n  type: string\n").unwrap();
+        let path = json!(path.to_str().unwrap());
+
+        assert_eq!(to_match_schema(&json!(["a"]), &path, false), None);
+        assert_eq!(
+            to_match_schema(&json!([1]), &path, false),
+            Some(
+                "expected value to match schema, but got 1 error:\n\
+                 /0: expected string but got number"
+                    .to_string()
+            )
+        );
+
+        let missing = to_match_schema(&json!([]), &json!("./nope.json"), false);
+        assert!(missing
+            .unwrap()
+            .starts_with("could not read schema ./nope.json"));
+    }
+}
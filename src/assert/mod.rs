@@ -1,5 +1,7 @@
+#[allow(clippy::module_inception)]
 pub mod assert;
 pub mod assertions;
+pub mod schema;
 pub mod utils;
 pub mod value_assertions;
 
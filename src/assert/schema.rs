@@ -0,0 +1,500 @@
+use regex::Regex;
+use serde_json::{Map, Value};
+
+/// A value that doesn't match a schema, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    /// JSON pointer to the value, like `/users/0/id`.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.pointer.is_empty() {
+            true => write!(f, "/: {}", self.message),
+            false => write!(f, "{}: {}", self.pointer, self.message),
+        }
+    }
+}
+
+/// Validate a value against a JSON Schema, and return every violation.
+///
+/// `root` is the document used to resolve `$ref`s like `#/definitions/user`.
+/// For a standalone schema this is the schema itself, but for OpenAPI it's
+/// the whole spec, so `#/components/schemas/user` can be resolved.
+///
+/// We support the keywords that are useful for API contracts, including
+/// `nullable` from OpenAPI 3.0. Unknown keywords (like `format`) are ignored.
+pub fn validate_schema(value: &Value, schema: &Value, root: &Value) -> Vec<SchemaError> {
+    let mut errors = vec![];
+    validate(value, schema, root, "", &mut errors, 0);
+    errors
+}
+
+fn validate(
+    value: &Value,
+    schema: &Value,
+    root: &Value,
+    pointer: &str,
+    errors: &mut Vec<SchemaError>,
+    depth: usize,
+) {
+    let mut error = |message: String| {
+        errors.push(SchemaError {
+            pointer: pointer.to_string(),
+            message,
+        })
+    };
+
+    // `true` matches everything and `false` nothing
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => return error("no value is allowed here".to_string()),
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    // protect against `$ref`s pointing at themselves
+    if depth > 64 {
+        return error("schema is nested too deep".to_string());
+    }
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match resolve_ref(reference, root) {
+            Some(resolved) => validate(value, resolved, root, pointer, errors, depth + 1),
+            None => error(format!("could not resolve `$ref` {}", reference)),
+        }
+        return;
+    }
+
+    if value.is_null() && schema.get("nullable") == Some(&Value::Bool(true)) {
+        return;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+
+        if !types.is_empty() && !types.iter().any(|name| is_type(value, name)) {
+            // the other keywords don't make sense for the wrong type
+            return error(format!(
+                "expected {} but got {}",
+                types.join(" or "),
+                type_name(value)
+            ));
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            error(format!(
+                "expected one of {} but got {}",
+                Value::Array(allowed.to_owned()),
+                value
+            ));
+        }
+    }
+
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            error(format!("expected {} but got {}", constant, value));
+        }
+    }
+
+    match value {
+        Value::Object(object) => validate_object(object, schema, &mut error),
+        Value::Array(array) => validate_array(array, schema, &mut error),
+        Value::String(string) => validate_string(string, schema, &mut error),
+        Value::Number(_) => validate_number(value, schema, &mut error),
+        _ => {}
+    }
+
+    // everything below can have errors in nested values
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+
+        for (key, property_value) in object {
+            let property_pointer = format!("{}/{}", pointer, escape_pointer(key));
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property_schema) => validate(
+                    property_value,
+                    property_schema,
+                    root,
+                    &property_pointer,
+                    errors,
+                    depth + 1,
+                ),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => errors.push(SchemaError {
+                        pointer: property_pointer,
+                        message: "property is not allowed".to_string(),
+                    }),
+                    Some(additional @ Value::Object(_)) => validate(
+                        property_value,
+                        additional,
+                        root,
+                        &property_pointer,
+                        errors,
+                        depth + 1,
+                    ),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    if let Value::Array(array) = value {
+        match schema.get("items") {
+            Some(Value::Array(items)) => {
+                for (index, (item, item_schema)) in array.iter().zip(items).enumerate() {
+                    let item_pointer = format!("{}/{}", pointer, index);
+                    validate(item, item_schema, root, &item_pointer, errors, depth + 1);
+                }
+            }
+            Some(item_schema) => {
+                for (index, item) in array.iter().enumerate() {
+                    let item_pointer = format!("{}/{}", pointer, index);
+                    validate(item, item_schema, root, &item_pointer, errors, depth + 1);
+                }
+            }
+            None => {}
+        }
+    }
+
+    if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+        for sub_schema in all_of {
+            validate(value, sub_schema, root, pointer, errors, depth + 1);
+        }
+    }
+
+    if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+        let matches = any_of
+            .iter()
+            .filter(|sub_schema| is_valid(value, sub_schema, root, depth))
+            .count();
+        if matches == 0 {
+            errors.push(SchemaError {
+                pointer: pointer.to_string(),
+                message: "expected value to match at least one schema in `anyOf`".to_string(),
+            });
+        }
+    }
+
+    if let Some(one_of) = schema.get("oneOf").and_then(Value::as_array) {
+        let matches = one_of
+            .iter()
+            .filter(|sub_schema| is_valid(value, sub_schema, root, depth))
+            .count();
+        if matches != 1 {
+            errors.push(SchemaError {
+                pointer: pointer.to_string(),
+                message: format!(
+                    "expected value to match exactly one schema in `oneOf`, but it matched {}",
+                    matches
+                ),
+            });
+        }
+    }
+
+    if let Some(not) = schema.get("not") {
+        if is_valid(value, not, root, depth) {
+            errors.push(SchemaError {
+                pointer: pointer.to_string(),
+                message: "expected value to not match the schema in `not`".to_string(),
+            });
+        }
+    }
+}
+
+fn is_valid(value: &Value, schema: &Value, root: &Value, depth: usize) -> bool {
+    let mut errors = vec![];
+    validate(value, schema, root, "", &mut errors, depth + 1);
+    errors.is_empty()
+}
+
+fn validate_object(
+    object: &Map<String, Value>,
+    schema: &Map<String, Value>,
+    error: &mut impl FnMut(String),
+) {
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                error(format!("missing required property `{}`", key));
+            }
+        }
+    }
+
+    if let Some(min) = schema.get("minProperties").and_then(Value::as_u64) {
+        if (object.len() as u64) < min {
+            error(format!("expected at least {} properties", min));
+        }
+    }
+
+    if let Some(max) = schema.get("maxProperties").and_then(Value::as_u64) {
+        if object.len() as u64 > max {
+            error(format!("expected at most {} properties", max));
+        }
+    }
+}
+
+fn validate_array(array: &[Value], schema: &Map<String, Value>, error: &mut impl FnMut(String)) {
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if (array.len() as u64) < min {
+            error(format!(
+                "expected at least {} items but got {}",
+                min,
+                array.len()
+            ));
+        }
+    }
+
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if array.len() as u64 > max {
+            error(format!(
+                "expected at most {} items but got {}",
+                max,
+                array.len()
+            ));
+        }
+    }
+
+    if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+        let has_duplicates = array
+            .iter()
+            .enumerate()
+            .any(|(index, item)| array[index + 1..].contains(item));
+        if has_duplicates {
+            error("expected all items to be unique".to_string());
+        }
+    }
+}
+
+fn validate_string(string: &str, schema: &Map<String, Value>, error: &mut impl FnMut(String)) {
+    let length = string.chars().count() as u64;
+
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if length < min {
+            error(format!(
+                "expected at least {} characters but got {}",
+                min, length
+            ));
+        }
+    }
+
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if length > max {
+            error(format!(
+                "expected at most {} characters but got {}",
+                max, length
+            ));
+        }
+    }
+
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        match Regex::new(pattern) {
+            Ok(regex) if !regex.is_match(string) => error(format!(
+                "expected \"{}\" to match pattern {}",
+                string, pattern
+            )),
+            Err(_) => error(format!("invalid pattern in schema: {}", pattern)),
+            _ => {}
+        }
+    }
+}
+
+fn validate_number(value: &Value, schema: &Map<String, Value>, error: &mut impl FnMut(String)) {
+    let number = value.as_f64().unwrap_or(0.0);
+
+    // draft 4 and OpenAPI 3.0 use booleans for `exclusiveMinimum`
+    // and `exclusiveMaximum`, newer drafts use numbers
+    let exclusive = |key: &str| schema.get(key) == Some(&Value::Bool(true));
+
+    if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+        if number < minimum || (exclusive("exclusiveMinimum") && number == minimum) {
+            error(format!("expected {} to be at least {}", value, minimum));
+        }
+    }
+
+    if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+        if number > maximum || (exclusive("exclusiveMaximum") && number == maximum) {
+            error(format!("expected {} to be at most {}", value, maximum));
+        }
+    }
+
+    if let Some(minimum) = schema.get("exclusiveMinimum").and_then(Value::as_f64) {
+        if number <= minimum {
+            error(format!("expected {} to be above {}", value, minimum));
+        }
+    }
+
+    if let Some(maximum) = schema.get("exclusiveMaximum").and_then(Value::as_f64) {
+        if number >= maximum {
+            error(format!("expected {} to be below {}", value, maximum));
+        }
+    }
+
+    if let Some(multiple_of) = schema.get("multipleOf").and_then(Value::as_f64) {
+        if multiple_of > 0.0 && (number / multiple_of).fract() != 0.0 {
+            error(format!(
+                "expected {} to be a multiple of {}",
+                value, multiple_of
+            ));
+        }
+    }
+}
+
+/// Resolve a local `$ref` like `#/components/schemas/user`.
+fn resolve_ref<'a>(reference: &str, root: &'a Value) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    root.pointer(pointer)
+}
+
+fn is_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+    }
+}
+
+/// Escape a key so it can be used in a JSON pointer.
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn errors(value: Value, schema: Value) -> Vec<String> {
+        validate_schema(&value, &schema, &schema)
+            .iter()
+            .map(|error| error.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "name"],
+            "properties": {
+                "id": { "type": "integer", "minimum": 1 },
+                "name": { "type": "string", "minLength": 1 },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "role": { "enum": ["admin", "user"] },
+                "manager": { "type": "object", "nullable": true }
+            }
+        });
+
+        let value = json!({
+            "id": 1,
+            "name": "Test McTest",
+            "tags": ["a", "b"],
+            "role": "admin",
+            "manager": null
+        });
+        assert_eq!(errors(value, schema), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_every_error_has_a_pointer() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "name"],
+            "additionalProperties": false,
+            "properties": {
+                "id": { "type": "integer" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+            }
+        });
+
+        let value = json!({ "id": "1", "tags": ["a", 2], "extra": true });
+        assert_eq!(
+            errors(value, schema),
+            vec![
+                "/: missing required property `name`",
+                "/extra: property is not allowed",
+                "/id: expected integer but got string",
+                "/tags/1: expected string but got number",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_refs() {
+        let schema = json!({
+            "type": "array",
+            "items": { "$ref": "#/definitions/user" },
+            "definitions": {
+                "user": {
+                    "type": "object",
+                    "properties": { "id": { "type": "number" } }
+                }
+            }
+        });
+
+        assert_eq!(
+            errors(json!([{ "id": 1 }, { "id": false }]), schema),
+            vec!["/1/id: expected number but got boolean"]
+        );
+    }
+
+    #[test]
+    fn test_combinators() {
+        let schema = json!({
+            "oneOf": [{ "type": "string" }, { "type": "number", "maximum": 5 }]
+        });
+
+        assert_eq!(errors(json!("a"), schema.clone()), Vec::<String>::new());
+        assert_eq!(
+            errors(json!(10), schema),
+            vec!["/: expected value to match exactly one schema in `oneOf`, but it matched 0"]
+        );
+
+        let schema = json!({ "not": { "type": "null" } });
+        assert_eq!(errors(json!(null), schema).len(), 1);
+    }
+
+    #[test]
+    fn test_strings_and_numbers() {
+        let schema = json!({ "type": "string", "pattern": "^[a-z]+$", "maxLength": 3 });
+        assert_eq!(
+            errors(json!("abcD"), schema),
+            vec![
+                "/: expected at most 3 characters but got 4",
+                "/: expected \"abcD\" to match pattern ^[a-z]+$",
+            ]
+        );
+
+        let schema = json!({ "type": "number", "exclusiveMinimum": 0, "multipleOf": 2 });
+        assert_eq!(
+            errors(json!(0), schema),
+            vec!["/: expected 0 to be above 0"]
+        );
+    }
+}
@@ -1,9 +1,13 @@
 use crate::assert::assertions::prelude::*;
-use crate::utils::exit_with_code;
+use crate::error::CapterError;
 use serde_json::Value;
 
 pub struct ValueAssertions {}
 
+/// Asserts that the value matches the expected value, and returns
+/// a message if it doesn't. The last argument inverts the test.
+pub type AssertFn = fn(&Value, &Value, bool) -> Option<String>;
+
 pub const ASSERTION_TYPES: &[&str] = &[
     "to_be_ok",
     "to_equal",
@@ -25,11 +29,13 @@ pub const ASSERTION_TYPES: &[&str] = &[
     "to_have_length",
     "to_be_empty",
     "to_match",
+    "to_match_schema",
 ];
 
 impl ValueAssertions {
-    pub fn get(name: &str) -> fn(&Value, &Value, bool) -> Option<String> {
-        match name {
+    /// The function for the assertion `name`, like `to_equal`.
+    pub fn get(name: &str) -> Result<AssertFn, CapterError> {
+        let assert_fn: AssertFn = match name {
             "to_equal" => to_equal,
             "to_be_above" => to_be_above,
             "to_be_at_least" => to_be_at_least,
@@ -49,10 +55,15 @@ impl ValueAssertions {
             "to_contain" => to_contain,
             "to_be_empty" => to_be_empty,
             "to_match" => to_match,
-            val => exit_with_code(
-                exitcode::CONFIG,
-                Some(&format!("assertion not found: `{}`", val)),
-            ),
-        }
+            "to_match_schema" => to_match_schema,
+            val => {
+                return Err(CapterError::Assertion(format!(
+                    "assertion not found: `{}`",
+                    val
+                )))
+            }
+        };
+
+        Ok(assert_fn)
     }
 }
@@ -37,14 +37,8 @@ impl From<GitHubActionsPullRequestPayload> for RunSourcePullRequest {
             id: item.id,
             title: item.title,
             number: item.number,
-            head: match item.head {
-                Some(val) => Some(val.into()),
-                _ => None,
-            },
-            base: match item.base {
-                Some(val) => Some(val.into()),
-                _ => None,
-            },
+            head: item.head.map(|val| val.into()),
+            base: item.base.map(|val| val.into()),
         }
     }
 }
@@ -129,7 +123,7 @@ impl RunSource {
 fn read_github_workflow_event() -> Option<GitHubActionsEventPayload> {
     let path = env::var("GITHUB_EVENT_PATH").unwrap();
 
-    return match fs::read_to_string(&path) {
+    match fs::read_to_string(&path) {
         Ok(s) => {
             if let Ok(payload) = serde_json::from_str(&s) {
                 return Some(payload);
@@ -138,7 +132,7 @@ fn read_github_workflow_event() -> Option<GitHubActionsEventPayload> {
             None
         }
         _ => None,
-    };
+    }
 }
 
 #[cfg(test)]
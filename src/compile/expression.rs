@@ -0,0 +1,715 @@
+use crate::compile::functions;
+use serde_json::{json, Value};
+
+/// Evaluate an expression from inside a `${{ }}` template.
+///
+/// Paths like `user.name`, `people.0.name` or `get-user.response.body`
+/// are looked up in `data`, and missing values are null.
+///
+/// On top of that, expressions support:
+///
+/// - literals: `1`, `1.5`, `"text"`, `'text'`, `true`, `false`, `null`
+/// - arithmetic: `+ - * / %`, where `+` also joins strings and arrays
+/// - comparisons: `== != < <= > >=`, and `&& || !`
+/// - defaults: `env.PORT || 8080` uses the right side if the left is null or false
+/// - indexing: `body[0]`, `body[-1]` for the last item, `body["key"]`
+/// - filters: `name | upper`, see `FILTERS`
+/// - functions: `uuid()`, `random_int(1, 10)`, see `functions::FUNCTIONS`
+///
+/// Since `-` is allowed in names, subtraction needs spaces: `a - 1`.
+pub fn evaluate(expression: &str, data: &Value) -> Result<Value, String> {
+    evaluate_masked(expression, data).map(|(value, _)| value)
+}
+
+/// Paths under this key in the data are masked, like `["vars.token"]`.
+pub const MASKED_KEY: &str = "$masked";
+
+/// Same as `evaluate`, but also returns if the expression
+/// used any of the paths listed under `MASKED_KEY`.
+pub fn evaluate_masked(expression: &str, data: &Value) -> Result<(Value, bool), String> {
+    let tokens = tokenize(expression)?;
+
+    // an empty template is just empty
+    if tokens.is_empty() {
+        return Ok((Value::Null, false));
+    }
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        data,
+        is_masked: false,
+    };
+    let value = parser.expression()?;
+
+    match parser.peek() {
+        None => Ok((value, parser.is_masked)),
+        Some(token) => Err(format!("unexpected {}", token)),
+    }
+}
+
+/// The names an expression looks up in the data, like `user`
+/// for `user.name | upper`. Functions, filters and literals
+/// are left out.
+pub fn references(expression: &str) -> Result<Vec<String>, String> {
+    let tokens = tokenize(expression)?;
+    let mut names = vec![];
+
+    for (index, token) in tokens.iter().enumerate() {
+        let name = match token {
+            Token::Name(name) => name,
+            _ => continue,
+        };
+
+        let previous = index.checked_sub(1).and_then(|index| tokens.get(index));
+        let next = tokens.get(index + 1);
+        let is_path = !matches!(
+            previous,
+            Some(Token::Operator(".")) | Some(Token::Operator("|"))
+        );
+        let is_function = matches!(next, Some(Token::Operator("(")))
+            || (name == "fake" && matches!(next, Some(Token::Operator("."))));
+        let is_literal = ["true", "false", "null"].contains(&name.as_str());
+
+        if is_path && !is_function && !is_literal && !names.contains(name) {
+            names.push(name.to_owned());
+        }
+    }
+
+    Ok(names)
+}
+
+/// Filters that can be used like `value | name`.
+pub const FILTERS: &[&str] = &["length", "upper", "lower", "trim", "json", "base64"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    String(String),
+    Name(String),
+    Operator(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "`{}`", number),
+            Token::String(string) => write!(f, "\"{}\"", string),
+            Token::Name(name) => write!(f, "`{}`", name),
+            Token::Operator(operator) => write!(f, "`{}`", operator),
+        }
+    }
+}
+
+/// Longest operators first, so `==` is not read as `=` and `=`.
+const OPERATORS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "(", ")", "[", "]",
+    ".", "|", ",",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = vec![];
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+
+        if c.is_whitespace() {
+            index += 1;
+            continue;
+        }
+
+        // after a `.` numbers are array indexes, like `people.0.name`,
+        // and not floats like `0.5`
+        let after_dot = tokens.last() == Some(&Token::Operator("."));
+
+        if c.is_ascii_digit() {
+            let start = index;
+            while index < chars.len()
+                && (chars[index].is_ascii_digit()
+                    || (!after_dot
+                        && chars[index] == '.'
+                        && chars.get(index + 1).is_some_and(char::is_ascii_digit)))
+            {
+                index += 1;
+            }
+
+            let number: String = chars[start..index].iter().collect();
+            tokens.push(match after_dot {
+                true => Token::Name(number),
+                false => Token::Number(number.parse().map_err(|_| "invalid number")?),
+            });
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = index;
+            while index < chars.len() && is_name_char(&chars, index) {
+                index += 1;
+            }
+
+            tokens.push(Token::Name(chars[start..index].iter().collect()));
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            let mut string = String::new();
+            index += 1;
+
+            loop {
+                match chars.get(index) {
+                    Some(&end) if end == c => break,
+                    Some('\\') => {
+                        match chars.get(index + 1) {
+                            Some('n') => string.push('\n'),
+                            Some('t') => string.push('\t'),
+                            Some(escaped) => string.push(*escaped),
+                            None => return Err("unterminated string".to_string()),
+                        }
+                        index += 2;
+                    }
+                    Some(other) => {
+                        string.push(*other);
+                        index += 1;
+                    }
+                    None => return Err("unterminated string".to_string()),
+                }
+            }
+
+            index += 1;
+            tokens.push(Token::String(string));
+            continue;
+        }
+
+        let rest: String = chars[index..].iter().take(2).collect();
+        match OPERATORS
+            .iter()
+            .find(|operator| rest.starts_with(*operator))
+        {
+            Some(operator) => {
+                tokens.push(Token::Operator(operator));
+                index += operator.len();
+            }
+            None => return Err(format!("unexpected character `{}`", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Names can have `-` in them, like `get-user`, as long as the
+/// next character starts a word. `count-1` is still a subtraction.
+fn is_name_char(chars: &[char], index: usize) -> bool {
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_' || *c == '$';
+    let starts_word = |c: &char| c.is_alphabetic() || *c == '_';
+
+    match chars[index] {
+        '-' => chars.get(index + 1).is_some_and(starts_word),
+        c => is_word(&c),
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    data: &'a Value,
+    is_masked: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Move past `operator` if it's next.
+    fn accept(&mut self, operator: &str) -> bool {
+        match self.peek() {
+            Some(Token::Operator(next)) if *next == operator => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, operator: &str) -> Result<(), String> {
+        match self.accept(operator) {
+            true => Ok(()),
+            false => Err(format!("expected `{}`", operator)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Value, String> {
+        let mut value = self.and()?;
+        while self.accept("||") {
+            let right = self.and()?;
+            if !is_truthy(&value) {
+                value = right;
+            }
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<Value, String> {
+        let mut value = self.comparison()?;
+        while self.accept("&&") {
+            let right = self.comparison()?;
+            if is_truthy(&value) {
+                value = right;
+            }
+        }
+        Ok(value)
+    }
+
+    fn comparison(&mut self) -> Result<Value, String> {
+        let left = self.sum()?;
+
+        for operator in ["==", "!=", "<=", ">=", "<", ">"] {
+            if self.accept(operator) {
+                let right = self.sum()?;
+                return compare(operator, &left, &right).map(Value::Bool);
+            }
+        }
+
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Value, String> {
+        let mut value = self.product()?;
+        loop {
+            if self.accept("+") {
+                let right = self.product()?;
+                value = add(value, right)?;
+            } else if self.accept("-") {
+                let right = self.product()?;
+                value = arithmetic("-", &value, &right)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Value, String> {
+        let mut value = self.unary()?;
+        loop {
+            let operator = match ["*", "/", "%"]
+                .iter()
+                .find(|operator| self.accept(operator))
+            {
+                Some(operator) => *operator,
+                None => return Ok(value),
+            };
+            let right = self.unary()?;
+            value = arithmetic(operator, &value, &right)?;
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, String> {
+        if self.accept("!") {
+            return Ok(Value::Bool(!is_truthy(&self.unary()?)));
+        }
+
+        if self.accept("-") {
+            let value = self.unary()?;
+            return arithmetic("-", &json!(0), &value);
+        }
+
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Value, String> {
+        // the path to the value in the data, as long as we know it,
+        // which is checked against the masked paths once we're done
+        let (mut value, mut path) = self.primary()?;
+
+        loop {
+            if self.accept(".") {
+                let name = match self.next() {
+                    Some(Token::Name(name)) => name,
+                    _ => return Err("expected a name after `.`".to_string()),
+                };
+                path = path.map(|path| format!("{}.{}", path, name));
+                value = get(&value, &Value::String(name));
+            } else if self.accept("[") {
+                let index = self.expression()?;
+                self.expect("]")?;
+                path = path.map(|path| format!("{}.{}", path, to_text(&index)));
+                value = get(&value, &index);
+            } else if self.accept("|") {
+                self.check_masked(&path);
+                path = None;
+                value = match self.next() {
+                    Some(Token::Name(filter)) => apply_filter(&filter, value)?,
+                    _ => return Err("expected a filter after `|`".to_string()),
+                };
+            } else {
+                self.check_masked(&path);
+                return Ok(value);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<(Value, Option<String>), String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok((to_number(number), None)),
+            Some(Token::String(string)) => Ok((Value::String(string), None)),
+            Some(Token::Name(name)) => {
+                if let Some(function) = self.function_name(&name) {
+                    return Ok((self.call(&function)?, None));
+                }
+
+                Ok(match name.as_str() {
+                    "true" => (Value::Bool(true), None),
+                    "false" => (Value::Bool(false), None),
+                    "null" => (Value::Null, None),
+                    name => (
+                        get(self.data, &Value::String(name.to_string())),
+                        Some(name.to_string()),
+                    ),
+                })
+            }
+            Some(Token::Operator("(")) => {
+                let value = self.expression()?;
+                self.expect(")")?;
+                Ok((value, None))
+            }
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    /// The value is masked if the path is masked, is inside a masked
+    /// path, or if it has a masked path inside of it.
+    fn check_masked(&mut self, path: &Option<String>) {
+        let path = match path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let masked_paths = self.data[MASKED_KEY].as_array().into_iter().flatten();
+        for masked in masked_paths.filter_map(Value::as_str) {
+            if path == masked
+                || path.starts_with(&format!("{}.", masked))
+                || masked.starts_with(&format!("{}.", path))
+            {
+                self.is_masked = true;
+            }
+        }
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// If `name` is followed by `(` it's a function, like `uuid()`.
+    /// The `fake` functions are namespaced, like `fake.email()`.
+    fn function_name(&mut self, name: &str) -> Option<String> {
+        match self.tokens.get(self.position..self.position + 3) {
+            Some([Token::Operator("."), Token::Name(function), Token::Operator("(")])
+                if name == "fake" =>
+            {
+                let function = format!("fake.{}", function);
+                self.position += 2;
+                Some(function)
+            }
+            _ => match self.peek() {
+                Some(Token::Operator("(")) => Some(name.to_string()),
+                _ => None,
+            },
+        }
+    }
+
+    fn call(&mut self, function: &str) -> Result<Value, String> {
+        self.expect("(")?;
+
+        let mut args = vec![];
+        if !self.accept(")") {
+            loop {
+                args.push(self.expression()?);
+                if self.accept(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+
+        functions::call(function, &args)
+    }
+}
+
+/// Get a property from an object, or an item from an array.
+/// Negative indexes count from the end of the array.
+fn get(value: &Value, key: &Value) -> Value {
+    match (value, key) {
+        (Value::Object(object), Value::String(key)) => {
+            object.get(key).cloned().unwrap_or(Value::Null)
+        }
+        (Value::Object(object), Value::Number(key)) => {
+            object.get(&key.to_string()).cloned().unwrap_or(Value::Null)
+        }
+        (Value::Array(array), key) => {
+            let index = match key {
+                Value::Number(number) => number.as_f64(),
+                Value::String(string) => string.parse().ok(),
+                _ => None,
+            };
+
+            match index {
+                Some(index) if index < 0.0 => {
+                    let index = array.len() as f64 + index;
+                    match index >= 0.0 {
+                        true => array.get(index as usize).cloned(),
+                        false => None,
+                    }
+                }
+                Some(index) => array.get(index as usize).cloned(),
+                None => None,
+            }
+            .unwrap_or(Value::Null)
+        }
+        _ => Value::Null,
+    }
+}
+
+fn add(left: Value, right: Value) -> Result<Value, String> {
+    match (left, right) {
+        (Value::Array(mut left), Value::Array(mut right)) => {
+            left.append(&mut right);
+            Ok(Value::Array(left))
+        }
+        (left @ Value::Number(_), right @ Value::Number(_)) => arithmetic("+", &left, &right),
+        // anything else with a string is joined as text
+        (left, right) if left.is_string() || right.is_string() => Ok(Value::String(format!(
+            "{}{}",
+            to_text(&left),
+            to_text(&right)
+        ))),
+        (left, right) => Err(format!("can't add {} and {}", left, right)),
+    }
+}
+
+fn arithmetic(operator: &str, left: &Value, right: &Value) -> Result<Value, String> {
+    let (a, b) = match (left.as_f64(), right.as_f64()) {
+        (Some(a), Some(b)) => (a, b),
+        _ => {
+            return Err(format!(
+                "can't use `{}` on {} and {}",
+                operator, left, right
+            ))
+        }
+    };
+
+    let result = match operator {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" if b == 0.0 => return Err("division by zero".to_string()),
+        "/" => a / b,
+        "%" if b == 0.0 => return Err("division by zero".to_string()),
+        "%" => a % b,
+        _ => unreachable!(),
+    };
+
+    Ok(to_number(result))
+}
+
+fn compare(operator: &str, left: &Value, right: &Value) -> Result<bool, String> {
+    let ordering = match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+
+    match (operator, ordering) {
+        ("==", Some(ordering)) => Ok(ordering.is_eq()),
+        ("!=", Some(ordering)) => Ok(ordering.is_ne()),
+        ("==", None) => Ok(left == right),
+        ("!=", None) => Ok(left != right),
+        ("<", Some(ordering)) => Ok(ordering.is_lt()),
+        ("<=", Some(ordering)) => Ok(ordering.is_le()),
+        (">", Some(ordering)) => Ok(ordering.is_gt()),
+        (">=", Some(ordering)) => Ok(ordering.is_ge()),
+        _ => Err(format!("can't compare {} and {}", left, right)),
+    }
+}
+
+fn apply_filter(filter: &str, value: Value) -> Result<Value, String> {
+    match filter {
+        "length" => match &value {
+            Value::String(string) => Ok(json!(string.chars().count())),
+            Value::Array(array) => Ok(json!(array.len())),
+            Value::Object(object) => Ok(json!(object.len())),
+            _ => Err(format!("can't get the length of {}", value)),
+        },
+        "upper" => Ok(Value::String(to_text(&value).to_uppercase())),
+        "lower" => Ok(Value::String(to_text(&value).to_lowercase())),
+        "trim" => Ok(Value::String(to_text(&value).trim().to_string())),
+        "json" => Ok(Value::String(value.to_string())),
+        "base64" => Ok(Value::String(base64::encode(to_text(&value)))),
+        filter => Err(format!(
+            "unknown filter `{}`, use one of: {}",
+            filter,
+            FILTERS.join(", ")
+        )),
+    }
+}
+
+/// Null and false are the only falsy values, so `0 || 1` is 0.
+pub fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+/// Strings are used as they are, everything else as JSON.
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.to_owned(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Keep whole numbers as integers, so `1 + 1` is `2` and not `2.0`.
+fn to_number(number: f64) -> Value {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        return json!(number as i64);
+    }
+
+    json!(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str) -> Value {
+        let data = json!({
+            "user": { "name": "Test McTest", "age": 30 },
+            "people": [{ "name": "a" }, { "name": "b" }, { "name": "c" }],
+            "get-user": { "response": { "status": 200 } },
+            "env": { "PORT": "3000", "EMPTY": "" },
+            "count": 3,
+        });
+        evaluate(expression, &data).unwrap()
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(eval("user.name"), json!("Test McTest"));
+        assert_eq!(eval("people.1.name"), json!("b"));
+        assert_eq!(eval("people[0].name"), json!("a"));
+        assert_eq!(eval("people[-1].name"), json!("c"));
+        assert_eq!(eval("people[-4]"), Value::Null);
+        assert_eq!(eval("user['name']"), json!("Test McTest"));
+        assert_eq!(eval("get-user.response.status"), json!(200));
+        assert_eq!(eval("missing.path"), Value::Null);
+        assert_eq!(eval(""), Value::Null);
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(eval("user.age + 1"), json!(31));
+        assert_eq!(eval("user.age - 1"), json!(29));
+        assert_eq!(eval("user.age / 4"), json!(7.5));
+        assert_eq!(eval("2 + 3 * 4"), json!(14));
+        assert_eq!(eval("(2 + 3) * 4"), json!(20));
+        assert_eq!(eval("10 % 4"), json!(2));
+        assert_eq!(eval("-user.age"), json!(-30));
+        assert_eq!(eval("count-1"), json!(2));
+        assert_eq!(eval("user.age-1"), json!(29));
+        assert_eq!(eval("'id-' + user.age"), json!("id-30"));
+        assert_eq!(
+            eval("user.age >= 18 && user.name == \"Test McTest\""),
+            json!(true)
+        );
+        assert_eq!(eval("user.age == 30.0"), json!(true));
+        assert_eq!(eval("!(user.age < 18)"), json!(true));
+        assert_eq!(eval("people | length > 2"), json!(true));
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(eval("env.MISSING || 8080"), json!(8080));
+        assert_eq!(eval("env.PORT || 8080"), json!("3000"));
+        assert_eq!(eval("env.EMPTY || 'default'"), json!(""));
+        assert_eq!(eval("env.MISSING || env.OTHER || 'last'"), json!("last"));
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(eval("user.name | upper"), json!("TEST MCTEST"));
+        assert_eq!(eval("user.name | lower | length"), json!(11));
+        assert_eq!(eval("people | length"), json!(3));
+        assert_eq!(eval("people[0] | json"), json!("{\"name\":\"a\"}"));
+        assert_eq!(eval("'user:pass' | base64"), json!("dXNlcjpwYXNz"));
+        assert_eq!(eval("'  a ' | trim"), json!("a"));
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(eval("uuid() | length"), json!(36));
+        assert_eq!(eval("random_int(5, 5) * 2"), json!(10));
+        assert_eq!(eval("random_string(user.age) | length"), json!(30));
+        assert_eq!(eval("('id-' + timestamp()) | length > 3"), json!(true));
+        assert!(eval("fake.email()").as_str().unwrap().contains('@'));
+        assert!(eval("fake.name()").as_str().unwrap().contains(' '));
+    }
+
+    #[test]
+    fn test_masked() {
+        let data = json!({
+            "vars": { "token": "secret", "id": 1 },
+            "$masked": ["vars.token"],
+        });
+        let is_masked = |expression| evaluate_masked(expression, &data).unwrap().1;
+
+        assert!(is_masked("vars.token"));
+        assert!(is_masked("'Bearer ' + (vars.token | upper)"));
+        assert!(is_masked("vars['token']"));
+        assert!(is_masked("vars"));
+        assert!(!is_masked("vars.id"));
+        assert!(!is_masked("vars.id + 1"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let data = json!({});
+        assert_eq!(
+            evaluate("path invalid", &data),
+            Err("unexpected `invalid`".to_string())
+        );
+        assert_eq!(
+            evaluate("a | nope", &data),
+            Err(
+                "unknown filter `nope`, use one of: length, upper, lower, trim, json, base64"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            evaluate("'a' * 2", &data),
+            Err("can't use `*` on \"a\" and 2".to_string())
+        );
+        assert_eq!(evaluate("(1", &data), Err("expected `)`".to_string()));
+        assert_eq!(
+            evaluate("'open", &data),
+            Err("unterminated string".to_string())
+        );
+    }
+
+    #[test]
+    fn test_references() {
+        assert_eq!(
+            references("get-user.response.body[index].name | upper").unwrap(),
+            vec!["get-user", "index"]
+        );
+        assert_eq!(
+            references("fake.email() + uuid() + env.URL || null").unwrap(),
+            vec!["env"]
+        );
+        assert!(references("'open").is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use chrono::Utc;
+use rand::{distributions::Alphanumeric, rngs::StdRng, Rng, SeedableRng};
+use serde_json::{json, Value};
+use std::{cell::RefCell, fmt::Write};
+
+thread_local! {
+    // every thread running a workflow has its own generator,
+    // so the values don't depend on how the workflows are scheduled
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Functions that can be called inside a `${{ }}` template.
+pub const FUNCTIONS: &[&str] = &[
+    "uuid",
+    "now",
+    "timestamp",
+    "random_int",
+    "random_string",
+    "fake.email",
+    "fake.name",
+    "fake.first_name",
+    "fake.last_name",
+    "fake.username",
+];
+
+const FIRST_NAMES: &[&str] = &[
+    "Alex", "Ava", "Ben", "Chloe", "Daniel", "Emma", "Finn", "Grace", "Hugo", "Isla", "Jack",
+    "Lea", "Leo", "Maya", "Noah", "Olivia", "Oscar", "Sara", "Theo", "Zoe",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Andersson",
+    "Brown",
+    "Clarke",
+    "Davis",
+    "Evans",
+    "Garcia",
+    "Hughes",
+    "Johnson",
+    "Kim",
+    "Lopez",
+    "Martin",
+    "Miller",
+    "Nguyen",
+    "Patel",
+    "Quinn",
+    "Smith",
+    "Taylor",
+    "Walker",
+    "Wilson",
+    "Young",
+];
+
+/// Generate a new seed for a run.
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
+/// Seed the generator used by the functions on this thread.
+///
+/// The `key` is mixed in to the seed, so two workflows
+/// run with the same seed don't get the same values.
+///
+/// `now()` and `timestamp()` are the time of the call, so they
+/// aren't repeated by a seed. They don't use the generator, so
+/// the other values are the same with or without them.
+pub fn set_seed(seed: u64, key: &str) {
+    // FNV-1a, which is stable between builds unlike the std hasher
+    let hash = key.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed ^ hash));
+}
+
+/// Call the function `name` with the arguments `args`.
+pub fn call(name: &str, args: &[Value]) -> Result<Value, String> {
+    match (name, args) {
+        ("uuid", []) => Ok(json!(uuid())),
+        ("now", []) => Ok(json!(Utc::now().to_rfc3339())),
+        ("now", [Value::String(format)]) => {
+            let mut date = String::new();
+            match write!(date, "{}", Utc::now().format(format)) {
+                Ok(_) => Ok(json!(date)),
+                Err(_) => Err(format!("invalid date format `{}`", format)),
+            }
+        }
+        ("timestamp", []) => Ok(json!(Utc::now().timestamp())),
+        ("random_int", [min, max]) => match (min.as_i64(), max.as_i64()) {
+            (Some(min), Some(max)) if min <= max => {
+                Ok(json!(RNG.with(|rng| rng.borrow_mut().gen_range(min..=max))))
+            }
+            _ => Err(format!(
+                "`random_int` needs a min and max number, got {} and {}",
+                min, max
+            )),
+        },
+        ("random_string", []) => Ok(json!(random_string(16))),
+        ("random_string", [length]) => match length.as_u64() {
+            Some(length) => Ok(json!(random_string(length as usize))),
+            None => Err(format!("`random_string` needs a length, got {}", length)),
+        },
+        ("fake.first_name", []) => Ok(json!(pick(FIRST_NAMES))),
+        ("fake.last_name", []) => Ok(json!(pick(LAST_NAMES))),
+        ("fake.name", []) => Ok(json!(format!("{} {}", pick(FIRST_NAMES), pick(LAST_NAMES)))),
+        ("fake.username", []) => Ok(json!(username())),
+        ("fake.email", []) => Ok(json!(format!("{}@example.com", username()))),
+        (name, _) if FUNCTIONS.contains(&name) => Err(format!("wrong arguments to `{}`", name)),
+        (name, _) => Err(format!(
+            "unknown function `{}`, use one of: {}",
+            name,
+            FUNCTIONS.join(", ")
+        )),
+    }
+}
+
+/// A version 4 UUID, from the seeded generator.
+fn uuid() -> String {
+    let mut bytes: [u8; 16] = RNG.with(|rng| rng.borrow_mut().gen());
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn random_string(length: usize) -> String {
+    RNG.with(|rng| {
+        let mut rng = rng.borrow_mut();
+        (0..length)
+            .map(|_| char::from(rng.sample(Alphanumeric)))
+            .collect()
+    })
+}
+
+fn pick(list: &[&'static str]) -> &'static str {
+    RNG.with(|rng| list[rng.borrow_mut().gen_range(0..list.len())])
+}
+
+/// Random numbers at the end keep the usernames unique between runs.
+fn username() -> String {
+    let number: u32 = RNG.with(|rng| rng.borrow_mut().gen_range(1000..10000));
+    format!(
+        "{}.{}{}",
+        pick(FIRST_NAMES).to_lowercase(),
+        pick(LAST_NAMES).to_lowercase(),
+        number
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    #[test]
+    fn test_seed() {
+        set_seed(42, "workflow");
+        let first = (call("uuid", &[]), call("fake.email", &[]));
+        set_seed(42, "workflow");
+        let second = (call("uuid", &[]), call("fake.email", &[]));
+        assert_eq!(first, second);
+
+        set_seed(42, "other workflow");
+        let other = (call("uuid", &[]), call("fake.email", &[]));
+        assert_ne!(first, other);
+
+        // time isn't seeded, and doesn't change the values after it
+        set_seed(42, "workflow");
+        call("now", &[]).unwrap();
+        call("timestamp", &[]).unwrap();
+        let with_time = (call("uuid", &[]), call("fake.email", &[]));
+        assert_eq!(first, with_time);
+    }
+
+    #[test]
+    fn test_functions() {
+        let uuid = call("uuid", &[]).unwrap();
+        let pattern = r"^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$";
+        assert!(Regex::new(pattern)
+            .unwrap()
+            .is_match(uuid.as_str().unwrap()));
+
+        let number = call("random_int", &[json!(1), json!(3)]).unwrap();
+        assert!((1..=3).contains(&number.as_i64().unwrap()));
+
+        let string = call("random_string", &[json!(12)]).unwrap();
+        assert_eq!(string.as_str().unwrap().len(), 12);
+
+        let date = call("now", &[json!("%Y")]).unwrap();
+        assert_eq!(date, json!(Utc::now().format("%Y").to_string()));
+
+        let email = call("fake.email", &[]).unwrap();
+        assert!(email.as_str().unwrap().ends_with("@example.com"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            call("random_int", &[json!(3), json!(1)]),
+            Err("`random_int` needs a min and max number, got 3 and 1".to_string())
+        );
+        assert_eq!(
+            call("uuid", &[json!(1)]),
+            Err("wrong arguments to `uuid`".to_string())
+        );
+        assert_eq!(
+            call("now", &[json!("%Q")]),
+            Err("invalid date format `%Q`".to_string())
+        );
+        assert!(call("nope", &[])
+            .unwrap_err()
+            .starts_with("unknown function `nope`"));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod expression;
+pub mod functions;
 pub mod string;
 pub mod value;
 
@@ -1,4 +1,4 @@
-use crate::utils::exit_with_code;
+use crate::{compile::expression::evaluate_masked, error::CapterError};
 use regex::Regex;
 use serde::Serialize;
 use serde_json::Value;
@@ -17,14 +17,17 @@ pub struct CompiledString {
 /// You can use a mask to hide the value: `My name is {{ mask user.name }}`.
 /// This will compile in to `My name is ****`.
 ///
+/// The inside of a tag is an expression, so things like
+/// `{{ user.name | upper }}` or `{{ env.PORT || 8080 }}` work too.
+///
 /// You always get back two version of the string, one masked under `.masked`,
-/// and the raw one under `.raw`.
-pub fn compile_string(value: &str, data: &Value) -> CompiledString {
+/// and the raw one under `.raw`. Returns an error if a tag can't be evaluated.
+pub fn compile_string(value: &str, data: &Value) -> Result<CompiledString, CapterError> {
     let mut raw_value = value.to_string();
     let mut masked_value = value.to_string();
 
     let search = Regex::new(r"\$\{\{(.*?)}}").unwrap();
-    let hits = search.captures_iter(&value);
+    let hits = search.captures_iter(value);
 
     for hit in hits {
         // nothing to do here becuase it doesn't have any value
@@ -35,31 +38,23 @@ pub fn compile_string(value: &str, data: &Value) -> CompiledString {
         let original_value = &hit[0];
         let inside_value = hit[1].trim();
 
-        // grab parts of inside_value to check if there's a mask
-        let parts: Vec<&str> = inside_value.split(" ").collect();
+        // a leading `mask` hides the value in the masked string
+        let (expression, has_mask) = match inside_value.strip_prefix("mask ") {
+            Some(expression) => (expression, true),
+            None => (inside_value, false),
+        };
 
-        // figure out pointer and if we should mask value
-        let (pointer, has_mask) = match parts.len() {
-            1 => {
-                let pointer = format!("/{}", parts[0].replace(".", "/"));
-                (pointer, false)
-            }
-            _ => {
-                if parts.len() == 2 {
-                    let pointer = format!("/{}", parts[1].replace(".", "/"));
-                    (pointer, true)
-                } else {
-                    exit_with_code(
-                        exitcode::CONFIG,
-                        Some(&format!("invalid template: `{}`", original_value)),
-                    );
-                }
+        // values from masked variables are masked too
+        let (data, has_mask) = match evaluate_masked(expression, data) {
+            Ok((data, uses_masked)) => (data, has_mask || uses_masked),
+            Err(error) => {
+                return Err(CapterError::Template(format!(
+                    "invalid template `{}`: {}",
+                    original_value, error
+                )))
             }
         };
 
-        // grab data at pointer
-        let data = data.pointer(&pointer).unwrap_or(&Value::Null);
-
         // handle case where no data was found
         if data.is_null() {
             raw_value = raw_value.replace(original_value, "");
@@ -68,7 +63,7 @@ pub fn compile_string(value: &str, data: &Value) -> CompiledString {
         }
 
         // data needs to be a string
-        let new_raw_value = match data {
+        let new_raw_value = match &data {
             Value::String(string) => string.to_string(),
             value => {
                 format!("{}", value)
@@ -83,10 +78,10 @@ pub fn compile_string(value: &str, data: &Value) -> CompiledString {
         masked_value = masked_value.replace(original_value, &new_masked_value);
     }
 
-    CompiledString {
+    Ok(CompiledString {
         raw: raw_value,
         masked: masked_value,
-    }
+    })
 }
 
 #[cfg(test)]
@@ -98,7 +93,7 @@ mod tests {
     fn test_plain() {
         let data = json!({ "first_name": "Test", "last_name": "McTest" });
         let test_string = "I am ${{ first_name }} ${{ mask last_name }}";
-        let output = compile_string(test_string, &data);
+        let output = compile_string(test_string, &data).unwrap();
         assert_eq!(output.raw, "I am Test McTest");
         assert_eq!(output.masked, "I am Test ****");
     }
@@ -107,11 +102,18 @@ mod tests {
     fn test_masking() {
         let data = json!({ "name": "Test McTest" });
         let test_string = "I am ${{ mask name }}";
-        let output = compile_string(test_string, &data);
+        let output = compile_string(test_string, &data).unwrap();
         assert_eq!(output.raw, "I am Test McTest");
         assert_eq!(output.masked, "I am ****");
     }
 
+    #[test]
+    fn test_subtraction() {
+        let data = json!({ "a": 5, "get-user": { "id": 1 } });
+        let output = compile_string("${{ a-1 }} ${{ get-user.id }}", &data).unwrap();
+        assert_eq!(output.raw, "4 1");
+    }
+
     #[test]
     fn test_nested_data() {
         let data = json!({
@@ -120,7 +122,7 @@ mod tests {
             }]
         });
         let test_string = "I am ${{ mask people.0.name }}";
-        let output = compile_string(test_string, &data);
+        let output = compile_string(test_string, &data).unwrap();
         assert_eq!(output.raw, "I am Test McTest");
         assert_eq!(output.masked, "I am ****");
     }
@@ -131,7 +133,7 @@ mod tests {
             "test": "test_string"
         });
         let test_string = "${{ mask test }}";
-        let output = compile_string(test_string, &data);
+        let output = compile_string(test_string, &data).unwrap();
         assert_eq!(output.raw, "test_string");
         assert_eq!(output.masked, "****");
     }
@@ -142,20 +144,24 @@ mod tests {
             "test": "test_string"
         });
         let test_string = "${{ }}";
-        let output = compile_string(test_string, &data);
+        let output = compile_string(test_string, &data).unwrap();
 
         assert_eq!(output.raw, "");
         assert_eq!(output.masked, "");
     }
 
     #[test]
-    #[should_panic]
     fn test_invalid_format() {
         let data = json!({
             "test": "test_string"
         });
         let test_string = "${{ mask path invalid }}";
-        compile_string(test_string, &data);
+        let error = compile_string(test_string, &data).unwrap_err();
+
+        assert!(matches!(error, CapterError::Template(_)));
+        assert!(error
+            .to_string()
+            .starts_with("invalid template `${{ mask path invalid }}`"));
     }
 
     #[test]
@@ -164,12 +170,41 @@ mod tests {
             "test": "test_string"
         });
         let test_string = "Path [${{ invalid.path }}] is invalid";
-        let output = compile_string(test_string, &data);
+        let output = compile_string(test_string, &data).unwrap();
 
         assert_eq!(output.raw, "Path [] is invalid");
         assert_eq!(output.masked, "Path [] is invalid");
     }
 
+    #[test]
+    fn test_expressions() {
+        let data = json!({
+            "env": { "TOKEN": "secret" },
+            "items": [1, 2, 3]
+        });
+        let test_string =
+            "${{ items | length }} items, last is ${{ items[-1] }}, next is ${{ items[-1] + 1 }}";
+        let output = compile_string(test_string, &data).unwrap();
+        assert_eq!(output.raw, "3 items, last is 3, next is 4");
+
+        let test_string = "Bearer ${{ mask env.TOKEN | upper }}, port ${{ env.PORT || 8080 }}";
+        let output = compile_string(test_string, &data).unwrap();
+        assert_eq!(output.raw, "Bearer SECRET, port 8080");
+        assert_eq!(output.masked, "Bearer ****, port 8080");
+    }
+
+    #[test]
+    fn test_masked_vars() {
+        let data = json!({
+            "vars": { "token": "secret" },
+            "$masked": ["vars.token"]
+        });
+        let test_string = "Bearer ${{ vars.token }}";
+        let output = compile_string(test_string, &data).unwrap();
+        assert_eq!(output.raw, "Bearer secret");
+        assert_eq!(output.masked, "Bearer ****");
+    }
+
     #[test]
     fn test_nested_value() {
         let data = json!({
@@ -179,7 +214,7 @@ mod tests {
             }
         });
         let test_string = "nested: ${{ mask nested }}";
-        let output = compile_string(test_string, &data);
+        let output = compile_string(test_string, &data).unwrap();
 
         assert_eq!(output.raw, "nested: {\"a\":\"b\",\"c\":[\"d\",\"e\"]}");
         assert_eq!(output.masked, "nested: ****");
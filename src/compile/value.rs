@@ -1,4 +1,4 @@
-use crate::compile::compile_string;
+use crate::{compile::compile_string, error::CapterError};
 use serde::Serialize;
 use serde_yaml::Value;
 
@@ -9,58 +9,74 @@ pub struct CompiledValue {
 }
 
 /// Compiles a serde_json Value.
-pub fn compile_value(value: Option<serde_yaml::Value>, data: &serde_json::Value) -> CompiledValue {
+pub fn compile_value(
+    value: Option<serde_yaml::Value>,
+    data: &serde_json::Value,
+) -> Result<CompiledValue, CapterError> {
     if let Some(value) = value {
-        let raw = deep_keys(&value, &data, false);
-        let masked = deep_keys(&value, &data, true);
-
-        return CompiledValue { raw, masked };
+        return deep_keys(&value, data);
     }
 
-    return CompiledValue {
+    Ok(CompiledValue {
         raw: Value::Null,
         masked: Value::Null,
-    };
+    })
 }
 
+/// Every string is only compiled once, so values from functions
+/// like `uuid()` are the same in the raw and the masked value.
 fn deep_keys(
     value: &serde_yaml::Value,
     data: &serde_json::Value,
-    masked: bool,
-) -> serde_yaml::Value {
+) -> Result<CompiledValue, CapterError> {
     match value {
         Value::String(val) => {
-            let result = compile_string(val, data);
-
-            let val = match masked {
-                true => result.masked,
-                false => result.raw,
-            };
-
-            // this will try to parse the value as a number or boolean
-            // and if that fails we'll just return it as a string below
-            let yaml_value = serde_yaml::from_str(&val);
-            if let Ok(val) = yaml_value {
-                return val;
-            }
+            let result = compile_string(val, data)?;
 
-            return serde_yaml::Value::String(val);
+            Ok(CompiledValue {
+                raw: parse_scalar(result.raw),
+                masked: parse_scalar(result.masked),
+            })
         }
         Value::Mapping(map) => {
-            let mut new_map = map.clone();
+            let mut raw = map.clone();
+            let mut masked = map.clone();
             for (k, v) in map.iter() {
-                new_map[k] = deep_keys(v, data, masked);
+                let compiled = deep_keys(v, data)?;
+                raw[k] = compiled.raw;
+                masked[k] = compiled.masked;
             }
-            return new_map.into();
+            Ok(CompiledValue {
+                raw: raw.into(),
+                masked: masked.into(),
+            })
         }
         Value::Sequence(vec) => {
-            let mut new_vec = vec![];
+            let mut raw = vec![];
+            let mut masked = vec![];
             for v in vec {
-                new_vec.push(deep_keys(v, data, masked));
+                let compiled = deep_keys(v, data)?;
+                raw.push(compiled.raw);
+                masked.push(compiled.masked);
             }
-            return new_vec.into();
+            Ok(CompiledValue {
+                raw: raw.into(),
+                masked: masked.into(),
+            })
         }
-        _ => value.clone(),
+        _ => Ok(CompiledValue {
+            raw: value.clone(),
+            masked: value.clone(),
+        }),
+    }
+}
+
+/// This will try to parse the value as a number or boolean
+/// and if that fails we'll just return it as a string.
+fn parse_scalar(val: String) -> serde_yaml::Value {
+    match serde_yaml::from_str(&val) {
+        Ok(val) => val,
+        Err(_) => serde_yaml::Value::String(val),
     }
 }
 
@@ -79,7 +95,7 @@ mod tests {
         "};
         let test_value: Value = from_str(yaml).unwrap();
         let data = json!({ "user": { "name":  "Test McTest" }});
-        let output = compile_value(Some(test_value), &data);
+        let output = compile_value(Some(test_value), &data).unwrap();
 
         assert_eq!(output.raw["name"], "Test McTest");
         assert_eq!(output.masked["name"], "Test McTest");
@@ -93,7 +109,7 @@ mod tests {
         "};
         let test_value: Value = from_str(yaml).unwrap();
         let data = json!({ "user": { "name":  "Test McTest" }});
-        let output = compile_value(Some(test_value), &data);
+        let output = compile_value(Some(test_value), &data).unwrap();
 
         assert_eq!(output.raw["name"], "Test McTest");
         assert_eq!(output.masked["name"], "****");
@@ -113,7 +129,7 @@ mod tests {
         "};
         let test_value: Value = from_str(yaml).unwrap();
         let data = json!({ "user": { "name":  "Test McTest", "age": 30 }});
-        let output = compile_value(Some(test_value), &data);
+        let output = compile_value(Some(test_value), &data).unwrap();
 
         assert_eq!(output.raw["array"][0], "Test McTest");
         assert_eq!(output.masked["array"][0], "****");
@@ -141,7 +157,7 @@ mod tests {
         "};
         let test_value: Value = from_str(yaml).unwrap();
         let data = json!({ "string": "test_string", "int": 5, "float": 1.5, "boolean": true });
-        let output = compile_value(Some(test_value), &data);
+        let output = compile_value(Some(test_value), &data).unwrap();
 
         assert_eq!(output.raw["string"], "test_string");
         assert_eq!(output.raw["int"], 5);
@@ -157,7 +173,7 @@ mod tests {
     #[test]
     fn test_empty_value() {
         let data = serde_json::Value::Null;
-        let output = compile_value(None, &data);
+        let output = compile_value(None, &data).unwrap();
 
         assert_eq!(output.raw["name"], serde_yaml::Value::Null);
         assert_eq!(output.masked["name"], serde_yaml::Value::Null);
@@ -171,7 +187,7 @@ mod tests {
         "};
         let test_value: Value = from_str(yaml).unwrap();
         let data = json!({ "nested": { "a": "b", "c": ["d", "e"] } });
-        let output = compile_value(Some(test_value), &data);
+        let output = compile_value(Some(test_value), &data).unwrap();
 
         let expected_output = indoc! {"
             ---
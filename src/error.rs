@@ -0,0 +1,52 @@
+use crate::validate::Diagnostic;
+use std::fmt;
+
+/// Something that stops a workflow from running, like a template
+/// or an assertion that can't be parsed.
+///
+/// This is not the same as a failed assertion. The workflow is
+/// reported as errored, and the other workflows still run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapterError {
+    /// A workflow, or a file it uses, that can't be read or parsed.
+    Config(String),
+    /// Mistakes found in a workflow before it runs, with where they are.
+    Invalid(Vec<Diagnostic>),
+    /// A template, like `${{ user.id }}`, that can't be evaluated.
+    Template(String),
+    /// An assertion that can't be parsed, or doesn't exist.
+    Assertion(String),
+    /// A step that can't be made in to a request, like one without a url.
+    Request(String),
+    /// Any of the above, in the step with this name.
+    Step(String, Box<CapterError>),
+}
+
+impl CapterError {
+    /// Add the name of the step the error happened in.
+    pub fn in_step(self, name: &str) -> CapterError {
+        match self {
+            // these already say where they are
+            CapterError::Invalid(_) | CapterError::Step(..) => self,
+            error => CapterError::Step(name.to_string(), Box::new(error)),
+        }
+    }
+}
+
+impl fmt::Display for CapterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CapterError::Config(message)
+            | CapterError::Template(message)
+            | CapterError::Assertion(message)
+            | CapterError::Request(message) => write!(f, "{}", message),
+            CapterError::Invalid(diagnostics) => {
+                let lines: Vec<String> = diagnostics.iter().map(Diagnostic::to_string).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            CapterError::Step(name, error) => write!(f, "in step `{}`: {}", name, error),
+        }
+    }
+}
+
+impl std::error::Error for CapterError {}
@@ -0,0 +1,24 @@
+pub mod postman;
+
+pub use postman::import_postman;
+
+use crate::{utils::unique_slugs, workflow::WorkflowConfig};
+
+/// Workflows imported from another tool, and everything
+/// we couldn't translate.
+pub struct ImportedCollection {
+    pub workflows: Vec<WorkflowConfig>,
+    pub warnings: Vec<String>,
+}
+
+impl ImportedCollection {
+    /// The file for every workflow, in `.capter`. Folders with
+    /// the same name get their own file, like `users-2.yml`.
+    pub fn paths(&self) -> Vec<String> {
+        let names: Vec<&str> = self.workflows.iter().map(|w| w.name.as_str()).collect();
+        unique_slugs(&names)
+            .iter()
+            .map(|slug| format!(".capter/{}.yml", slug))
+            .collect()
+    }
+}
@@ -0,0 +1,599 @@
+use crate::{
+    import::ImportedCollection,
+    workflow::{
+        WorkflowConfig, WorkflowConfigAssertion, WorkflowConfigGraphQlConfig, WorkflowConfigStep,
+    },
+};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Convert a Postman collection (v2.0 or v2.1) in to workflows.
+///
+/// Every top level folder becomes a workflow, and every request in it
+/// becomes a step. Requests that are not in a folder end up in a workflow
+/// named after the collection. Collection variables are added to `env`,
+/// and `{{var}}` is translated to `${{ env.var }}`.
+///
+/// Status checks in `pm.test` scripts are translated to assertions.
+/// Everything else we can't translate is added to the warnings.
+pub fn import_postman(collection: &Value) -> Result<ImportedCollection, String> {
+    let items = collection["item"]
+        .as_array()
+        .ok_or("missing `item`, is this a Postman collection?")?;
+    let name = collection["info"]["name"]
+        .as_str()
+        .unwrap_or("postman")
+        .to_string();
+
+    let mut importer = PostmanImporter {
+        env: collection_variables(collection),
+        warnings: vec![],
+    };
+
+    let mut workflows = vec![];
+    let mut loose_steps = vec![];
+    for item in items {
+        match item["item"].as_array() {
+            Some(folder_items) => {
+                let folder_name = item["name"].as_str().unwrap_or("folder").to_string();
+                let mut steps = vec![];
+                importer.add_items(folder_items, "", &mut steps);
+                workflows.push(importer.workflow(folder_name, steps));
+            }
+            None => importer.add_items(std::slice::from_ref(item), "", &mut loose_steps),
+        }
+    }
+
+    if !loose_steps.is_empty() {
+        workflows.insert(0, importer.workflow(name, loose_steps));
+    }
+
+    if collection["auth"].is_object() {
+        importer.warnings.push(
+            "collection auth was not imported, add it to the headers of each workflow".into(),
+        );
+    }
+
+    Ok(ImportedCollection {
+        workflows,
+        warnings: importer.warnings,
+    })
+}
+
+struct PostmanImporter {
+    env: BTreeMap<String, serde_yaml::Value>,
+    warnings: Vec<String>,
+}
+
+impl PostmanImporter {
+    fn workflow(&self, name: String, steps: Vec<WorkflowConfigStep>) -> WorkflowConfig {
+        WorkflowConfig {
+            name,
+            env: match self.env.is_empty() {
+                true => None,
+                false => Some(self.env.to_owned()),
+            },
+            steps,
+            ..Default::default()
+        }
+    }
+
+    /// Add the requests in `items` as steps. Nested folders are
+    /// flattened, with the folder name added to the step names.
+    fn add_items(&mut self, items: &[Value], prefix: &str, steps: &mut Vec<WorkflowConfigStep>) {
+        for item in items {
+            let name = format!("{}{}", prefix, item["name"].as_str().unwrap_or("request"));
+
+            match item["item"].as_array() {
+                Some(folder_items) => self.add_items(folder_items, &format!("{} / ", name), steps),
+                None => {
+                    if let Some(step) = self.step(&name, item) {
+                        steps.push(step);
+                    }
+                }
+            }
+        }
+    }
+
+    fn step(&mut self, name: &str, item: &Value) -> Option<WorkflowConfigStep> {
+        let request = match &item["request"] {
+            // a request can be just the url
+            Value::String(url) => serde_json::json!({ "url": url, "method": "GET" }),
+            Value::Object(_) => item["request"].to_owned(),
+            _ => {
+                self.warnings.push(format!("`{}` has no request", name));
+                return None;
+            }
+        };
+
+        let method = request["method"].as_str().unwrap_or("GET").to_uppercase();
+        let (url, query) = self.url(name, &request["url"]);
+        let mut headers = self.headers(name, &request["header"]);
+        let (body, graphql) = self.body(name, &request["body"], &mut headers);
+        self.auth(name, &request["auth"], &mut headers);
+
+        let assertions = self.assertions(name, &item["event"]);
+
+        Some(WorkflowConfigStep {
+            name: name.to_string(),
+            url: Some(url),
+            method: Some(method),
+            query,
+            headers: match headers.is_empty() {
+                true => None,
+                false => Some(headers),
+            },
+            body,
+            assertions,
+            graphql,
+            skip: match item["disabled"].as_bool() {
+                Some(true) => Some(true),
+                _ => None,
+            },
+            ..Default::default()
+        })
+    }
+
+    fn url(&mut self, name: &str, url: &Value) -> (String, Option<BTreeMap<String, String>>) {
+        let raw = match url {
+            Value::String(raw) => raw.to_owned(),
+            url => url["raw"].as_str().unwrap_or_default().to_string(),
+        };
+
+        // the query is added as `query:`, so it's easier to edit
+        let mut query = BTreeMap::new();
+        let params = url["query"].as_array().into_iter().flatten();
+        for param in params.filter(|param| !is_disabled(param)) {
+            if let (Some(key), value) = (param["key"].as_str(), param["value"].as_str()) {
+                query.insert(
+                    self.template(name, key),
+                    self.template(name, value.unwrap_or_default()),
+                );
+            }
+        }
+
+        let raw = match query.is_empty() {
+            true => raw,
+            false => raw.split('?').next().unwrap_or_default().to_string(),
+        };
+
+        let query = match query.is_empty() {
+            true => None,
+            false => Some(query),
+        };
+
+        (self.template(name, &raw), query)
+    }
+
+    fn headers(&mut self, name: &str, headers: &Value) -> BTreeMap<String, serde_yaml::Value> {
+        let mut result = BTreeMap::new();
+        let headers = headers.as_array().into_iter().flatten();
+
+        for header in headers.filter(|header| !is_disabled(header)) {
+            if let (Some(key), Some(value)) = (header["key"].as_str(), header["value"].as_str()) {
+                result.insert(
+                    key.to_string(),
+                    serde_yaml::Value::String(self.template(name, value)),
+                );
+            }
+        }
+
+        result
+    }
+
+    fn body(
+        &mut self,
+        name: &str,
+        body: &Value,
+        headers: &mut BTreeMap<String, serde_yaml::Value>,
+    ) -> (
+        Option<serde_yaml::Value>,
+        Option<WorkflowConfigGraphQlConfig>,
+    ) {
+        match body["mode"].as_str() {
+            Some("raw") => {
+                let raw = self.template(name, body["raw"].as_str().unwrap_or_default());
+                if raw.is_empty() {
+                    return (None, None);
+                }
+
+                // JSON bodies are added as yaml, so they are easier to read
+                match serde_json::from_str::<Value>(&raw) {
+                    Ok(json) if json.is_object() || json.is_array() => {
+                        (serde_yaml::to_value(json).ok(), None)
+                    }
+                    _ => (Some(serde_yaml::Value::String(raw)), None),
+                }
+            }
+            Some("graphql") => {
+                let graphql = &body["graphql"];
+                let query = self.template(name, graphql["query"].as_str().unwrap_or_default());
+                let variables = graphql["variables"]
+                    .as_str()
+                    .map(|variables| self.template(name, variables))
+                    .and_then(|variables| serde_json::from_str::<Value>(&variables).ok())
+                    .and_then(|variables| serde_yaml::to_value(variables).ok());
+
+                (None, Some(WorkflowConfigGraphQlConfig { query, variables }))
+            }
+            Some("urlencoded") => {
+                let params = body["urlencoded"].as_array().into_iter().flatten();
+                let params: Vec<String> = params
+                    .filter(|param| !is_disabled(param))
+                    .map(|param| {
+                        format!(
+                            "{}={}",
+                            param["key"].as_str().unwrap_or_default(),
+                            param["value"].as_str().unwrap_or_default()
+                        )
+                    })
+                    .collect();
+
+                headers
+                    .entry("Content-Type".to_string())
+                    .or_insert_with(|| "application/x-www-form-urlencoded".into());
+
+                let body = self.template(name, &params.join("&"));
+                (Some(serde_yaml::Value::String(body)), None)
+            }
+            Some(mode) => {
+                self.warnings.push(format!(
+                    "`{}` has a {} body, which is not supported",
+                    name, mode
+                ));
+                (None, None)
+            }
+            None => (None, None),
+        }
+    }
+
+    fn auth(
+        &mut self,
+        name: &str,
+        auth: &Value,
+        headers: &mut BTreeMap<String, serde_yaml::Value>,
+    ) {
+        match auth["type"].as_str() {
+            Some("bearer") => {
+                let token = auth_value(&auth["bearer"], "token");
+                let token = self.template(name, &token);
+                headers.insert(
+                    "Authorization".to_string(),
+                    serde_yaml::Value::String(format!("Bearer {}", token)),
+                );
+            }
+            Some("noauth") | None => {}
+            Some(auth_type) => self.warnings.push(format!(
+                "`{}` uses {} auth, which is not supported",
+                name, auth_type
+            )),
+        }
+    }
+
+    /// Translate status checks in the test scripts to assertions.
+    fn assertions(&mut self, name: &str, events: &Value) -> Vec<WorkflowConfigAssertion> {
+        let mut assertions = vec![];
+        let events = events.as_array().into_iter().flatten();
+        let test_name = Regex::new(r#"^\s*["'`](.*?)["'`]"#).unwrap();
+
+        for event in events {
+            let script = match &event["script"]["exec"] {
+                Value::Array(lines) => lines
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<&str>>()
+                    .join("\n"),
+                Value::String(script) => script.to_owned(),
+                _ => continue,
+            };
+
+            if script.trim().is_empty() {
+                continue;
+            }
+
+            if event["listen"].as_str() != Some("test") {
+                self.warnings
+                    .push(format!("`{}` has a pre-request script", name));
+                continue;
+            }
+
+            // every `pm.test(...)` is checked on its own, so we can
+            // tell the user which ones we couldn't translate
+            for test in script.split("pm.test(").skip(1) {
+                let test_name = test_name
+                    .captures(test)
+                    .map(|captures| captures[1].to_string())
+                    .unwrap_or_default();
+
+                match status_assertions(test) {
+                    Some(mut status_assertions) => assertions.append(&mut status_assertions),
+                    None => self.warnings.push(format!(
+                        "`{}` has a test that could not be translated: \"{}\"",
+                        name, test_name
+                    )),
+                }
+            }
+        }
+
+        assertions
+    }
+
+    /// Translate `{{var}}` to `${{ env.var }}`.
+    fn template(&mut self, name: &str, value: &str) -> String {
+        let search = Regex::new(r"\{\{\s*([^{}]+?)\s*}}").unwrap();
+        let mut warnings = vec![];
+
+        let result = search.replace_all(value, |captures: &Captures| {
+            let variable = &captures[1];
+
+            // dynamic variables like `{{$guid}}` don't exist in capter, and
+            // since we look up values by path, names can't have dots in them
+            if variable.starts_with('$') || variable.contains('.') || variable.contains(' ') {
+                warnings.push(format!(
+                    "`{}` uses the variable {{{{{}}}}}, which could not be translated",
+                    name, variable
+                ));
+                return captures[0].to_string();
+            }
+
+            format!("${{{{ env.{} }}}}", variable)
+        });
+
+        let result = result.to_string();
+        self.warnings.append(&mut warnings);
+        result
+    }
+}
+
+/// Find the status checks in a `pm.test`, or None if the test
+/// checks anything else.
+fn status_assertions(test: &str) -> Option<Vec<WorkflowConfigAssertion>> {
+    let status_code = Regex::new(
+        r"pm\.response\.to\.have\.status\((\d+)\)|pm\.expect\(pm\.response\.code\)\.to\.(?:eql|equal)\((\d+)\)",
+    )
+    .unwrap();
+    let success = Regex::new(r"pm\.response\.to\.be\.(?:ok|success)\b").unwrap();
+
+    // `pm.expect(...)` and `pm.response.to...` checks in the test
+    let checks = Regex::new(r"pm\.expect\(|pm\.response\.to\.").unwrap();
+    let check_count = checks.find_iter(test).count();
+
+    let mut assertions = vec![];
+    for captures in status_code.captures_iter(test) {
+        let status = captures.get(1).or_else(|| captures.get(2))?.as_str();
+        assertions.push(WorkflowConfigAssertion::expect(format!(
+            "status to_equal {}",
+            status
+        )));
+    }
+
+    let success_count = success.find_iter(test).count();
+    if success_count > 0 {
+        assertions.push(WorkflowConfigAssertion::expect(
+            "status to_be_at_least 200".to_string(),
+        ));
+        assertions.push(WorkflowConfigAssertion::expect(
+            "status to_be_below 300".to_string(),
+        ));
+    }
+
+    // only translate tests where we understood every check
+    let translated = status_code.find_iter(test).count() + success_count;
+    match translated > 0 && translated == check_count {
+        true => Some(assertions),
+        false => None,
+    }
+}
+
+fn collection_variables(collection: &Value) -> BTreeMap<String, serde_yaml::Value> {
+    let mut env = BTreeMap::new();
+    let variables = collection["variable"].as_array().into_iter().flatten();
+
+    for variable in variables.filter(|variable| !is_disabled(variable)) {
+        if let Some(key) = variable["key"].as_str() {
+            let value = match &variable["value"] {
+                Value::String(value) => value.to_owned(),
+                Value::Null => String::new(),
+                value => value.to_string(),
+            };
+            env.insert(key.to_string(), serde_yaml::Value::String(value));
+        }
+    }
+
+    env
+}
+
+/// Auth values are a list of `{ key, value }` in v2.1 and a map in v2.0.
+fn auth_value(auth: &Value, key: &str) -> String {
+    match auth {
+        Value::Array(values) => values
+            .iter()
+            .find(|value| value["key"] == key)
+            .and_then(|value| value["value"].as_str())
+            .unwrap_or_default()
+            .to_string(),
+        auth => auth[key].as_str().unwrap_or_default().to_string(),
+    }
+}
+
+fn is_disabled(value: &Value) -> bool {
+    value["disabled"].as_bool().unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn collection() -> Value {
+        json!({
+            "info": { "name": "Users API", "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json" },
+            "variable": [{ "key": "baseUrl", "value": "https://api.example.com" }],
+            "item": [
+                {
+                    "name": "health",
+                    "request": { "method": "GET", "url": "{{baseUrl}}/health" }
+                },
+                {
+                    "name": "users",
+                    "item": [
+                        {
+                            "name": "create user",
+                            "event": [{
+                                "listen": "test",
+                                "script": { "exec": [
+                                    "pm.test(\"Status code is 201\", function () {",
+                                    "    pm.response.to.have.status(201);",
+                                    "});",
+                                    "pm.test(\"Has id\", function () {",
+                                    "    pm.expect(pm.response.json().id).to.be.a('number');",
+                                    "});"
+                                ]}
+                            }],
+                            "request": {
+                                "method": "POST",
+                                "auth": { "type": "bearer", "bearer": [{ "key": "token", "value": "{{token}}" }] },
+                                "header": [
+                                    { "key": "X-Trace", "value": "{{$guid}}" },
+                                    { "key": "X-Old", "value": "1", "disabled": true }
+                                ],
+                                "body": {
+                                    "mode": "raw",
+                                    "raw": "{\"name\": \"{{name}}\"}",
+                                    "options": { "raw": { "language": "json" } }
+                                },
+                                "url": {
+                                    "raw": "{{baseUrl}}/users?notify=true",
+                                    "query": [{ "key": "notify", "value": "true" }]
+                                }
+                            }
+                        },
+                        {
+                            "name": "admin",
+                            "item": [{
+                                "name": "search",
+                                "event": [{
+                                    "listen": "test",
+                                    "script": { "exec": ["pm.test('ok', () => pm.response.to.be.ok);"] }
+                                }],
+                                "request": {
+                                    "method": "POST",
+                                    "body": {
+                                        "mode": "graphql",
+                                        "graphql": { "query": "{ users { id } }", "variables": "{\"limit\": 10}" }
+                                    },
+                                    "url": "{{baseUrl}}/graphql"
+                                }
+                            }]
+                        }
+                    ]
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_import_postman() {
+        let imported = import_postman(&collection()).unwrap();
+
+        let names: Vec<&str> = imported.workflows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, vec!["Users API", "users"]);
+
+        let health = &imported.workflows[0];
+        assert_eq!(
+            health.env.to_owned().unwrap()["baseUrl"],
+            serde_yaml::Value::String("https://api.example.com".to_string())
+        );
+        assert_eq!(
+            health.steps[0].url,
+            Some("${{ env.baseUrl }}/health".to_string())
+        );
+        assert!(health.steps[0].assertions.is_empty());
+
+        let create = &imported.workflows[1].steps[0];
+        assert_eq!(create.method, Some("POST".to_string()));
+        assert_eq!(create.url, Some("${{ env.baseUrl }}/users".to_string()));
+        assert_eq!(create.query.to_owned().unwrap()["notify"], "true");
+        assert_eq!(
+            serde_json::to_value(&create.headers).unwrap(),
+            json!({ "Authorization": "Bearer ${{ env.token }}", "X-Trace": "{{$guid}}" })
+        );
+        assert_eq!(
+            serde_json::to_value(&create.body).unwrap(),
+            json!({ "name": "${{ env.name }}" })
+        );
+        assert_eq!(
+            serde_json::to_value(&create.assertions).unwrap(),
+            json!([{ "expect": "status to_equal 201" }])
+        );
+
+        let search = &imported.workflows[1].steps[1];
+        assert_eq!(search.name, "admin / search");
+        let graphql = search.graphql.to_owned().unwrap();
+        assert_eq!(graphql.query, "{ users { id } }");
+        assert_eq!(
+            serde_json::to_value(graphql.variables).unwrap(),
+            json!({ "limit": 10 })
+        );
+        assert_eq!(
+            serde_json::to_value(&search.assertions).unwrap(),
+            json!([
+                { "expect": "status to_be_at_least 200" },
+                { "expect": "status to_be_below 300" }
+            ])
+        );
+
+        assert_eq!(
+            imported.warnings,
+            vec![
+                "`create user` uses the variable {{$guid}}, which could not be translated",
+                "`create user` has a test that could not be translated: \"Has id\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_names_and_query_order() {
+        let imported = import_postman(&json!({
+            "info": { "name": "users" },
+            "item": [
+                {
+                    "name": "search",
+                    "request": {
+                        "method": "GET",
+                        "url": {
+                            "raw": "http://localhost/users?sort=name&page=1&limit=10",
+                            "query": [
+                                { "key": "sort", "value": "name" },
+                                { "key": "page", "value": "1" },
+                                { "key": "limit", "value": "10" }
+                            ]
+                        }
+                    }
+                },
+                { "name": "Users", "item": [] },
+                { "name": "users", "item": [] }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            imported.paths(),
+            vec![
+                ".capter/users.yml",
+                ".capter/users-2.yml",
+                ".capter/users-3.yml"
+            ]
+        );
+
+        // the query is written in the same order every time
+        let yaml = imported.workflows[0].to_yaml().unwrap();
+        assert!(yaml.contains("query:\n      limit: \"10\"\n      page: \"1\"\n      sort: name\n"));
+    }
+
+    #[test]
+    fn test_not_a_collection() {
+        assert!(import_postman(&json!({ "openapi": "3.0.0" })).is_err());
+    }
+}
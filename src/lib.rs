@@ -0,0 +1,56 @@
+//! Capter is a lightweight end-to-end testing tool for APIs.
+//!
+//! Besides the `capter` CLI, the workflows can be run from Rust, like
+//! from `cargo test` against a test server started by the test, with
+//! its url in `${{ vars.base_url }}`:
+//!
+//! ```no_run
+//! use capter::{CallbackEvent, RunOptions, WorkflowConfig, WorkflowResult};
+//!
+//! let config = WorkflowConfig::read_yaml_file(".capter/users.yml").unwrap();
+//! let mut vars = serde_json::Map::new();
+//! vars.insert("base_url".to_string(), "http://127.0.0.1:8080".into());
+//! let options = RunOptions::builder().timeout(5).vars(vars).build();
+//!
+//! let result = WorkflowResult::from_config(&options, &config, |event| {
+//!     if let CallbackEvent::StepDone(_, _, request, passed) = event {
+//!         println!("{} {}", request.name, passed);
+//!     }
+//! })
+//! .unwrap();
+//!
+//! assert!(result.passed);
+//! ```
+//!
+//! [`WorkflowPool`] runs many workflows at the same time, and the
+//! assertions can be used on their own with [`Assertion`].
+
+pub mod assert;
+pub mod compile;
+pub mod error;
+pub mod openapi;
+pub mod report;
+pub mod validate;
+pub mod workflow;
+
+// used by the `capter` binary, these are not part of the API
+#[doc(hidden)]
+pub mod ci;
+#[doc(hidden)]
+pub mod import;
+#[doc(hidden)]
+pub mod project;
+#[doc(hidden)]
+pub mod ui;
+#[doc(hidden)]
+pub mod utils;
+
+pub use assert::{
+    assert::{parse_assertion_string, AssertionTest},
+    Assertion, AssertionData, AssertionResultData,
+};
+pub use error::CapterError;
+pub use workflow::{
+    workflow_result::{CallbackEvent, SkipReason, SkippedStep, WorkflowResult},
+    RunOptions, RunOptionsBuilder, RunSource, WorkflowConfig, WorkflowPool,
+};
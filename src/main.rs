@@ -1,125 +1,318 @@
-mod assert;
-mod ci;
-mod compile;
-mod ui;
-mod utils;
-mod workflow;
-
-use clap::{crate_version, load_yaml, App, AppSettings};
+use capter::{
+    compile::functions,
+    error::CapterError,
+    import::import_postman,
+    openapi::{generate_workflows, OpenApiSpec},
+    project::ProjectConfig,
+    report::create_reporter,
+    ui::{webhook::WebhookResponse, TerminalUi},
+    utils::{exit_with_code, Logger},
+    validate::{file_error, validate_files},
+    workflow::{
+        workflow_result::{CallbackEvent, WorkflowResult},
+        RunOptions, RunSource, WorkflowConfig, WorkflowFilter, WorkflowPool,
+    },
+};
+use clap::{crate_version, load_yaml, App, AppSettings, Arg, ArgMatches, SubCommand};
 use dotenv::dotenv;
-use globwalk;
-use serde::Deserialize;
+use path_clean::PathClean;
+use regex::Regex;
 use serde_json::json;
-use ui::TerminalUi;
-use ureq;
-use utils::{exit_with_code, Logger};
-use workflow::{workflow_result::WorkflowResult, RunSource, WorkflowConfig};
-
-pub struct CliOptions {
-    is_debug: bool,
-    timeout: u64,
-}
-
-#[derive(Deserialize)]
-pub struct WebhookResponse {
-    url: String,
-}
-
-impl Default for CliOptions {
-    fn default() -> CliOptions {
-        CliOptions {
-            is_debug: false,
-            timeout: 30,
-        }
-    }
-}
+use std::path::PathBuf;
 
 fn main() {
-    // read .env file
-    dotenv().ok();
-
     let yml = load_yaml!("cli.yml");
-    let matches = App::from_yaml(yml)
+    let mut app = App::from_yaml(yml)
         .setting(AppSettings::SubcommandRequiredElseHelp)
-        .version(crate_version!())
-        .get_matches();
+        .version(crate_version!());
 
-    // handle the subcommand `test`
-    if let Some(matches) = matches.subcommand_matches("test") {
+    // `test` and `export` are added with the args they share
+    let run_args: Vec<Arg> = yml["run_args"]
+        .as_vec()
+        .into_iter()
+        .flatten()
+        .filter_map(|arg| arg.as_hash().map(Arg::from_yaml))
+        .collect();
+    for subcommand in yml["run_subcommands"].as_vec().into_iter().flatten() {
+        app = app.subcommand(SubCommand::from_yaml(subcommand).args(&run_args));
+    }
+    let matches = app.get_matches();
+
+    // `test`, and `export` which runs the workflows the same way,
+    // with the same filters, setup and environment, so every request
+    // is sent. it only writes the requests, not the results
+    let export_matches = matches.subcommand_matches("export");
+    let run_matches = matches.subcommand_matches("test").or(export_matches);
+
+    // handle the subcommand `test` and `export`
+    if let Some(matches) = run_matches {
+        let project_config = load_project_config(matches);
+        // the environment to run with, like `staging`
+        let environment = project_config.environment.to_owned();
+        let is_export = export_matches.is_some();
         // will run the CLI in debug mode
         let is_debug = matches.is_present("debug");
         // passing a token will submit the run to the webhook
-        let token = matches.value_of("token");
+        let token = matches
+            .value_of("token")
+            .or(project_config.token.as_deref());
         // the url we will post the run to after completion
-        let webhook = matches.value_of("webhook");
-        // where to look for the yaml files
-        let tests_glob = matches.value_of("INPUT").unwrap();
+        let webhook = matches
+            .value_of("webhook")
+            .or(project_config.webhook.as_deref());
         // stops the cli from posting to the webhook
         let dry_run = matches.is_present("dry-run");
-        // the timeout for requests
-        let timeout = matches.value_of("timeout");
-
-        let cli_options = CliOptions {
-            is_debug,
-            timeout: timeout.unwrap_or("30").parse().unwrap_or(30),
+        // the timeout for requests, in seconds
+        let timeout = match matches.value_of("timeout").map(str::parse) {
+            Some(Ok(timeout)) => timeout,
+            Some(Err(_)) => exit_with_code(
+                exitcode::USAGE,
+                Some("The timeout needs to be a positive number of seconds"),
+            ),
+            None => project_config.timeout.unwrap_or(30),
+        };
+        // how many workflows we run at the same time
+        let jobs = match matches.value_of("jobs").map(str::parse) {
+            Some(Ok(jobs)) if jobs > 0 => jobs,
+            Some(_) => exit_with_code(
+                exitcode::USAGE,
+                Some("The number of jobs needs to be a number above 0"),
+            ),
+            None => project_config.jobs.unwrap_or(1),
+        };
+        // write a report in this format, like `junit` or `json`,
+        // an export always uses the format that's passed
+        let (reporter, output) = match is_export {
+            true => (matches.value_of("format"), matches.value_of("output")),
+            false => (
+                matches
+                    .value_of("reporter")
+                    .or(project_config.reporter.as_deref()),
+                // where to write the report, defaults to stdout
+                matches
+                    .value_of("output")
+                    .or(project_config.output.as_deref()),
+            ),
+        };
+        if output.is_some() && reporter.is_none() {
+            exit_with_code(
+                exitcode::USAGE,
+                Some("A report can only be written with a reporter, use --reporter"),
+            );
+        }
+        // check every response against this OpenAPI spec
+        let openapi = matches
+            .value_of("openapi")
+            .or(project_config.openapi.as_deref());
+        // seed for generated values, a new one is picked for every run
+        let seed = match matches.value_of("seed").map(str::parse) {
+            Some(Ok(seed)) => seed,
+            Some(Err(_)) => exit_with_code(
+                exitcode::USAGE,
+                Some("The seed needs to be a positive number"),
+            ),
+            None => functions::random_seed(),
         };
 
-        // we'll collect all runs in this array so we can post it
-        // to the webhook after the run is complete
-        let mut workflow_runs: Vec<WorkflowResult> = vec![];
+        let mut builder = RunOptions::builder()
+            .debug(is_debug)
+            .timeout(timeout)
+            .jobs(jobs)
+            .seed(seed);
+        if let Some(reporter) = reporter {
+            builder = builder.reporter(reporter);
+        }
+        if let Some(output) = output {
+            builder = builder.output(output);
+        }
+        if let Some(openapi) = openapi {
+            let spec = match OpenApiSpec::from_file(openapi) {
+                Ok(spec) => spec,
+                Err(err) => exit_with_code(exitcode::CONFIG, Some(&err)),
+            };
+            builder = builder.openapi(spec);
+        }
+        if let Some(environment) = &environment {
+            builder = builder.environment(environment);
+        }
+        let run_options = builder.build();
 
         // collect the source information
-        let source = RunSource::new(&cli_options);
+        let source = RunSource::new(&run_options);
 
-        let entries = match globwalk::glob(tests_glob) {
-            Ok(res) => res,
-            _ => exit_with_code(
-                exitcode::USAGE,
-                Some(&format!("Invalid search glob: `{}`", tests_glob)),
-            ),
+        // where to look for the yaml files
+        let paths = workflow_paths(matches, &project_config);
+
+        // only run what's picked by tags, names and files
+        let filter = WorkflowFilter {
+            tags: values_of(matches, "tag"),
+            exclude_tags: values_of(matches, "exclude-tag"),
+            grep: matches.value_of("grep").map(|grep| match Regex::new(grep) {
+                Ok(grep) => grep,
+                Err(err) => exit_with_code(
+                    exitcode::USAGE,
+                    Some(&format!("Invalid --grep pattern: {}", err)),
+                ),
+            }),
+            only: values_of(matches, "only"),
+        };
+
+        // a workflow that can't be read is reported as errored,
+        // and the other workflows still run
+        let mut errored: Vec<(WorkflowConfig, CapterError)> = vec![];
+        let mut load = |path: &PathBuf, result: Result<WorkflowConfig, CapterError>| {
+            let result = result.and_then(|mut config| {
+                project_config.apply(&mut config)?;
+                Ok(config)
+            });
+            match result {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    let file = path.clean().to_string_lossy().to_string();
+                    let config = WorkflowConfig {
+                        name: file.to_owned(),
+                        file: Some(file),
+                        ..Default::default()
+                    };
+                    errored.push((config, CapterError::Invalid(vec![file_error(path, &err)])));
+                    None
+                }
+            }
+        };
+
+        // the setup workflow runs before everything else, and the
+        // teardown workflow after, even if the tests fail
+        let mut load_file = |path: &str| {
+            let path = PathBuf::from(path);
+            load(&path, WorkflowConfig::read_yaml_file(&path))
         };
+        let setup = matches
+            .value_of("setup")
+            .or(project_config.setup.as_deref())
+            .map(&mut load_file);
+        let teardown = matches
+            .value_of("teardown")
+            .or(project_config.teardown.as_deref())
+            .and_then(&mut load_file);
+        // the tests are skipped if setup can't be read, like when it fails
+        let is_setup_errored = matches!(setup, Some(None));
+        let setup = setup.flatten();
 
-        let configs: Vec<WorkflowConfig> = entries
-            .map(|entry| {
-                let entry = entry.expect("Invalid path");
-                let workflow = WorkflowConfig::from_yaml_file(&entry.into_path());
-                workflow
-            })
+        // files used with `extends` and `use` are usually next to the
+        // workflows, but they aren't workflows on their own
+        let results: Vec<Result<WorkflowConfig, CapterError>> =
+            paths.iter().map(WorkflowConfig::read_yaml_file).collect();
+        let includes: Vec<String> = results
+            .iter()
+            .flatten()
+            .flat_map(|config| config.includes.to_owned())
             .collect();
+        let mut configs: Vec<WorkflowConfig> = paths
+            .iter()
+            .zip(results)
+            .filter(|(path, _)| !includes.contains(&path.clean().to_string_lossy().to_string()))
+            .filter_map(|(path, result)| load(path, result))
+            .collect();
+
+        // setup and teardown are usually next to the other
+        // workflows too, but should only run once
+        configs.retain(|config| {
+            [&setup, &teardown]
+                .iter()
+                .all(|other| other.as_ref().map(|other| &other.file) != Some(&config.file))
+        });
+        for config in configs.iter_mut() {
+            filter.apply(config);
+        }
 
         // this sets up our UI
-        let mut terminal_ui = TerminalUi::new(&configs, &source, &cli_options);
+        let all_configs: Vec<WorkflowConfig> = errored
+            .iter()
+            .map(|(config, _)| config)
+            .chain(setup.iter())
+            .chain(configs.iter())
+            .chain(teardown.iter())
+            .cloned()
+            .collect();
 
-        let mut passed = true;
+        let mut terminal_ui = TerminalUi::new(&all_configs, &source, &run_options);
 
-        for workflow_config in configs {
-            // setting `skip: true` in the workflow will stop
-            // it from running:
-            if workflow_config.skip.is_some() {
-                terminal_ui.skipped_workflow(&workflow_config);
-                continue;
+        // the reporter gets the same events as the UI
+        let mut reporter = match reporter {
+            Some(reporter) => match create_reporter(reporter, output) {
+                Ok(mut reporter) => {
+                    reporter.start(&source);
+                    Some(reporter)
+                }
+                Err(err) => exit_with_code(
+                    exitcode::CANTCREAT,
+                    Some(&format!("Failed to create report: {}", err)),
+                ),
+            },
+            None => None,
+        };
+
+        let mut on_event = |event: CallbackEvent| {
+            terminal_ui.update(event);
+            if let Some(reporter) = &mut reporter {
+                reporter.update(event);
             }
+        };
 
-            // run the workflow and use the callback to update the UI on events like
-            // new step, step completed etc.
-            // we get `RequestData` back, which is the results of of this workflow
-            let workflow_result =
-                WorkflowResult::from_config(&cli_options, &workflow_config, |event| {
-                    terminal_ui.update(event);
-                });
-
-            if let Ok(workflow_run) = workflow_result {
-                if workflow_run.passed == false {
-                    passed = false
-                }
-                workflow_runs.push(workflow_run);
-            } else {
-                // TODO: handle error
+        let mut workflow_runs = vec![];
+
+        // the workflows that couldn't be read are reported first
+        for (config, error) in &errored {
+            on_event(CallbackEvent::RunStart(config));
+            on_event(CallbackEvent::RunErrored(config, error));
+            workflow_runs.push(WorkflowResult::from_error(&run_options, config, error));
+        }
+
+        // the tests are skipped if setup can't be read
+        if is_setup_errored {
+            for config in configs.iter_mut() {
+                config.skip = Some(true);
             }
         }
 
+        // run the workflows and use the callback to update the UI on events like
+        // new step, step completed etc.
+        // setup runs first and shares its variables, and teardown runs last.
+        // we get `WorkflowResult`s back, in the order they ran,
+        // and we'll post them to the webhook after the run is complete
+        let pool = WorkflowPool::new(&run_options);
+        workflow_runs.append(&mut pool.run_with_setup(
+            setup.as_ref(),
+            &configs,
+            teardown.as_ref(),
+            &mut on_event,
+        ));
+
+        let passed = workflow_runs.iter().all(|workflow_run| workflow_run.passed);
+        // mistakes in the workflows have their own exit code
+        let is_errored = workflow_runs
+            .iter()
+            .any(|workflow_run| workflow_run.error.is_some());
+
         terminal_ui.summarize(&workflow_runs);
 
+        if let Some(reporter) = &mut reporter {
+            if let Err(err) = reporter.finish(&source, &workflow_runs) {
+                exit_with_code(
+                    exitcode::IOERR,
+                    Some(&format!("Failed to write report: {}", err)),
+                );
+            }
+        }
+
+        // failing steps are what people export, so that's not an error
+        if is_export {
+            match is_errored {
+                true => exit_with_code(exitcode::CONFIG, None),
+                false => exit_with_code(exitcode::OK, None),
+            }
+        }
+
         // write to log on fail
         if !passed {
             let mut logger = Logger::new();
@@ -176,17 +369,175 @@ fn main() {
             }
         }
 
-        if passed {
+        if is_errored {
+            exit_with_code(exitcode::CONFIG, None);
+        } else if passed {
             exit_with_code(exitcode::OK, None);
         } else {
             exit_with_code(1, None);
         }
     }
 
+    // handle the subcommand `validate`
+    if let Some(matches) = matches.subcommand_matches("validate") {
+        let project_config = load_project_config(matches);
+        let paths = workflow_paths(matches, &project_config);
+        let (count, errors) = validate_files(&paths);
+
+        TerminalUi::print_validation(count, &errors);
+        match errors.is_empty() {
+            true => exit_with_code(exitcode::OK, None),
+            false => exit_with_code(exitcode::CONFIG, None),
+        }
+    }
+
     // handle the subcommand `test`
-    if matches.subcommand_matches("init").is_some() {
+    if let Some(matches) = matches.subcommand_matches("init") {
+        // create a workflow for every tag in the spec
+        if let Some(path) = matches.value_of("from-openapi") {
+            let spec = match OpenApiSpec::from_file(path) {
+                Ok(spec) => spec,
+                Err(err) => exit_with_code(exitcode::CONFIG, Some(&err)),
+            };
+
+            let mut created = vec![];
+            let mut existing = vec![];
+            for workflow in generate_workflows(&spec) {
+                match workflow.config.create_file(&workflow.path) {
+                    Ok(true) => created.push(workflow.path),
+                    Ok(false) => existing.push(workflow.path),
+                    Err(err) => exit_with_code(exitcode::CANTCREAT, Some(&err.to_string())),
+                }
+            }
+
+            TerminalUi::print_init_from_openapi(&created, &existing);
+            exit_with_code(exitcode::OK, None);
+        }
+
         WorkflowConfig::create_example();
         TerminalUi::print_init();
         exit_with_code(exitcode::OK, None);
     }
+
+    // handle the subcommand `import`
+    if let Some(matches) = matches.subcommand_matches("import") {
+        let path = matches.value_of("FILE").unwrap();
+
+        let collection = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => exit_with_code(
+                exitcode::NOINPUT,
+                Some(&format!("Failed to read {}: {}", path, err)),
+            ),
+        };
+
+        let imported = serde_json::from_str(&collection)
+            .map_err(|err| err.to_string())
+            .and_then(|collection| match matches.value_of("FORMAT") {
+                Some("postman") => import_postman(&collection),
+                format => Err(format!("unknown format: `{}`", format.unwrap_or_default())),
+            });
+
+        let imported = match imported {
+            Ok(imported) => imported,
+            Err(err) => exit_with_code(
+                exitcode::DATAERR,
+                Some(&format!("Failed to import {}: {}", path, err)),
+            ),
+        };
+
+        let mut created = vec![];
+        let mut existing = vec![];
+        for (workflow, path) in imported.workflows.iter().zip(imported.paths()) {
+            match workflow.create_file(&path) {
+                Ok(true) => created.push(path),
+                Ok(false) => existing.push(path),
+                Err(err) => exit_with_code(exitcode::CANTCREAT, Some(&err.to_string())),
+            }
+        }
+
+        TerminalUi::print_import(path, &created, &existing, &imported.warnings);
+        exit_with_code(exitcode::OK, None);
+    }
+}
+
+/// Settings for the whole project, from `capter.yml`, with the
+/// environment selected and the `.env` files read. This is only
+/// done for the commands that use them, so a broken config
+/// doesn't get in the way of `--help`, `init` or `import`.
+fn load_project_config(matches: &ArgMatches) -> ProjectConfig {
+    let mut project_config = match ProjectConfig::load() {
+        Ok(project_config) => project_config,
+        Err(err) => exit_with_code(exitcode::CONFIG, Some(&err.to_string())),
+    };
+
+    // the environment to run with, like `staging`
+    let environment = matches
+        .value_of("env")
+        .map(String::from)
+        .or_else(|| project_config.environment.to_owned());
+    if let Some(environment) = &environment {
+        if let Err(err) = project_config.set_environment(environment) {
+            exit_with_code(exitcode::CONFIG, Some(&err.to_string()));
+        }
+    }
+    // an environment that doesn't exist is a mistake for the whole run
+    if let Err(err) = project_config.env() {
+        exit_with_code(exitcode::CONFIG, Some(&err.to_string()));
+    }
+
+    // read .env file, or the files set in the project config
+    match &project_config.env_files {
+        Some(env_files) => {
+            for env_file in env_files {
+                if let Err(err) = dotenv::from_filename(env_file) {
+                    exit_with_code(
+                        exitcode::CONFIG,
+                        Some(&format!("Failed to read {}: {}", env_file, err)),
+                    );
+                }
+            }
+        }
+        None => {
+            dotenv().ok();
+        }
+    }
+
+    project_config
+}
+
+/// The workflow files in the `INPUT` glob, sorted so the workflows
+/// are always reported in the same order, no matter how many jobs
+/// we run them with. The project config is only used if the glob
+/// isn't passed.
+fn workflow_paths(matches: &ArgMatches, project_config: &ProjectConfig) -> Vec<PathBuf> {
+    let tests_glob = match (matches.occurrences_of("INPUT"), &project_config.input) {
+        (0, Some(input)) => input.as_str(),
+        _ => matches.value_of("INPUT").unwrap(),
+    };
+
+    let entries = match globwalk::glob(tests_glob) {
+        Ok(res) => res,
+        _ => exit_with_code(
+            exitcode::USAGE,
+            Some(&format!("Invalid search glob: `{}`", tests_glob)),
+        ),
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .map(|entry| entry.expect("Invalid path").into_path())
+        .collect();
+    paths.sort();
+
+    // the project config can be in the same folder as the workflows
+    paths.retain(|path| !project_config.is_file(path));
+    paths
+}
+
+/// All values of an argument that can be passed more than once.
+fn values_of(matches: &ArgMatches, name: &str) -> Vec<String> {
+    matches
+        .values_of(name)
+        .map(|values| values.map(String::from).collect())
+        .unwrap_or_default()
 }
@@ -0,0 +1,490 @@
+use crate::{
+    openapi::OpenApiSpec,
+    utils::unique_slugs,
+    workflow::{WorkflowConfig, WorkflowConfigAssertion, WorkflowConfigStep},
+};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+const METHODS: &[&str] = &[
+    "get", "post", "put", "patch", "delete", "head", "options", "trace",
+];
+
+/// A workflow created from the spec, and the file it should be saved to.
+pub struct GeneratedWorkflow {
+    pub path: String,
+    pub config: WorkflowConfig,
+}
+
+/// Create starter workflows from a spec.
+///
+/// Operations are grouped by their first tag, or by the first part of
+/// the path if they don't have any tags, and every group becomes a
+/// workflow with one step per operation. The workflows and their steps
+/// keep the order the spec lists the paths and methods in. The steps use
+/// `${{ env.URL }}` as the base url, examples from the spec for parameters
+/// and bodies, and assert on the first successful status the spec declares.
+pub fn generate_workflows(spec: &OpenApiSpec) -> Vec<GeneratedWorkflow> {
+    let document = spec.document();
+    let mut groups: Vec<(String, Vec<WorkflowConfigStep>)> = vec![];
+
+    for (path, keys) in spec.path_order() {
+        let path_item = spec.resolve(&document["paths"][path]);
+
+        // a path item from a `$ref` doesn't have its keys here,
+        // so its methods fall back to the usual order
+        let methods = keys
+            .iter()
+            .map(String::as_str)
+            .filter(|key| METHODS.contains(key))
+            .chain(
+                METHODS
+                    .iter()
+                    .copied()
+                    .filter(|method| !keys.iter().any(|key| key == method)),
+            );
+
+        for method in methods {
+            let operation = match path_item.get(method) {
+                Some(operation) => operation,
+                None => continue,
+            };
+
+            let group = match operation["tags"][0].as_str() {
+                Some(tag) => tag.to_string(),
+                None => path
+                    .split('/')
+                    .find(|segment| !segment.is_empty() && !segment.starts_with('{'))
+                    .unwrap_or("default")
+                    .to_string(),
+            };
+
+            let step = generate_step(spec, path, path_item, method, operation);
+            match groups.iter_mut().find(|(name, _)| *name == group) {
+                Some((_, steps)) => steps.push(step),
+                None => groups.push((group, vec![step])),
+            }
+        }
+    }
+
+    let mut env = BTreeMap::new();
+    env.insert(
+        "URL".to_string(),
+        serde_yaml::Value::String(server_url(document)),
+    );
+
+    // tags like `Users` and `users` would get the same file
+    let names: Vec<&str> = groups.iter().map(|(name, _)| name.as_str()).collect();
+    let slugs = unique_slugs(&names);
+
+    groups
+        .into_iter()
+        .zip(slugs)
+        .map(|((name, steps), slug)| GeneratedWorkflow {
+            path: format!(".capter/{}.yml", slug),
+            config: WorkflowConfig {
+                name,
+                env: Some(env.to_owned()),
+                steps,
+                ..Default::default()
+            },
+        })
+        .collect()
+}
+
+fn generate_step(
+    spec: &OpenApiSpec,
+    path: &str,
+    path_item: &Value,
+    method: &str,
+    operation: &Value,
+) -> WorkflowConfigStep {
+    let name = operation["summary"]
+        .as_str()
+        .or_else(|| operation["operationId"].as_str())
+        .map(String::from)
+        .unwrap_or_else(|| format!("{} {}", method.to_uppercase(), path));
+
+    // parameters can be set on both the path and the operation
+    let parameters: Vec<&Value> = path_item["parameters"]
+        .as_array()
+        .into_iter()
+        .chain(operation["parameters"].as_array())
+        .flatten()
+        .map(|parameter| spec.resolve(parameter))
+        .collect();
+
+    let mut url = path.to_string();
+    let mut query = BTreeMap::new();
+    for parameter in parameters {
+        let name = parameter["name"].as_str().unwrap_or_default();
+        let example = match parameter_example(spec, parameter) {
+            Value::String(string) => string,
+            value => value.to_string(),
+        };
+
+        match parameter["in"].as_str() {
+            Some("path") => url = url.replace(&format!("{{{}}}", name), &example),
+            Some("query") if parameter["required"] == json!(true) => {
+                query.insert(name.to_string(), example);
+            }
+            _ => {}
+        }
+    }
+
+    let body = spec.resolve(&operation["requestBody"])["content"]
+        .as_object()
+        .and_then(|content| {
+            content
+                .iter()
+                .find(|(content_type, _)| content_type.contains("json"))
+                .or_else(|| content.iter().next())
+        })
+        .map(|(_, media_type)| media_type_example(spec, media_type))
+        .and_then(|example| serde_yaml::to_value(example).ok());
+
+    WorkflowConfigStep {
+        name,
+        url: Some(format!(
+            "{} ${{{{ env.URL }}}}{}",
+            method.to_uppercase(),
+            url
+        )),
+        query: match query.is_empty() {
+            true => None,
+            false => Some(query),
+        },
+        body,
+        assertions: vec![WorkflowConfigAssertion::expect(format!(
+            "status to_equal {}",
+            expected_status(&operation["responses"])
+        ))],
+        ..Default::default()
+    }
+}
+
+/// The first successful status declared for an operation, or 200.
+fn expected_status(responses: &Value) -> u16 {
+    let statuses: Vec<u16> = responses
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(status, _)| status.parse().ok())
+        .collect();
+
+    statuses
+        .iter()
+        .filter(|status| (200..300).contains(*status))
+        .min()
+        .or_else(|| statuses.iter().min())
+        .copied()
+        .unwrap_or(200)
+}
+
+fn parameter_example(spec: &OpenApiSpec, parameter: &Value) -> Value {
+    if let Some(example) = parameter.get("example") {
+        return example.to_owned();
+    }
+
+    if let Some(example) = first_example(spec, &parameter["examples"]) {
+        return example;
+    }
+
+    schema_example(spec, &parameter["schema"], 0)
+}
+
+fn media_type_example(spec: &OpenApiSpec, media_type: &Value) -> Value {
+    if let Some(example) = media_type.get("example") {
+        return example.to_owned();
+    }
+
+    if let Some(example) = first_example(spec, &media_type["examples"]) {
+        return example;
+    }
+
+    schema_example(spec, &media_type["schema"], 0)
+}
+
+/// The value of the first entry in an `examples` map.
+fn first_example(spec: &OpenApiSpec, examples: &Value) -> Option<Value> {
+    let (_, example) = examples.as_object()?.iter().next()?;
+    spec.resolve(example).get("value").map(Value::to_owned)
+}
+
+/// Build an example value from a schema, using the examples,
+/// defaults and enums in it when there are any.
+fn schema_example(spec: &OpenApiSpec, schema: &Value, depth: usize) -> Value {
+    let schema = spec.resolve(schema);
+
+    // recursive schemas would never end
+    if depth > 8 {
+        return Value::Null;
+    }
+
+    for key in ["example", "default"] {
+        if let Some(value) = schema.get(key) {
+            return value.to_owned();
+        }
+    }
+
+    if let Some(value) = schema["enum"].get(0) {
+        return value.to_owned();
+    }
+
+    if let Some(schemas) = schema["allOf"].as_array() {
+        let mut object = Map::new();
+        for schema in schemas {
+            if let Value::Object(mut properties) = schema_example(spec, schema, depth + 1) {
+                object.append(&mut properties);
+            }
+        }
+        return Value::Object(object);
+    }
+
+    for key in ["oneOf", "anyOf"] {
+        if let Some(schema) = schema[key].get(0) {
+            return schema_example(spec, schema, depth + 1);
+        }
+    }
+
+    let schema_type = match &schema["type"] {
+        // OpenAPI 3.1 allows a list of types
+        Value::Array(types) => types.iter().find(|t| *t != "null").unwrap_or(&Value::Null),
+        schema_type => schema_type,
+    };
+
+    match schema_type.as_str() {
+        Some("object") | None if schema["properties"].is_object() => {
+            let properties = schema["properties"].as_object().into_iter().flatten();
+            let object = properties
+                .map(|(key, property)| (key.to_owned(), schema_example(spec, property, depth + 1)))
+                .collect();
+            Value::Object(object)
+        }
+        Some("object") => json!({}),
+        Some("array") => json!([schema_example(spec, &schema["items"], depth + 1)]),
+        Some("integer") | Some("number") => schema.get("minimum").cloned().unwrap_or(json!(1)),
+        Some("boolean") => json!(true),
+        Some("string") => json!(match schema["format"].as_str() {
+            Some("date-time") => "2021-01-01T00:00:00Z",
+            Some("date") => "2021-01-01",
+            Some("email") => "test@example.com",
+            Some("uuid") => "00000000-0000-0000-0000-000000000000",
+            Some("uri") => "https://example.com",
+            _ => "string",
+        }),
+        _ => Value::Null,
+    }
+}
+
+/// The url of the first server, or localhost if there are none.
+fn server_url(document: &Value) -> String {
+    let server = &document["servers"][0];
+    let mut url = server["url"]
+        .as_str()
+        .unwrap_or("http://localhost")
+        .to_string();
+
+    // server urls can have variables like `{version}`
+    let variables = server["variables"].as_object().into_iter().flatten();
+    for (name, variable) in variables {
+        if let Some(default) = variable["default"].as_str() {
+            url = url.replace(&format!("{{{}}}", name), default);
+        }
+    }
+
+    url.trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const SPEC: &str = indoc! {"
+        openapi: 3.0.0
+        info:
+          title: users
+          version: 1.0.0
+        servers:
+          - url: https://{env}.example.com/v1/
+            variables:
+              env:
+                default: api
+        paths:
+          /users:
+            post:
+              tags: [User Accounts]
+              summary: create user
+              requestBody:
+                content:
+                  application/json:
+                    schema:
+                      $ref: '#/components/schemas/user'
+              responses:
+                '400':
+                  description: bad request
+                '201':
+                  description: created
+          /users/{id}:
+            parameters:
+              - name: id
+                in: path
+                required: true
+                schema:
+                  type: integer
+                  example: 42
+            get:
+              tags: [User Accounts]
+              operationId: getUser
+              parameters:
+                - name: fields
+                  in: query
+                  required: true
+                  example: name
+              responses:
+                '200':
+                  description: a user
+          /health:
+            get:
+              responses:
+                default:
+                  description: ok
+        components:
+          schemas:
+            user:
+              type: object
+              properties:
+                name:
+                  type: string
+                email:
+                  type: string
+                  format: email
+                role:
+                  enum: [admin, user]
+                tags:
+                  type: array
+                  items:
+                    type: string
+    "};
+
+    #[test]
+    fn test_generate_workflows() {
+        let spec = OpenApiSpec::from_yaml(SPEC).unwrap();
+        let workflows = generate_workflows(&spec);
+
+        let paths: Vec<&str> = workflows.iter().map(|w| w.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![".capter/user-accounts.yml", ".capter/health.yml"]
+        );
+
+        let users = &workflows[0].config;
+        assert_eq!(users.name, "User Accounts");
+        assert_eq!(
+            users.env.to_owned().unwrap()["URL"],
+            serde_yaml::Value::String("https://api.example.com/v1".to_string())
+        );
+
+        let create = &users.steps[0];
+        assert_eq!(create.name, "create user");
+        assert_eq!(create.url, Some("POST ${{ env.URL }}/users".to_string()));
+        assert_eq!(
+            serde_json::to_value(&create.body).unwrap(),
+            json!({
+                "name": "string",
+                "email": "test@example.com",
+                "role": "admin",
+                "tags": ["string"]
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&create.assertions).unwrap(),
+            json!([{ "expect": "status to_equal 201" }])
+        );
+
+        let get = &users.steps[1];
+        assert_eq!(get.name, "getUser");
+        assert_eq!(get.url, Some("GET ${{ env.URL }}/users/42".to_string()));
+        assert_eq!(get.query.to_owned().unwrap()["fields"], "name");
+
+        let health = &workflows[1].config.steps[0];
+        assert_eq!(health.name, "GET /health");
+        assert!(health.body.is_none());
+    }
+
+    #[test]
+    fn test_same_names_and_query_order() {
+        let spec = OpenApiSpec::from_yaml(indoc! {"
+            paths:
+              /users:
+                get:
+                  tags: [Users]
+                  parameters:
+                    - { name: sort, in: query, required: true, example: name }
+                    - { name: page, in: query, required: true, example: 1 }
+                    - { name: limit, in: query, required: true, example: 10 }
+                  responses:
+                    '200':
+                      description: users
+              /users/me:
+                get:
+                  tags: [users]
+                  responses:
+                    '200':
+                      description: the current user
+        "})
+        .unwrap();
+        let workflows = generate_workflows(&spec);
+
+        let paths: Vec<&str> = workflows.iter().map(|w| w.path.as_str()).collect();
+        assert_eq!(paths, vec![".capter/users.yml", ".capter/users-2.yml"]);
+
+        // the query is written in the same order every time
+        let yaml = workflows[0].config.to_yaml().unwrap();
+        assert!(yaml.contains("query:\n      limit: \"10\"\n      page: \"1\"\n      sort: name\n"));
+    }
+
+    #[test]
+    fn test_spec_order() {
+        let spec = OpenApiSpec::from_yaml(indoc! {"
+            paths:
+              /users:
+                post:
+                  summary: create user
+                  responses:
+                    '201':
+                      description: created
+                get:
+                  summary: list users
+                  responses:
+                    '200':
+                      description: users
+              /orders:
+                get:
+                  summary: list orders
+                  responses:
+                    '200':
+                      description: orders
+              /users/{id}:
+                delete:
+                  summary: delete user
+                  responses:
+                    '204':
+                      description: deleted
+        "})
+        .unwrap();
+        let workflows = generate_workflows(&spec);
+
+        let paths: Vec<&str> = workflows.iter().map(|w| w.path.as_str()).collect();
+        assert_eq!(paths, vec![".capter/users.yml", ".capter/orders.yml"]);
+
+        let steps: Vec<&str> = workflows[0]
+            .config
+            .steps
+            .iter()
+            .map(|step| step.name.as_str())
+            .collect();
+        assert_eq!(steps, vec!["create user", "list users", "delete user"]);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod generate;
+pub mod spec;
+
+pub use generate::generate_workflows;
+pub use spec::OpenApiSpec;
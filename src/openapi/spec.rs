@@ -0,0 +1,614 @@
+use crate::{
+    assert::{assert::AssertionTest, schema::validate_schema, AssertionResultData},
+    workflow::ResponseData,
+};
+use serde_json::{json, Value};
+use std::fs::read_to_string;
+
+/// The name of the test in the `AssertionResultData` we create
+/// when checking a response against the spec.
+pub const OPENAPI_TEST: &str = "to_match_openapi";
+
+/// An OpenAPI 3 document, used to check that responses
+/// match what the API says it returns.
+///
+/// Every request is matched to an operation by its method and path,
+/// and the response status, headers and body are validated against
+/// the response declared for that operation.
+#[derive(Debug, Clone)]
+pub struct OpenApiSpec {
+    document: Value,
+    /// The paths and the keys of each path item in the order the spec
+    /// lists them, as the keys in `document` are sorted.
+    path_order: Vec<(String, Vec<String>)>,
+}
+
+/// An operation in the spec that matched a request.
+struct Operation<'a> {
+    name: String,
+    operation: &'a Value,
+}
+
+impl OpenApiSpec {
+    /// Read a spec from a `.yaml` or `.json` file.
+    pub fn from_file(path: &str) -> Result<OpenApiSpec, String> {
+        let content = read_to_string(path)
+            .map_err(|err| format!("Failed to read OpenAPI spec {}: {}", path, err))?;
+
+        OpenApiSpec::from_yaml(&content)
+            .map_err(|err| format!("Failed to parse OpenAPI spec {}: {}", path, err))
+    }
+
+    /// Parse a spec. JSON is valid YAML, so this handles both.
+    pub fn from_yaml(content: &str) -> Result<OpenApiSpec, String> {
+        let document: Value = serde_yaml::from_str(content).map_err(|err| err.to_string())?;
+
+        if !document["paths"].is_object() {
+            return Err("missing `paths`".to_string());
+        }
+
+        let mapping: serde_yaml::Value =
+            serde_yaml::from_str(content).map_err(|err| err.to_string())?;
+        let path_order = mapping["paths"]
+            .as_mapping()
+            .into_iter()
+            .flatten()
+            .filter_map(|(path, path_item)| {
+                let keys = path_item
+                    .as_mapping()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(key, _)| key.as_str().map(String::from))
+                    .collect();
+                Some((path.as_str()?.to_string(), keys))
+            })
+            .collect();
+
+        Ok(OpenApiSpec {
+            document,
+            path_order,
+        })
+    }
+
+    /// Check a response against the operation matching `method` and `url`.
+    ///
+    /// Returns one `AssertionResultData` for the status, one for
+    /// every declared header and one for the body.
+    pub fn check(
+        &self,
+        method: &str,
+        url: &str,
+        response: &ResponseData,
+    ) -> Vec<AssertionResultData> {
+        let path = url_path(url);
+
+        let operation = match self.find_operation(method, &path) {
+            Ok(operation) => operation,
+            Err(message) => {
+                return vec![result(
+                    "path",
+                    &format!("{} {}", method, path),
+                    Some(message),
+                )]
+            }
+        };
+
+        // no status means we never got a response, which
+        // the normal assertions will already tell the user about
+        let status = match response.status {
+            Some(status) => status,
+            None => return vec![],
+        };
+
+        let declared = match self.find_response(operation.operation, status) {
+            Some(declared) => declared,
+            None => {
+                let message = format!("status {} is not declared in the spec", status);
+                return vec![result("status", &operation.name, Some(message))];
+            }
+        };
+
+        let mut results = vec![result("status", &operation.name, None)];
+        results.append(&mut self.check_headers(&operation.name, declared, response));
+        if let Some(body) = self.check_body(&operation.name, declared, response) {
+            results.push(body);
+        }
+
+        results
+    }
+
+    fn find_operation(&self, method: &str, path: &str) -> Result<Operation<'_>, String> {
+        let method = method.to_lowercase();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        // servers can add a prefix to every path, like `/v1`
+        let mut prefixes: Vec<String> = self.document["servers"]
+            .as_array()
+            .map(|servers| {
+                servers
+                    .iter()
+                    .filter_map(|server| server["url"].as_str())
+                    .map(url_path)
+                    .collect()
+            })
+            .unwrap_or_default();
+        prefixes.push(String::new());
+
+        // literal segments are more specific than templates, so
+        // `/users/me` is picked over `/users/{id}`
+        let mut best_match: Option<(usize, &str, &Value)> = None;
+        let paths = self.document["paths"].as_object().into_iter().flatten();
+        for (template, path_item) in paths {
+            for prefix in &prefixes {
+                let full_template = format!("{}{}", prefix.trim_end_matches('/'), template);
+                if let Some(score) = match_template(&full_template, &segments) {
+                    if best_match.is_none_or(|(best_score, _, _)| score > best_score) {
+                        best_match = Some((score, template, path_item));
+                    }
+                }
+            }
+        }
+
+        let (template, path_item) = match best_match {
+            Some((_, template, path_item)) => (template, self.resolve(path_item)),
+            None => return Err(format!("no path in the spec matches {}", path)),
+        };
+
+        let name = format!("{} {}", method.to_uppercase(), template);
+        match path_item.get(&method) {
+            Some(operation) => Ok(Operation { name, operation }),
+            None => Err(format!("{} is not declared in the spec", name)),
+        }
+    }
+
+    /// Find the response for a status. `200` is picked before `2XX`,
+    /// and `default` is used if neither is declared.
+    fn find_response<'a>(&'a self, operation: &'a Value, status: u16) -> Option<&'a Value> {
+        let responses = &operation["responses"];
+        let status = status.to_string();
+        let range = format!("{}XX", &status[..1]);
+
+        [
+            status.as_str(),
+            range.as_str(),
+            &range.to_lowercase(),
+            "default",
+        ]
+        .iter()
+        .find_map(|key| responses.get(*key))
+        .map(|response| self.resolve(response))
+    }
+
+    fn check_headers(
+        &self,
+        name: &str,
+        declared: &Value,
+        response: &ResponseData,
+    ) -> Vec<AssertionResultData> {
+        let mut results = vec![];
+        let headers = declared["headers"].as_object().into_iter().flatten();
+
+        for (header_name, header) in headers {
+            let header = self.resolve(header);
+            let property = format!("headers.{}", header_name.to_lowercase());
+
+            let value = response.headers.as_object().and_then(|headers| {
+                headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(header_name))
+                    .map(|(_, value)| value)
+            });
+
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    if header["required"] == json!(true) {
+                        let message = format!("missing required header `{}`", header_name);
+                        results.push(result(&property, name, Some(message)));
+                    }
+                    continue;
+                }
+            };
+
+            // header values are always strings, so we parse them
+            // in case the schema expects a number or a boolean
+            let schema = &header["schema"];
+            let value = match (schema["type"].as_str(), value.as_str()) {
+                (Some("integer" | "number" | "boolean"), Some(string)) => {
+                    serde_json::from_str(string).unwrap_or_else(|_| value.to_owned())
+                }
+                _ => value.to_owned(),
+            };
+
+            let errors = validate_schema(&value, schema, &self.document);
+            results.push(result(&property, name, to_message(errors)));
+        }
+
+        results
+    }
+
+    fn check_body(
+        &self,
+        name: &str,
+        declared: &Value,
+        response: &ResponseData,
+    ) -> Option<AssertionResultData> {
+        let content = declared["content"].as_object()?;
+
+        let content_type = response
+            .headers
+            .as_object()
+            .and_then(|headers| {
+                headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+            })
+            .and_then(|(_, value)| value.as_str())
+            .map(|value| value.split(';').next().unwrap_or("").trim().to_lowercase());
+
+        let body = response.body.to_owned().unwrap_or(Value::Null);
+
+        // no body and no content type means there is nothing to check
+        let content_type = match content_type {
+            Some(content_type) => content_type,
+            None if body.is_null() => return None,
+            None => "application/json".to_string(),
+        };
+
+        let wildcard = format!("{}/*", content_type.split('/').next().unwrap_or(""));
+        let media_type = [content_type.as_str(), wildcard.as_str(), "*/*"]
+            .iter()
+            .find_map(|key| content.get(*key));
+
+        let media_type = match media_type {
+            Some(media_type) => media_type,
+            None => {
+                let message = format!("content type {} is not declared in the spec", content_type);
+                return Some(result("body", name, Some(message)));
+            }
+        };
+
+        // we can only validate bodies we could decode, binary
+        // bodies only have a hash
+        let schema = media_type.get("schema")?;
+        if response.hash.is_some() {
+            return None;
+        }
+
+        let errors = validate_schema(&body, schema, &self.document);
+        Some(result("body", name, to_message(errors)))
+    }
+
+    /// The parsed spec.
+    pub fn document(&self) -> &Value {
+        &self.document
+    }
+
+    /// The paths in the order the spec lists them,
+    /// with the keys of each path item in the same order.
+    pub fn path_order(&self) -> &[(String, Vec<String>)] {
+        &self.path_order
+    }
+
+    /// Follow `$ref`s to components, like `#/components/responses/NotFound`.
+    pub fn resolve<'a>(&'a self, value: &'a Value) -> &'a Value {
+        let mut value = value;
+
+        // limit the number of jumps, in case a `$ref` points at itself
+        for _ in 0..16 {
+            let pointer = match value["$ref"].as_str().and_then(|r| r.strip_prefix('#')) {
+                Some(pointer) => pointer,
+                None => break,
+            };
+
+            match self.document.pointer(pointer) {
+                Some(resolved) => value = resolved,
+                None => break,
+            }
+        }
+
+        value
+    }
+}
+
+/// Match a path template like `/users/{id}` against the segments of a path.
+///
+/// Returns the number of literal segments that matched,
+/// or None if the path doesn't match the template.
+fn match_template(template: &str, segments: &[&str]) -> Option<usize> {
+    let template: Vec<&str> = template.split('/').filter(|s| !s.is_empty()).collect();
+    if template.len() != segments.len() {
+        return None;
+    }
+
+    let mut score = 0;
+    for (template, segment) in template.iter().zip(segments) {
+        if template.starts_with('{') && template.ends_with('}') {
+            continue;
+        }
+
+        if template != segment {
+            return None;
+        }
+
+        score += 1;
+    }
+
+    Some(score)
+}
+
+/// Get the path from a url, without the host, query or fragment.
+fn url_path(url: &str) -> String {
+    let without_host = match url.find("://") {
+        Some(index) => {
+            let rest = &url[index + 3..];
+            match rest.find('/') {
+                Some(index) => &rest[index..],
+                None => "/",
+            }
+        }
+        None => url,
+    };
+
+    let path = without_host
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("/")
+        .to_string();
+
+    match path.is_empty() {
+        true => "/".to_string(),
+        false => path,
+    }
+}
+
+fn to_message(errors: Vec<crate::assert::schema::SchemaError>) -> Option<String> {
+    if errors.is_empty() {
+        return None;
+    }
+
+    let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+    Some(format!(
+        "expected value to match the spec, but got {} error{}:\n{}",
+        errors.len(),
+        match errors.len() {
+            1 => "",
+            _ => "s",
+        },
+        errors.join("\n")
+    ))
+}
+
+fn result(property: &str, operation: &str, message: Option<String>) -> AssertionResultData {
+    AssertionResultData {
+        passed: message.is_none(),
+        message,
+        assertion: AssertionTest {
+            test: OPENAPI_TEST.to_string(),
+            property: property.to_string(),
+            value: json!(operation),
+            not: false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const SPEC: &str = indoc! {"
+        openapi: 3.0.0
+        info:
+          title: users
+          version: 1.0.0
+        servers:
+          - url: https://api.example.com/v1
+        paths:
+          /users/{id}:
+            get:
+              responses:
+                '200':
+                  description: a user
+                  headers:
+                    x-rate-limit:
+                      required: true
+                      schema:
+                        type: integer
+                  content:
+                    application/json:
+                      schema:
+                        $ref: '#/components/schemas/user'
+                '404':
+                  $ref: '#/components/responses/not_found'
+          /users/me:
+            get:
+              responses:
+                2XX:
+                  description: the current user
+        components:
+          schemas:
+            user:
+              type: object
+              required: [id, name]
+              properties:
+                id:
+                  type: integer
+                name:
+                  type: string
+          responses:
+            not_found:
+              description: not found
+    "};
+
+    fn response(status: u16, headers: Value, body: Value) -> ResponseData {
+        ResponseData {
+            status: Some(status),
+            headers,
+            body: Some(body),
+            ..Default::default()
+        }
+    }
+
+    fn messages(results: Vec<AssertionResultData>) -> Vec<(String, Option<String>)> {
+        results
+            .into_iter()
+            .map(|result| (result.assertion.property, result.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_matching_response() {
+        let spec = OpenApiSpec::from_yaml(SPEC).unwrap();
+        let response = response(
+            200,
+            json!({ "content-type": "application/json; charset=utf-8", "x-rate-limit": "10" }),
+            json!({ "id": 1, "name": "Test McTest" }),
+        );
+
+        let results = spec.check("GET", "https://api.example.com/v1/users/1?a=b", &response);
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|result| result.passed));
+        assert_eq!(results[0].assertion.value, json!("GET /users/{id}"));
+    }
+
+    #[test]
+    fn test_drift() {
+        let spec = OpenApiSpec::from_yaml(SPEC).unwrap();
+        let user = response(
+            200,
+            json!({ "content-type": "application/json" }),
+            json!({ "id": "1" }),
+        );
+
+        assert_eq!(
+            messages(spec.check("GET", "http://localhost/v1/users/1", &user)),
+            vec![
+                ("status".to_string(), None),
+                (
+                    "headers.x-rate-limit".to_string(),
+                    Some("missing required header `x-rate-limit`".to_string())
+                ),
+                (
+                    "body".to_string(),
+                    Some(
+                        "expected value to match the spec, but got 2 errors:\n\
+                         /: missing required property `name`\n\
+                         /id: expected integer but got string"
+                            .to_string()
+                    )
+                ),
+            ]
+        );
+
+        let error = response(500, json!({}), Value::Null);
+        assert_eq!(
+            messages(spec.check("GET", "http://localhost/v1/users/1", &error)),
+            vec![(
+                "status".to_string(),
+                Some("status 500 is not declared in the spec".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn test_operations() {
+        let spec = OpenApiSpec::from_yaml(SPEC).unwrap();
+
+        let results = spec.check(
+            "GET",
+            "http://localhost/users/me",
+            &response(204, json!({}), Value::Null),
+        );
+        assert_eq!(results[0].assertion.value, json!("GET /users/me"));
+        assert!(results[0].passed);
+
+        let results = spec.check(
+            "GET",
+            "http://localhost/users/1",
+            &response(404, json!({}), Value::Null),
+        );
+        assert!(results[0].passed);
+
+        assert_eq!(
+            messages(spec.check(
+                "DELETE",
+                "http://localhost/users/1",
+                &response(200, json!({}), Value::Null)
+            )),
+            vec![(
+                "path".to_string(),
+                Some("DELETE /users/{id} is not declared in the spec".to_string())
+            )]
+        );
+        assert_eq!(
+            messages(spec.check(
+                "GET",
+                "http://localhost/posts",
+                &response(200, json!({}), Value::Null)
+            )),
+            vec![(
+                "path".to_string(),
+                Some("no path in the spec matches /posts".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn test_numeric_status_keys() {
+        let spec = OpenApiSpec::from_yaml(indoc! {"
+            paths:
+              /health:
+                get:
+                  responses:
+                    200:
+                      description: ok
+        "})
+        .unwrap();
+
+        let results = spec.check(
+            "GET",
+            "http://localhost/health",
+            &response(200, json!({}), Value::Null),
+        );
+        assert!(results[0].passed);
+    }
+
+    #[test]
+    fn test_binary_response() {
+        let spec = OpenApiSpec::from_yaml(indoc! {"
+            paths:
+              /users/{id}/avatar:
+                get:
+                  responses:
+                    '200':
+                      description: the avatar
+                      content:
+                        image/png:
+                          schema:
+                            type: string
+                            format: binary
+        "})
+        .unwrap();
+
+        // like a decoded binary body, with only the size and the hash
+        let avatar = ResponseData {
+            status: Some(200),
+            headers: json!({ "content-type": "image/png" }),
+            body: Some(Value::Null),
+            size: Some(4),
+            hash: Some("a1b2".to_string()),
+            ..Default::default()
+        };
+
+        let results = spec.check("GET", "http://localhost/users/1/avatar", &avatar);
+        assert_eq!(messages(results), vec![("status".to_string(), None)]);
+    }
+
+    #[test]
+    fn test_url_path() {
+        assert_eq!(url_path("https://api.com"), "/");
+        assert_eq!(url_path("https://api.com/a/b?c=d#e"), "/a/b");
+        assert_eq!(url_path("/v1"), "/v1");
+    }
+}
@@ -0,0 +1,398 @@
+use crate::{
+    error::CapterError,
+    workflow::{WorkflowConfig, WorkflowConfigStepOptions},
+};
+use path_clean::PathClean;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+/// Where we look for the project config, in this order.
+pub const PROJECT_CONFIG_FILES: &[&str] = &["capter.yml", ".capter/config.yml"];
+
+/// Environments can also be files in this folder, like `.capter/env/staging.yml`.
+pub const ENVIRONMENTS_DIR: &str = ".capter/env";
+
+/// Settings for every run in a project, from `capter.yml`
+/// or `.capter/config.yml`.
+///
+/// Everything is optional, and flags on the command line
+/// always win over what's set here. `url`, `headers`, `env`
+/// and `mask` are defaults for every workflow, so a workflow
+/// can still set its own.
+#[derive(Debug, Default, Deserialize)]
+pub struct ProjectConfig {
+    /// The file the config was read from.
+    #[serde(skip)]
+    pub file: Option<String>,
+    /// Glob pattern where to look for workflows.
+    pub input: Option<String>,
+    pub timeout: Option<u64>,
+    pub jobs: Option<usize>,
+    pub token: Option<String>,
+    pub webhook: Option<String>,
+    pub reporter: Option<String>,
+    pub output: Option<String>,
+    pub openapi: Option<String>,
+    pub setup: Option<String>,
+    pub teardown: Option<String>,
+    /// Base url for steps with a relative url, like `/users`.
+    pub url: Option<String>,
+    pub headers: Option<BTreeMap<String, serde_yaml::Value>>,
+    pub env: Option<BTreeMap<String, serde_yaml::Value>>,
+    /// `.env` files to load, instead of just `.env`.
+    pub env_files: Option<Vec<String>>,
+    /// Keys to mask in every response.
+    pub mask: Option<Vec<String>>,
+    /// Named sets of env variables, like `staging` or `production`.
+    pub environments: Option<BTreeMap<String, BTreeMap<String, serde_yaml::Value>>>,
+    /// The environment to use from `environments`.
+    pub environment: Option<String>,
+}
+
+impl ProjectConfig {
+    /// Read the project config in the current directory, or
+    /// use an empty one if there is none.
+    ///
+    /// Returns an error if the file exists but can't be parsed.
+    pub fn load() -> Result<ProjectConfig, CapterError> {
+        let path = PROJECT_CONFIG_FILES
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file());
+
+        match path {
+            Some(path) => ProjectConfig::from_yaml_file(&path),
+            None => Ok(ProjectConfig::default()),
+        }
+    }
+
+    pub fn from_yaml_file(path: &Path) -> Result<ProjectConfig, CapterError> {
+        let path = path.to_path_buf().clean().to_string_lossy().to_string();
+
+        let yaml = read_to_string(&path)
+            .map_err(|_| CapterError::Config(format!("Failed to read {}", path)))?;
+
+        let mut project_config = ProjectConfig::from_yaml(&yaml).map_err(|err| {
+            CapterError::Config(format!("Failed to parse {}: {}", path, &err.to_string()))
+        })?;
+        project_config.file = Some(path);
+
+        Ok(project_config)
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<ProjectConfig, serde_yaml::Error> {
+        // an empty file is an empty config
+        if yaml.trim().is_empty() {
+            return Ok(ProjectConfig::default());
+        }
+
+        serde_yaml::from_str(yaml)
+    }
+
+    /// Select the environment to run with.
+    ///
+    /// The variables come from `.env.{name}`, `.capter/env/{name}.yml`
+    /// and `environments` in the config, in that order, so the
+    /// config wins if a variable is set in more than one.
+    /// Variables from `.env.{name}` are in the process env, so
+    /// like with `.env`, the shell wins over the file.
+    ///
+    /// If the environment isn't found anywhere, `env()` returns an error.
+    pub fn set_environment(&mut self, name: &str) -> Result<(), CapterError> {
+        let mut env = BTreeMap::new();
+        let mut is_found = false;
+
+        // these are added to the process env, like `.env`, so
+        // this has to be done before `.env` is read
+        let dotenv_file = format!(".env.{}", name);
+        if Path::new(&dotenv_file).is_file() {
+            is_found = true;
+            dotenv::from_filename(&dotenv_file).map_err(|err| {
+                CapterError::Config(format!("Failed to read {}: {}", dotenv_file, err))
+            })?;
+        }
+
+        let yaml_file = Path::new(ENVIRONMENTS_DIR).join(format!("{}.yml", name));
+        if yaml_file.is_file() {
+            is_found = true;
+            let vars = read_to_string(&yaml_file)
+                .map_err(|err| err.to_string())
+                .and_then(|yaml| {
+                    serde_yaml::from_str::<Option<BTreeMap<String, serde_yaml::Value>>>(&yaml)
+                        .map_err(|err| err.to_string())
+                });
+            let vars = vars.map_err(|err| {
+                CapterError::Config(format!(
+                    "Failed to parse {}: {}",
+                    yaml_file.to_string_lossy(),
+                    err
+                ))
+            })?;
+            env.extend(vars.unwrap_or_default());
+        }
+
+        let environments = self.environments.get_or_insert_with(BTreeMap::new);
+        if let Some(environment) = environments.get(name) {
+            is_found = true;
+            env.extend(environment.to_owned());
+        }
+
+        // leave it to `env()` to tell which environments there are
+        if is_found {
+            environments.insert(name.to_string(), env);
+        }
+        self.environment = Some(name.to_string());
+
+        Ok(())
+    }
+
+    /// The env variables for every workflow, from `env`
+    /// and the selected environment.
+    ///
+    /// Returns an error if the selected environment doesn't exist.
+    pub fn env(&self) -> Result<BTreeMap<String, serde_yaml::Value>, CapterError> {
+        let mut env = self.env.to_owned().unwrap_or_default();
+
+        if let Some(name) = &self.environment {
+            let environment = self
+                .environments
+                .as_ref()
+                .and_then(|environments| environments.get(name));
+            match environment {
+                Some(environment) => env.extend(environment.to_owned()),
+                None => {
+                    return Err(CapterError::Config(format!(
+                        "Unknown environment `{}`, use one of: {}",
+                        name,
+                        self.environment_names().join(", ")
+                    )))
+                }
+            }
+        }
+
+        Ok(env)
+    }
+
+    /// Every environment in the config and in the environment files.
+    fn environment_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .environments
+            .iter()
+            .flat_map(|environments| environments.keys().cloned())
+            .collect();
+
+        let files = [(ENVIRONMENTS_DIR, "", ".yml"), (".", ".env.", "")];
+        for (dir, prefix, suffix) in files {
+            let entries = read_dir(dir).into_iter().flatten().flatten();
+            for entry in entries {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let name = file_name
+                    .strip_prefix(prefix)
+                    .and_then(|name| name.strip_suffix(suffix));
+                if let Some(name) = name {
+                    names.push(name.to_string());
+                }
+            }
+        }
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Add the project defaults to a workflow. Anything
+    /// set on the workflow is kept as it is.
+    ///
+    /// Variables set in the shell win over the ones in the project,
+    /// the same way they win over the ones in `.env` files.
+    pub fn apply(&self, config: &mut WorkflowConfig) -> Result<(), CapterError> {
+        let mut env = self.env()?;
+        env.retain(|key, _| std::env::var_os(key).is_none());
+        if let Some(workflow_env) = config.env.take() {
+            env.extend(workflow_env);
+        }
+        if !env.is_empty() {
+            config.env = Some(env);
+        }
+
+        if let Some(headers) = &self.headers {
+            let mut all_headers = headers.to_owned();
+            if let Some(workflow_headers) = config.headers.take() {
+                all_headers.extend(workflow_headers);
+            }
+            config.headers = Some(all_headers);
+        }
+
+        if config.base_url.is_none() {
+            config.base_url = self.url.to_owned();
+        }
+
+        if let Some(mask) = &self.mask {
+            for step in config.steps.iter_mut() {
+                let options = step
+                    .options
+                    .get_or_insert(WorkflowConfigStepOptions { mask: None });
+                let step_mask = options.mask.get_or_insert_with(Vec::new);
+                for key in mask {
+                    if !step_mask.contains(key) {
+                        step_mask.push(key.to_owned());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if `path` is this config file, or an
+    /// environment file, so it isn't read as a workflow.
+    pub fn is_file(&self, path: &PathBuf) -> bool {
+        let path = path.clean();
+        self.file.as_deref() == path.to_str() || path.starts_with(ENVIRONMENTS_DIR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::{workflow_result::WorkflowResult, RunOptions};
+    use indoc::indoc;
+    use mockito::mock;
+
+    #[test]
+    fn test_apply() {
+        let project_config = ProjectConfig::from_yaml(indoc! {"
+            url: http://localhost
+            headers:
+              x-client: capter
+              x-version: 1
+            env:
+              USER: test
+              TOKEN: default
+            environments:
+              staging:
+                TOKEN: staging
+            environment: staging
+            mask:
+              - token
+        "})
+        .unwrap();
+
+        let mut config = WorkflowConfig::from_yaml(
+            indoc! {"
+                ---
+                name: test
+                headers:
+                  x-version: 2
+                env:
+                  USER: workflow
+                steps:
+                  - name: step 1
+                    url: /users
+                    options:
+                      mask:
+                        - password
+                    assertions: []
+            "}
+            .to_string(),
+        )
+        .unwrap();
+        project_config.apply(&mut config).unwrap();
+
+        let env = config.env.unwrap();
+        assert_eq!(env["USER"], "workflow");
+        assert_eq!(env["TOKEN"], "staging");
+
+        let headers = config.headers.unwrap();
+        assert_eq!(headers["x-client"], "capter");
+        assert_eq!(headers["x-version"], 2);
+
+        assert_eq!(config.base_url, Some("http://localhost".to_string()));
+        assert_eq!(
+            config.steps[0].options.as_ref().unwrap().mask,
+            Some(vec!["password".to_string(), "token".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_shell_env() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/project/shell").with_status(200).create();
+        std::env::set_var("CAPTER_PROJECT_URL", url);
+
+        let project_config = ProjectConfig::from_yaml(indoc! {"
+            env:
+              CAPTER_PROJECT_URL: http://localhost:1
+              CAPTER_PROJECT_USER: project
+        "})
+        .unwrap();
+        let mut config = WorkflowConfig::from_yaml(
+            indoc! {"
+                ---
+                name: test
+                steps:
+                  - name: step 1
+                    url: ${{ env.CAPTER_PROJECT_URL }}/project/shell
+                    assertions:
+                      - !expect status to_equal 200
+            "}
+            .to_string(),
+        )
+        .unwrap();
+        project_config.apply(&mut config).unwrap();
+
+        // `URL=... capter test` is used, not the url in capter.yml
+        let env = config.env.to_owned().unwrap();
+        assert_eq!(env.get("CAPTER_PROJECT_URL"), None);
+        assert_eq!(env["CAPTER_PROJECT_USER"], "project");
+
+        let result = WorkflowResult::from_config(&RunOptions::default(), &config, |_| {}).unwrap();
+        assert!(result.passed);
+    }
+
+    #[test]
+    fn test_set_environment() {
+        let mut project_config = ProjectConfig::from_yaml(indoc! {"
+            env:
+              URL: http://localhost
+            environments:
+              staging:
+                URL: https://staging.capter.io
+        "})
+        .unwrap();
+        project_config.set_environment("staging").unwrap();
+
+        assert_eq!(
+            project_config.env().unwrap()["URL"],
+            "https://staging.capter.io"
+        );
+        assert!(project_config.is_file(&PathBuf::from("./.capter/env/staging.yml")));
+        assert!(!project_config.is_file(&PathBuf::from(".capter/users.yml")));
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        let path = std::env::temp_dir().join("capter-invalid-project.yml");
+        std::fs::write(&path, "jobs: many").unwrap();
+
+        let error = ProjectConfig::from_yaml_file(&path).unwrap_err();
+        assert!(error.to_string().starts_with("Failed to parse"));
+    }
+
+    #[test]
+    fn test_empty() {
+        let project_config = ProjectConfig::from_yaml("").unwrap();
+        assert!(project_config.input.is_none());
+    }
+
+    #[test]
+    fn test_unknown_environment() {
+        let project_config = ProjectConfig::from_yaml("environment: nope").unwrap();
+        let error = project_config.env().unwrap_err();
+        assert!(error.to_string().starts_with("Unknown environment `nope`"));
+    }
+}
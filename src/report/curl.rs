@@ -0,0 +1,160 @@
+use crate::{
+    report::Reporter,
+    workflow::{workflow_result::WorkflowResult, RequestData, RunSource},
+};
+use std::io::{self, Write};
+
+/// Writes every request in the run as a curl command, so a
+/// failing step can be reproduced outside of capter.
+///
+/// The requests are masked, so masked values will show up as `****`
+/// and have to be filled in by hand.
+pub struct CurlReporter {
+    output: Box<dyn Write>,
+}
+
+impl CurlReporter {
+    pub fn new(output: Box<dyn Write>) -> CurlReporter {
+        CurlReporter { output }
+    }
+}
+
+impl Reporter for CurlReporter {
+    fn finish(&mut self, _source: &RunSource, workflow_runs: &[WorkflowResult]) -> io::Result<()> {
+        for workflow_run in workflow_runs {
+            for request in &workflow_run.requests {
+                let passed = request
+                    .response
+                    .as_ref()
+                    .is_some_and(|response| response.assertion_results.iter().all(|r| r.passed));
+
+                writeln!(
+                    self.output,
+                    "# {} / {}{}",
+                    workflow_run.name,
+                    request.name,
+                    match passed {
+                        true => "",
+                        false => " (failed)",
+                    }
+                )?;
+                writeln!(self.output, "{}\n", to_curl(request))?;
+            }
+        }
+
+        self.output.flush()
+    }
+}
+
+/// Create a curl command from a request.
+pub fn to_curl(request: &RequestData) -> String {
+    let mut parts = vec![format!(
+        "curl -X {} {}",
+        request.method,
+        quote(&request.full_url())
+    )];
+
+    let headers = request.header_pairs();
+    for (name, value) in &headers {
+        parts.push(format!("-H {}", quote(&format!("{}: {}", name, value))));
+    }
+
+    match &request.body {
+        Some(serde_yaml::Value::Null) | None => {}
+        Some(serde_yaml::Value::String(body)) => {
+            parts.push(format!("--data-raw {}", quote(body)));
+        }
+        Some(body) => {
+            // bodies that are not strings are sent as JSON
+            let has_content_type = headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+            if !has_content_type {
+                parts.push(format!("-H {}", quote("Content-Type: application/json")));
+            }
+
+            let body = serde_json::to_string(body).unwrap_or_default();
+            parts.push(format!("--data-raw {}", quote(&body)));
+        }
+    }
+
+    parts.join(" \\\n  ")
+}
+
+/// Quote a value so it can be used as one argument in a shell.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::{RunOptions, WorkflowConfig};
+    use indoc::formatdoc;
+    use mockito::mock;
+
+    #[test]
+    fn test_curl() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/curl?search=it%27s+me")
+            .with_status(200)
+            .create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: curl
+            env:
+              TOKEN: secret
+            steps:
+              - name: step 1
+                url: POST {url}/curl
+                query:
+                  search: it's me
+                headers:
+                  authorization: Bearer ${{{{ mask env.TOKEN }}}}
+                body:
+                  token: ${{{{ mask env.TOKEN }}}}
+                  id: 1
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+        let result =
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |_| {}).unwrap();
+
+        assert_eq!(
+            to_curl(&result.requests[0]),
+            formatdoc! {r#"
+                curl -X POST '{url}/curl?search=it%27s+me' \
+                  -H 'authorization: Bearer ****' \
+                  -H 'Content-Type: application/json' \
+                  --data-raw '{{"token":"****","id":1}}'"#,
+                url = url,
+            }
+        );
+    }
+
+    #[test]
+    fn test_string_body() {
+        let request = RequestData {
+            created_at: chrono::Utc::now(),
+            url: "http://localhost/text".to_string(),
+            name: "text".to_string(),
+            method: "PUT".to_string(),
+            headers: None,
+            query: None,
+            body: Some(serde_yaml::Value::String("it's".to_string())),
+            order: 0,
+            is_graphql: false,
+            response: None,
+            attempts: vec![],
+        };
+
+        assert_eq!(
+            to_curl(&request),
+            "curl -X PUT 'http://localhost/text' \\\n  --data-raw 'it'\\''s'"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    report::Reporter,
+    workflow::{workflow_result::WorkflowResult, RequestData, ResponseData, RunSource},
+};
+use serde_json::{json, Value};
+use std::io::{self, Write};
+
+/// Writes the run as a HAR 1.2 file, which can be opened in the
+/// network tab of most browsers and in tools like Postman.
+///
+/// Every request is an entry, and so is every earlier attempt
+/// of a retried step. The requests and responses are masked.
+pub struct HarReporter {
+    output: Box<dyn Write>,
+}
+
+impl HarReporter {
+    pub fn new(output: Box<dyn Write>) -> HarReporter {
+        HarReporter { output }
+    }
+}
+
+impl Reporter for HarReporter {
+    fn finish(&mut self, _source: &RunSource, workflow_runs: &[WorkflowResult]) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut self.output, &to_har(workflow_runs))?;
+        writeln!(self.output)?;
+        self.output.flush()
+    }
+}
+
+/// Create a HAR document from the results of a run.
+pub fn to_har(workflow_runs: &[WorkflowResult]) -> Value {
+    let mut entries = vec![];
+
+    for workflow_run in workflow_runs {
+        for request in &workflow_run.requests {
+            let comment = format!("{} / {}", workflow_run.name, request.name);
+
+            for attempt in &request.attempts {
+                entries.push(entry(request, Some(attempt), &comment));
+            }
+            entries.push(entry(request, request.response.as_ref(), &comment));
+        }
+    }
+
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": {
+                "name": "capter",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "entries": entries,
+        }
+    })
+}
+
+fn entry(request: &RequestData, response: Option<&ResponseData>, comment: &str) -> Value {
+    let response_time = response.map_or(0, |response| response.response_time);
+
+    json!({
+        "startedDateTime": response
+            .map_or(request.created_at, |response| response.created_at)
+            .to_rfc3339(),
+        "time": response_time,
+        "request": har_request(request),
+        "response": har_response(response),
+        "cache": {},
+        "timings": {
+            "send": 0,
+            "wait": response_time,
+            "receive": 0,
+        },
+        "comment": comment,
+    })
+}
+
+fn har_request(request: &RequestData) -> Value {
+    let headers = request.header_pairs();
+
+    let post_data = match &request.body {
+        Some(serde_yaml::Value::Null) | None => Value::Null,
+        Some(serde_yaml::Value::String(body)) => json!({
+            "mimeType": content_type(&headers).unwrap_or("text/plain"),
+            "text": body,
+        }),
+        Some(body) => json!({
+            "mimeType": content_type(&headers).unwrap_or("application/json"),
+            "text": serde_json::to_string(body).unwrap_or_default(),
+        }),
+    };
+
+    let mut har_request = json!({
+        "method": request.method,
+        "url": request.full_url(),
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": name_values(&headers),
+        "queryString": name_values(&request.query_pairs()),
+        "headersSize": -1,
+        "bodySize": -1,
+    });
+
+    if !post_data.is_null() {
+        har_request["postData"] = post_data;
+    }
+
+    har_request
+}
+
+fn har_response(response: Option<&ResponseData>) -> Value {
+    let default = ResponseData::default();
+    let response = response.unwrap_or(&default);
+
+    let headers: Vec<(String, String)> = response
+        .headers
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| {
+            (
+                name.to_owned(),
+                value.as_str().unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+
+    // we only have the masked body, so binary bodies are left out
+    let text = match &response.body {
+        Some(Value::String(text)) => Some(text.to_owned()),
+        Some(Value::Null) | None => None,
+        Some(body) => serde_json::to_string(body).ok(),
+    };
+
+    let mut content = json!({
+        "size": response.size.map_or(-1, |size| size as i64),
+        "mimeType": content_type(&headers).unwrap_or("x-unknown"),
+    });
+    if let Some(text) = text {
+        content["text"] = json!(text);
+    }
+
+    json!({
+        // HAR uses 0 when there was no response
+        "status": response.status.unwrap_or(0),
+        "statusText": response.status_text.to_owned().unwrap_or_default(),
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": name_values(&headers),
+        "content": content,
+        "redirectURL": "",
+        "headersSize": -1,
+        "bodySize": response.size.map_or(-1, |size| size as i64),
+    })
+}
+
+fn content_type(headers: &[(String, String)]) -> Option<&str> {
+    headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str())
+}
+
+fn name_values(pairs: &[(String, String)]) -> Value {
+    pairs
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::{RunOptions, WorkflowConfig};
+    use indoc::formatdoc;
+    use mockito::mock;
+
+    #[test]
+    fn test_har() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/har?page=1")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"token": "secret", "id": 1}"#)
+            .create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: har
+            env:
+              TOKEN: secret
+            steps:
+              - name: step 1
+                url: POST {url}/har
+                query:
+                  page: 1
+                body:
+                  token: ${{{{ mask env.TOKEN }}}}
+                options:
+                  mask:
+                    - token
+                assertions:
+                  - !expect status to_equal 201
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+        let result =
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |_| {}).unwrap();
+
+        let har = to_har(&[result]);
+        assert_eq!(har["log"]["version"], "1.2");
+
+        let entry = &har["log"]["entries"][0];
+        assert_eq!(entry["comment"], "har / step 1");
+        assert_eq!(entry["request"]["method"], "POST");
+        assert_eq!(entry["request"]["url"], format!("{}/har?page=1", url));
+        assert_eq!(
+            entry["request"]["queryString"],
+            json!([{ "name": "page", "value": "1" }])
+        );
+        assert_eq!(
+            entry["request"]["postData"],
+            json!({ "mimeType": "application/json", "text": r#"{"token":"****"}"# })
+        );
+        assert_eq!(entry["response"]["status"], 201);
+        assert_eq!(entry["response"]["content"]["mimeType"], "application/json");
+        assert_eq!(
+            entry["response"]["content"]["text"],
+            r#"{"id":1,"token":"****"}"#
+        );
+    }
+}
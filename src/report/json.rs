@@ -0,0 +1,33 @@
+use crate::{
+    report::Reporter,
+    workflow::{workflow_result::WorkflowResult, RunSource},
+};
+use serde_json::json;
+use std::io::{self, Write};
+
+/// Writes the whole run as one JSON document when it's done.
+///
+/// This is the same payload we post to the webhook, so the
+/// requests and responses are masked.
+pub struct JsonReporter {
+    output: Box<dyn Write>,
+}
+
+impl JsonReporter {
+    pub fn new(output: Box<dyn Write>) -> JsonReporter {
+        JsonReporter { output }
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn finish(&mut self, source: &RunSource, workflow_runs: &[WorkflowResult]) -> io::Result<()> {
+        let payload = json!({
+            "source": json!(source),
+            "data": json!(workflow_runs)
+        });
+
+        serde_json::to_writer_pretty(&mut self.output, &payload)?;
+        writeln!(self.output)?;
+        self.output.flush()
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{
+    report::Reporter,
+    workflow::{workflow_result::WorkflowResult, RequestData, RunSource},
+};
+use std::io::{self, Write};
+
+/// Writes the run as JUnit XML, which most CI dashboards understand.
+///
+/// Every `WorkflowResult` becomes a `<testsuite>`, and every step in
+/// the workflow becomes a `<testcase>`. Failed assertions are added
+/// as `<failure>` elements and skipped steps as `<skipped/>`, with the
+/// reason they were skipped. Every step in a skipped workflow is skipped,
+/// and a workflow that errored gets an extra `<testcase>` with an `<error>`.
+pub struct JunitReporter {
+    output: Box<dyn Write>,
+}
+
+impl JunitReporter {
+    pub fn new(output: Box<dyn Write>) -> JunitReporter {
+        JunitReporter { output }
+    }
+}
+
+impl Reporter for JunitReporter {
+    fn finish(&mut self, _source: &RunSource, workflow_runs: &[WorkflowResult]) -> io::Result<()> {
+        write!(self.output, "{}", to_junit_xml(workflow_runs))?;
+        self.output.flush()
+    }
+}
+
+/// Create a JUnit XML document from the results of a run.
+pub fn to_junit_xml(workflow_runs: &[WorkflowResult]) -> String {
+    let mut suites = String::new();
+    let mut tests = 0;
+    let mut failures = 0;
+    let mut errors = 0;
+    let mut skipped = 0;
+    let mut run_time = 0;
+
+    for workflow_run in workflow_runs {
+        let suite = TestSuite::from_workflow_result(workflow_run);
+
+        tests += suite.tests;
+        failures += suite.failures;
+        errors += suite.errors;
+        skipped += suite.skipped;
+        run_time += workflow_run.run_time;
+
+        suites.push_str(&suite.xml);
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"capter\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" errors=\"{}\" time=\"{}\">\n\
+         {}\
+         </testsuites>\n",
+        tests,
+        failures,
+        skipped,
+        errors,
+        to_seconds(run_time),
+        suites
+    )
+}
+
+/// A `<testsuite>` element and the numbers we need for its parent.
+struct TestSuite {
+    xml: String,
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+}
+
+impl TestSuite {
+    fn from_workflow_result(workflow_run: &WorkflowResult) -> TestSuite {
+        let file = workflow_run.file.to_owned().unwrap_or_default();
+        let mut cases = String::new();
+        let mut tests = 0;
+        let mut failures = 0;
+        let mut errors = 0;
+        let mut skipped = 0;
+
+        if let Some(error) = &workflow_run.error {
+            tests += 1;
+            errors += 1;
+            cases.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"0\">\n      <error message=\"{}\"/>\n    </testcase>\n",
+                escape(&workflow_run.name),
+                escape(&workflow_run.name),
+                escape(error),
+            ));
+        }
+
+        for (index, step) in workflow_run.workflow.steps.iter().enumerate() {
+            let requests: Vec<&RequestData> = workflow_run
+                .requests
+                .iter()
+                .filter(|request| request.order == index as i32)
+                .collect();
+
+            // steps without a request were never called, either because
+            // they were skipped or because the workflow errored before them
+            if requests.is_empty() {
+                let skipped_step = workflow_run
+                    .skipped_steps
+                    .iter()
+                    .find(|skipped_step| skipped_step.order == index as i32);
+                let element = match skipped_step {
+                    Some(skipped_step) => {
+                        format!("<skipped message=\"{}\"/>", escape(&skipped_step.reason))
+                    }
+                    None => "<skipped/>".to_string(),
+                };
+
+                tests += 1;
+                skipped += 1;
+                cases.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\" time=\"0\">\n      {}\n    </testcase>\n",
+                    escape(&step.name),
+                    escape(&workflow_run.name),
+                    element,
+                ));
+                continue;
+            }
+
+            for request in requests {
+                tests += 1;
+                let case = test_case(&workflow_run.name, request);
+                if case.failed {
+                    failures += 1;
+                }
+                cases.push_str(&case.xml);
+            }
+        }
+
+        let xml = format!(
+            "  <testsuite name=\"{}\" file=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" errors=\"{}\" time=\"{}\" timestamp=\"{}\">\n{}  </testsuite>\n",
+            escape(&workflow_run.name),
+            escape(&file),
+            tests,
+            failures,
+            skipped,
+            errors,
+            to_seconds(workflow_run.run_time),
+            workflow_run.created_at.format("%Y-%m-%dT%H:%M:%S"),
+            cases,
+        );
+
+        TestSuite {
+            xml,
+            tests,
+            failures,
+            errors,
+            skipped,
+        }
+    }
+}
+
+/// A `<testcase>` element and whether it failed.
+struct TestCase {
+    xml: String,
+    failed: bool,
+}
+
+fn test_case(workflow_name: &str, request: &RequestData) -> TestCase {
+    let mut failed = false;
+    let mut body = String::new();
+    let mut response_time = 0;
+
+    if let Some(response) = &request.response {
+        response_time = response.response_time;
+
+        for result in response.assertion_results.iter().filter(|r| !r.passed) {
+            failed = true;
+
+            let assertion = &result.assertion;
+            let description = format!(
+                "{}{} {} {}",
+                match assertion.not {
+                    true => "not ",
+                    false => "",
+                },
+                assertion.property,
+                assertion.test,
+                assertion.value.as_str().unwrap_or(""),
+            );
+            let message = result.message.to_owned().unwrap_or_default();
+
+            body.push_str(&format!(
+                "      <failure message=\"{}\" type=\"{}\">{}: {}</failure>\n",
+                escape(&message),
+                escape(&assertion.test),
+                escape(description.trim()),
+                escape(&message),
+            ));
+        }
+
+        // no status means we never got a response
+        if response.status.is_none() {
+            let status_text = response.status_text.to_owned().unwrap_or_default();
+            body.push_str(&format!(
+                "      <system-out>{} {}: {}</system-out>\n",
+                escape(&request.method),
+                escape(&request.url),
+                escape(&status_text),
+            ));
+        }
+    }
+
+    let xml = format!(
+        "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\">\n{}    </testcase>\n",
+        escape(&request.name),
+        escape(workflow_name),
+        to_seconds(response_time),
+        body,
+    );
+
+    TestCase { xml, failed }
+}
+
+/// Milliseconds to the seconds format JUnit uses.
+fn to_seconds(milliseconds: i64) -> String {
+    format!("{:.3}", milliseconds as f64 / 1000.0)
+}
+
+/// Escape a string so it can be used in both attributes and text.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            // other control characters are not allowed in XML 1.0
+            c if (c as u32) < 0x20 && c != '\t' && c != '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::{RunOptions, WorkflowConfig};
+    use indoc::formatdoc;
+    use mockito::mock;
+
+    #[test]
+    fn test_junit_xml() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/junit")
+            .with_status(200)
+            .with_body(r#"{"name": "<test>"}"#)
+            .create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: junit
+            file: ./capter/junit.yml
+            steps:
+              - name: step 1
+                url: {url}/junit
+                assertions:
+                  - !expect status to_equal 200
+                  - !expect body.name to_equal \"other\"
+              - name: step 2
+                url: {url}/junit
+                assertions:
+                  - !expect status to_equal 200
+              - name: step 3
+                skip: true
+                url: {url}/junit
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+        let result =
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |_| {}).unwrap();
+
+        // a skipped workflow is in the report too, like in the terminal
+        let mut skipped_config = workflow_config.to_owned();
+        skipped_config.name = "skipped".to_string();
+        skipped_config.skip = Some(true);
+        let skipped = WorkflowResult::from_skipped(&RunOptions::default(), &skipped_config);
+
+        let xml = to_junit_xml(&[result, skipped]);
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        assert!(
+            xml.contains("<testsuites name=\"capter\" tests=\"6\" failures=\"1\" skipped=\"4\"")
+        );
+        assert!(xml.contains(
+            "<testsuite name=\"junit\" file=\"./capter/junit.yml\" tests=\"3\" failures=\"1\" skipped=\"1\""
+        ));
+        assert!(xml.contains("<testcase name=\"step 1\" classname=\"junit\""));
+        assert!(xml.contains(
+            "<failure message=\"expected &lt;test&gt; to equal &quot;other&quot;\" type=\"to_equal\">"
+        ));
+        assert!(xml.contains(
+            "<testcase name=\"step 3\" classname=\"junit\" time=\"0\">\n      <skipped message=\"skipped\"/>"
+        ));
+        assert!(xml.contains(
+            "<testsuite name=\"skipped\" file=\"./capter/junit.yml\" tests=\"3\" failures=\"0\" skipped=\"3\""
+        ));
+        assert_eq!(xml.matches("<failure").count(), 1);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a < b & \"c\""), "a &lt; b &amp; &quot;c&quot;");
+        assert_eq!(escape("line\nbreak\u{1}"), "line&#10;break");
+    }
+}
@@ -0,0 +1,55 @@
+pub mod curl;
+pub mod har;
+pub mod json;
+pub mod junit;
+pub mod ndjson;
+
+pub use curl::CurlReporter;
+pub use har::HarReporter;
+pub use json::JsonReporter;
+pub use junit::JunitReporter;
+pub use ndjson::NdjsonReporter;
+
+use crate::workflow::{
+    workflow_result::{CallbackEvent, WorkflowResult},
+    RunSource,
+};
+use std::{
+    fs::File,
+    io::{self, stdout, Write},
+};
+
+/// A `Reporter` writes a machine readable version of the run,
+/// either to a file or to stdout.
+///
+/// It gets where the run is from before anything runs, every
+/// `CallbackEvent` while the workflows run, and all results
+/// when the run is done.
+pub trait Reporter {
+    fn start(&mut self, _source: &RunSource) {}
+
+    fn update(&mut self, _event: CallbackEvent) {}
+
+    fn finish(&mut self, source: &RunSource, workflow_runs: &[WorkflowResult]) -> io::Result<()>;
+}
+
+/// Create the reporter with the name `name`, writing to the
+/// file at `output`, or to stdout if no output is set.
+pub fn create_reporter(name: &str, output: Option<&str>) -> io::Result<Box<dyn Reporter>> {
+    let output: Box<dyn Write> = match output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(stdout()),
+    };
+
+    match name {
+        "junit" => Ok(Box::new(JunitReporter::new(output))),
+        "json" => Ok(Box::new(JsonReporter::new(output))),
+        "ndjson" => Ok(Box::new(NdjsonReporter::new(output))),
+        "curl" => Ok(Box::new(CurlReporter::new(output))),
+        "har" => Ok(Box::new(HarReporter::new(output))),
+        name => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unknown reporter: `{}`", name),
+        )),
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    report::Reporter,
+    workflow::{
+        workflow_result::{CallbackEvent, WorkflowResult},
+        RunSource,
+    },
+};
+use serde_json::{json, Value};
+use std::io::{self, Write};
+
+/// Writes one line of JSON for every `CallbackEvent`, as it happens.
+///
+/// Useful for tools that want to stream the progress of a run. The
+/// first line is a `start` with the `RunSource`, and the last one is
+/// a `finish` with every `WorkflowResult`, like the webhook payload.
+/// The request data in `step_done` is masked, just like in the webhook.
+pub struct NdjsonReporter {
+    output: Box<dyn Write>,
+}
+
+impl NdjsonReporter {
+    pub fn new(output: Box<dyn Write>) -> NdjsonReporter {
+        NdjsonReporter { output }
+    }
+
+    /// Write a line without stopping the run if it fails, `finish`
+    /// will report the error when we flush.
+    fn write_line(&mut self, line: Value) {
+        writeln!(self.output, "{}", line).ok();
+        self.output.flush().ok();
+    }
+}
+
+impl Reporter for NdjsonReporter {
+    fn start(&mut self, source: &RunSource) {
+        self.write_line(json!({
+            "event": "start",
+            "source": source,
+        }));
+    }
+
+    fn update(&mut self, event: CallbackEvent) {
+        self.write_line(event_to_json(event));
+    }
+
+    fn finish(&mut self, source: &RunSource, workflow_runs: &[WorkflowResult]) -> io::Result<()> {
+        let line = json!({
+            "event": "finish",
+            "source": source,
+            "passed": workflow_runs.iter().all(|workflow_run| workflow_run.passed),
+            "data": workflow_runs,
+        });
+
+        writeln!(self.output, "{}", line)?;
+        self.output.flush()
+    }
+}
+
+/// Convert an event in to the JSON we write on each line.
+pub fn event_to_json(event: CallbackEvent) -> Value {
+    match event {
+        CallbackEvent::RunStart(config) => json!({
+            "event": "run_start",
+            "workflow": config.name,
+            "file": config.file,
+        }),
+        CallbackEvent::RunDone(config, passed) => json!({
+            "event": "run_done",
+            "workflow": config.name,
+            "file": config.file,
+            "passed": passed,
+        }),
+        CallbackEvent::RunSkipped(config) => json!({
+            "event": "run_skipped",
+            "workflow": config.name,
+            "file": config.file,
+        }),
+        CallbackEvent::RunErrored(config, error) => json!({
+            "event": "run_errored",
+            "workflow": config.name,
+            "file": config.file,
+            "error": error.to_string(),
+        }),
+        CallbackEvent::StepStart(config, index) => json!({
+            "event": "step_start",
+            "workflow": config.name,
+            "file": config.file,
+            "step": index,
+            "name": config.steps[index as usize].name,
+        }),
+        CallbackEvent::StepDone(config, index, request_data, passed) => json!({
+            "event": "step_done",
+            "workflow": config.name,
+            "file": config.file,
+            "step": index,
+            "name": config.steps[index as usize].name,
+            "passed": passed,
+            "request": request_data,
+        }),
+        CallbackEvent::StepSkipped(config, index, reason) => json!({
+            "event": "step_skipped",
+            "workflow": config.name,
+            "file": config.file,
+            "step": index,
+            "name": config.steps[index as usize].name,
+            "reason": reason.to_string(),
+        }),
+        CallbackEvent::StepRetry(config, index, attempt) => json!({
+            "event": "step_retry",
+            "workflow": config.name,
+            "file": config.file,
+            "step": index,
+            "name": config.steps[index as usize].name,
+            "attempt": attempt,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        report::create_reporter,
+        workflow::{RunOptions, WorkflowConfig},
+    };
+    use indoc::formatdoc;
+    use mockito::mock;
+
+    #[test]
+    fn test_events() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/ndjson")
+            .with_status(200)
+            .with_body(r#"{"token": "secret", "name": "test"}"#)
+            .create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: ndjson
+            file: ./capter/ndjson.yml
+            env:
+              TOKEN: abc
+            steps:
+              - name: step 1
+                url: {url}/ndjson
+                headers:
+                  authorization: ${{{{ mask env.TOKEN }}}}
+                assertions:
+                  - !expect status to_equal 200
+                options:
+                  mask:
+                    - token
+              - name: step 2
+                skip: true
+                url: {url}/ndjson
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+
+        let mut lines = vec![];
+        WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |event| {
+            lines.push(event_to_json(event));
+        })
+        .unwrap();
+
+        let events: Vec<&str> = lines.iter().map(|l| l["event"].as_str().unwrap()).collect();
+        assert_eq!(
+            events,
+            vec![
+                "run_start",
+                "step_start",
+                "step_done",
+                "step_start",
+                "step_skipped",
+                "run_done"
+            ]
+        );
+
+        let step_done = &lines[2];
+        assert_eq!(step_done["passed"], true);
+        assert_eq!(step_done["name"], "step 1");
+        assert_eq!(step_done["request"]["headers"]["authorization"], "****");
+        assert_eq!(step_done["request"]["response"]["body"]["token"], "****");
+        assert_eq!(step_done["request"]["response"]["body"]["name"], "test");
+        assert_eq!(lines[5]["passed"], true);
+    }
+
+    #[test]
+    fn test_report() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/ndjson/report").with_status(200).create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: report
+            steps:
+              - name: step 1
+                url: {url}/ndjson/report
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+
+        let path = std::env::temp_dir().join("capter_report.ndjson");
+        let mut reporter = create_reporter("ndjson", path.to_str()).unwrap();
+        let source = RunSource {
+            branch: Some("main".to_string()),
+            ..Default::default()
+        };
+
+        reporter.start(&source);
+        let result = WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |e| {
+            reporter.update(e)
+        })
+        .unwrap();
+        reporter.finish(&source, &[result]).unwrap();
+
+        // every line is JSON on its own
+        let lines: Vec<Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let events: Vec<&str> = lines.iter().map(|l| l["event"].as_str().unwrap()).collect();
+        assert_eq!(
+            events,
+            vec![
+                "start",
+                "run_start",
+                "step_start",
+                "step_done",
+                "run_done",
+                "finish"
+            ]
+        );
+        assert_eq!(lines[0]["source"]["branch"], "main");
+
+        let finish = &lines[5];
+        assert_eq!(finish["source"]["branch"], "main");
+        assert_eq!(finish["passed"], true);
+        assert_eq!(finish["data"][0]["name"], "report");
+        assert_eq!(finish["data"][0]["requests"][0]["name"], "step 1");
+    }
+}
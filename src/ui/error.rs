@@ -0,0 +1,47 @@
+use crate::{error::CapterError, ui::TerminalUi, workflow::WorkflowConfig};
+use crossterm::{
+    execute,
+    style::{Attribute, Color, Colors, Print, SetAttribute, SetColors, SetForegroundColor},
+};
+use std::io::stdout;
+
+impl TerminalUi {
+    /// Print why a workflow couldn't run. Mistakes found before it ran
+    /// are shown with where they are, like in `capter validate`.
+    pub fn errored_workflow(&mut self, config: &WorkflowConfig, error: &CapterError) {
+        self.errored_workflows_count += 1;
+
+        if self.is_silent {
+            return;
+        }
+
+        execute!(
+            stdout(),
+            SetColors(Colors::new(
+                Color::Rgb {
+                    r: 50,
+                    g: 50,
+                    b: 50
+                },
+                Color::Red
+            )),
+            Print(" ERROR "),
+            SetAttribute(Attribute::Reset),
+            Print(format!(" {}\n\n", config.name)),
+        )
+        .unwrap();
+
+        match error {
+            CapterError::Invalid(diagnostics) => TerminalUi::print_diagnostics(diagnostics),
+            error => execute!(
+                stdout(),
+                SetForegroundColor(Color::Red),
+                SetAttribute(Attribute::Bold),
+                Print("error"),
+                SetAttribute(Attribute::Reset),
+                Print(format!(": {}\n\n", error)),
+            )
+            .unwrap(),
+        }
+    }
+}
@@ -1,8 +1,11 @@
+pub mod error;
 pub mod skip;
 pub mod step;
 pub mod summarize;
+#[allow(clippy::module_inception)]
 pub mod ui;
 pub mod update;
+pub mod validate;
 pub mod webhook;
 
 pub use ui::TerminalUi;
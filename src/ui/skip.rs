@@ -7,6 +7,10 @@ use std::io::stdout;
 
 impl TerminalUi {
     pub fn skipped_workflow(&mut self, config: &WorkflowConfig) {
+        if self.is_silent {
+            return;
+        }
+
         let file = config.file.clone().unwrap().clone();
         execute!(
             stdout(),
@@ -20,5 +24,6 @@ impl TerminalUi {
         .unwrap();
 
         self.skipped_workflows_count += 1;
+        self.skipped_steps_count += config.steps.len() as i32;
     }
 }
@@ -1,7 +1,7 @@
 use crate::{
     assert::AssertionResultData,
     ui::{StepStatus, TerminalUi},
-    workflow::{WorkflowConfig, WorkflowConfigStep},
+    workflow::{workflow_result::SkipReason, WorkflowConfig, WorkflowConfigStep},
 };
 use crossterm::{
     execute,
@@ -22,7 +22,15 @@ impl TerminalUi {
                 true => (" PASS ", Color::Green),
                 false => (" FAIL ", Color::Red),
             },
-            StepStatus::Skipped => (" SKIP ", Color::Grey),
+            StepStatus::Skipped(_) => (" SKIP ", Color::Grey),
+            StepStatus::Retrying(_) => (" WAIT ", Color::Magenta),
+        };
+
+        let suffix = match status {
+            StepStatus::Retrying(attempt) => format!(" (attempt {} failed, retrying)", attempt),
+            StepStatus::Skipped(SkipReason::Skip) => "".to_string(),
+            StepStatus::Skipped(reason) => format!(" ({})", reason),
+            _ => "".to_string(),
         };
 
         execute!(
@@ -37,7 +45,7 @@ impl TerminalUi {
             )),
             Print(status_str.0),
             SetAttribute(Attribute::Reset),
-            Print(format!(" {} → {}", config.name, step.name)),
+            Print(format!(" {} → {}{}", config.name, step.name, suffix)),
             Print("\n")
         )
         .unwrap();
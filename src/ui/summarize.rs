@@ -6,7 +6,11 @@ use crossterm::{
 use std::io::stdout;
 
 impl TerminalUi {
-    pub fn summarize(&self, workflow_runs: &Vec<WorkflowResult>) {
+    pub fn summarize(&self, workflow_runs: &[WorkflowResult]) {
+        if self.is_silent {
+            return;
+        }
+
         // find failed assertion
         let mut assertion_results = vec![];
         workflow_runs.iter().for_each(|run| {
@@ -39,14 +43,14 @@ impl TerminalUi {
                     stdout(),
                     Print("\n\n   "),
                     SetAttribute(Attribute::Dim),
-                    Print(format!("{}", message)),
+                    Print(message.to_string()),
                     SetAttribute(Attribute::Reset),
                 )
                 .unwrap();
             }
         }
 
-        if assertion_results.len() > 0 {
+        if !assertion_results.is_empty() {
             execute!(stdout(), Print("\n\n")).unwrap();
         }
 
@@ -61,6 +65,7 @@ impl TerminalUi {
             self.workflow_count,
             self.passed_workflows_count,
             self.failed_workflows_count,
+            self.errored_workflows_count,
             self.skipped_workflows_count,
         );
 
@@ -72,10 +77,25 @@ impl TerminalUi {
             self.passed_steps_count,
             self.failed_steps_count,
             0,
+            self.skipped_steps_count,
         );
 
         execute!(stdout(), Print("\n")).unwrap();
 
+        if let Some(environment) = &self.environment {
+            execute!(
+                stdout(),
+                SetAttribute(Attribute::Bold),
+                Print("Environment: "),
+                SetAttribute(Attribute::Reset),
+                SetAttribute(Attribute::Dim),
+                Print(environment),
+                SetAttribute(Attribute::Reset),
+                Print("\n"),
+            )
+            .unwrap();
+        }
+
         let elapsed = format!("{:.1}", self.timer.elapsed().as_millis() as f32 / 1000.0);
         execute!(
             stdout(),
@@ -96,6 +116,7 @@ impl TerminalUi {
         total: i32,
         passed: i32,
         failed: i32,
+        errored: i32,
         skipped: i32,
     ) {
         execute!(
@@ -106,45 +127,29 @@ impl TerminalUi {
         )
         .unwrap();
 
-        if failed > 0 {
-            execute!(
-                stdout(),
-                SetForegroundColor(Color::Red),
-                Print(format!("{} failed", failed)),
-                SetAttribute(Attribute::Reset)
-            )
-            .unwrap();
-        }
-
-        if passed > 0 && failed > 0 {
-            execute!(stdout(), Print(", ")).unwrap();
-        }
-
-        if passed > 0 {
-            execute!(
-                stdout(),
-                SetForegroundColor(Color::Green),
-                Print(format!("{} passed", passed)),
-                SetAttribute(Attribute::Reset)
-            )
-            .unwrap();
-        }
-
-        if (passed > 0 || failed > 0) && skipped > 0 {
-            execute!(stdout(), Print(", ")).unwrap();
-        }
+        let counts = [
+            (errored, "errored", Color::Red),
+            (failed, "failed", Color::Red),
+            (passed, "passed", Color::Green),
+            (skipped, "skipped", Color::Grey),
+        ];
+        let counts = counts.iter().filter(|(count, _, _)| *count > 0);
+
+        for (index, (count, label, color)) in counts.enumerate() {
+            if index > 0 {
+                execute!(stdout(), Print(", ")).unwrap();
+            }
 
-        if skipped > 0 {
             execute!(
                 stdout(),
-                SetForegroundColor(Color::Grey),
-                Print(format!("{} skipped", skipped)),
+                SetForegroundColor(*color),
+                Print(format!("{} {}", count, label)),
                 SetAttribute(Attribute::Reset)
             )
             .unwrap();
         }
 
-        let total = match passed + failed + skipped {
+        let total = match passed + failed + errored + skipped {
             0 => format!("0 of {} total", total),
             _ => format!(", {} total", total),
         };
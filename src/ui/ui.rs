@@ -1,10 +1,7 @@
-use crate::{
-    workflow::{run_source::RunSource, WorkflowConfig},
-    CliOptions,
-};
+use crate::workflow::{run_source::RunSource, RunOptions, WorkflowConfig};
 use crossterm::{
     execute,
-    style::{Attribute, Print, SetAttribute},
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
     tty::IsTty,
 };
 use serde_json::{json, Value};
@@ -15,31 +12,43 @@ use std::{io::stdout, time::Instant};
 ///
 /// It can run in either with *tty* or without it. If tty is supported
 /// the UI will be a lot nicer, but not all environments support it.
+///
+/// When a reporter writes to stdout, the UI is silent so it doesn't
+/// end up in the report.
 pub struct TerminalUi {
     pub is_tty: bool,
+    pub is_silent: bool,
     pub workflow_count: i32,
     pub step_count: i32,
     pub passed_steps_count: i32,
     pub passed_workflows_count: i32,
     pub skipped_workflows_count: i32,
+    pub skipped_steps_count: i32,
     pub failed_steps_count: i32,
     pub failed_workflows_count: i32,
+    pub errored_workflows_count: i32,
+    /// If a running step and the summary are shown below
+    /// the last line, so they can be replaced.
+    pub is_step_running: bool,
     pub timer: Instant,
     pub is_debug: bool,
+    pub environment: Option<String>,
 }
 
 impl TerminalUi {
     pub fn new(
-        configs: &Vec<WorkflowConfig>,
+        configs: &[WorkflowConfig],
         source: &RunSource,
-        cli_options: &CliOptions,
+        run_options: &RunOptions,
     ) -> TerminalUi {
-        let is_tty = match cli_options.is_debug {
+        let is_tty = match run_options.is_debug {
             true => false,
             false => stdout().is_tty(),
         };
 
-        if cli_options.is_debug {
+        let is_silent = run_options.reporter.is_some() && run_options.output.is_none();
+
+        if run_options.is_debug && !is_silent {
             TerminalUi::print_run_source(source);
         }
 
@@ -53,15 +62,20 @@ impl TerminalUi {
 
         TerminalUi {
             is_tty,
+            is_silent,
             timer,
             passed_steps_count: 0,
             passed_workflows_count: 0,
             failed_steps_count: 0,
             failed_workflows_count: 0,
+            errored_workflows_count: 0,
+            is_step_running: false,
             skipped_workflows_count: 0,
+            skipped_steps_count: 0,
             workflow_count,
             step_count,
-            is_debug: cli_options.is_debug,
+            is_debug: run_options.is_debug,
+            environment: source.environment.to_owned(),
         }
     }
 
@@ -115,4 +129,105 @@ impl TerminalUi {
         )
         .unwrap();
     }
+
+    /// Print the workflows created by `capter init --from-openapi`.
+    pub fn print_init_from_openapi(created: &[String], existing: &[String]) {
+        execute!(
+            stdout(),
+            SetAttribute(Attribute::Bold),
+            Print("\nWelcome to Capter!\n"),
+            SetAttribute(Attribute::Reset),
+        )
+        .unwrap();
+
+        TerminalUi::print_created_files("your OpenAPI spec", created, existing);
+
+        execute!(
+            stdout(),
+            Print("\nChange "),
+            SetAttribute(Attribute::Bold),
+            Print("env.URL"),
+            SetAttribute(Attribute::Reset),
+            Print(" in the workflows to test another server, and run them by calling: "),
+            SetAttribute(Attribute::Bold),
+            Print("capter test"),
+            SetAttribute(Attribute::Reset),
+            Print("\n"),
+        )
+        .unwrap();
+    }
+
+    /// Print the workflows created by `capter import`, and
+    /// everything we couldn't translate.
+    pub fn print_import(
+        source: &str,
+        created: &[String],
+        existing: &[String],
+        warnings: &[String],
+    ) {
+        TerminalUi::print_created_files(source, created, existing);
+
+        if !warnings.is_empty() {
+            execute!(
+                stdout(),
+                SetForegroundColor(Color::Yellow),
+                Print(format!(
+                    "\n{} thing(s) could not be imported, and need to be added by hand:\n",
+                    warnings.len()
+                )),
+                ResetColor,
+            )
+            .unwrap();
+
+            for warning in warnings {
+                execute!(stdout(), Print(format!("  - {}\n", warning))).unwrap();
+            }
+        }
+
+        execute!(
+            stdout(),
+            Print("\nRun them by calling: "),
+            SetAttribute(Attribute::Bold),
+            Print("capter test"),
+            SetAttribute(Attribute::Reset),
+            Print("\n"),
+        )
+        .unwrap();
+    }
+
+    fn print_created_files(source: &str, created: &[String], existing: &[String]) {
+        execute!(
+            stdout(),
+            Print(format!(
+                "\nWe've created {} workflow(s) from {}:\n",
+                created.len(),
+                source
+            )),
+        )
+        .unwrap();
+
+        for path in created {
+            execute!(
+                stdout(),
+                Print("  "),
+                SetAttribute(Attribute::Underlined),
+                Print(path),
+                SetAttribute(Attribute::Reset),
+                Print("\n"),
+            )
+            .unwrap();
+        }
+
+        if !existing.is_empty() {
+            execute!(
+                stdout(),
+                Print("\nThese files already exist, and were left as they are:\n"),
+            )
+            .unwrap();
+
+            for path in existing {
+                execute!(stdout(), Print(format!("  {}\n", path))).unwrap();
+            }
+        }
+    }
 }
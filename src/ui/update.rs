@@ -1,4 +1,7 @@
-use crate::{ui::TerminalUi, workflow::workflow_result::CallbackEvent};
+use crate::{
+    ui::TerminalUi,
+    workflow::workflow_result::{CallbackEvent, SkipReason},
+};
 use crossterm::{
     cursor, execute,
     style::{Attribute, Print, SetAttribute},
@@ -7,14 +10,19 @@ use crossterm::{
 };
 use std::io::stdout;
 
-pub enum StepStatus {
+pub enum StepStatus<'a> {
     Running,
     Done(bool),
-    Skipped,
+    Skipped(&'a SkipReason),
+    Retrying(u32),
 }
 
 impl TerminalUi {
     pub fn update(&mut self, event: CallbackEvent) {
+        if self.is_silent {
+            return;
+        }
+
         match event {
             CallbackEvent::RunStart(config) => {
                 let file = config.file.clone().unwrap().clone();
@@ -36,20 +44,21 @@ impl TerminalUi {
                     execute!(stdout(), cursor::SavePosition).unwrap();
                     self.print_step(config, &step, StepStatus::Running);
                     self.print_summary();
+                    self.is_step_running = true;
                 }
             }
-            CallbackEvent::StepDone(config, index, assertion_results, passed) => {
-                let step = config.steps[index as usize].clone();
-
-                if self.is_tty {
-                    execute!(stdout(), cursor::MoveToPreviousLine(5)).unwrap();
-                    execute!(stdout(), terminal::Clear(ClearType::FromCursorDown)).unwrap();
-                }
+            CallbackEvent::StepDone(config, index, request_data, passed) => {
+                // steps with `for_each` have the item in the name
+                let mut step = config.steps[index as usize].clone();
+                step.name = request_data.name.to_owned();
 
+                self.clear_running_step();
                 self.print_step(config, &step, StepStatus::Done(passed));
 
                 if !passed {
-                    self.print_assertions(assertion_results);
+                    if let Some(response) = &request_data.response {
+                        self.print_assertions(&response.assertion_results);
+                    }
                     execute!(stdout(), Print("\n")).unwrap();
                     if config.steps.len() - 1 > index as usize {
                         execute!(stdout(), Print("\n")).unwrap();
@@ -65,20 +74,45 @@ impl TerminalUi {
                 true => self.passed_workflows_count += 1,
                 false => self.failed_workflows_count += 1,
             },
-            CallbackEvent::StepSkipped(config, index) => {
+            CallbackEvent::StepRetry(config, index, attempt) => {
                 let step = config.steps[index as usize].clone();
 
+                self.clear_running_step();
+                self.print_step(config, &step, StepStatus::Retrying(attempt));
+
+                // the step is running again
                 if self.is_tty {
-                    execute!(
-                        stdout(),
-                        cursor::MoveToPreviousLine(5),
-                        terminal::Clear(ClearType::FromCursorDown)
-                    )
-                    .unwrap();
+                    self.print_step(config, &step, StepStatus::Running);
+                    self.print_summary();
+                    self.is_step_running = true;
                 }
+            }
+            CallbackEvent::RunSkipped(config) => self.skipped_workflow(config),
+            CallbackEvent::RunErrored(config, error) => {
+                self.clear_running_step();
+                self.errored_workflow(config, error);
+            }
+            CallbackEvent::StepSkipped(config, index, reason) => {
+                let step = config.steps[index as usize].clone();
 
-                self.print_step(config, &step, StepStatus::Skipped);
+                self.clear_running_step();
+                self.print_step(config, &step, StepStatus::Skipped(reason));
+                self.skipped_steps_count += 1;
             }
         }
     }
+
+    /// Remove the running step and the summary below it.
+    fn clear_running_step(&mut self) {
+        if self.is_tty && self.is_step_running {
+            execute!(
+                stdout(),
+                cursor::MoveToPreviousLine(5),
+                terminal::Clear(ClearType::FromCursorDown)
+            )
+            .unwrap();
+        }
+
+        self.is_step_running = false;
+    }
 }
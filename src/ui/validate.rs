@@ -0,0 +1,58 @@
+use crate::{ui::TerminalUi, validate::Diagnostic};
+use crossterm::{
+    execute,
+    style::{Attribute, Color, Print, SetAttribute, SetForegroundColor},
+};
+use std::io::stdout;
+
+impl TerminalUi {
+    /// Print the mistakes found by `capter validate`.
+    pub fn print_validation(count: usize, diagnostics: &[Diagnostic]) {
+        TerminalUi::print_diagnostics(diagnostics);
+
+        let mut files: Vec<&String> = diagnostics
+            .iter()
+            .map(|diagnostic| &diagnostic.file)
+            .collect();
+        files.dedup();
+
+        match diagnostics.is_empty() {
+            true => execute!(
+                stdout(),
+                SetForegroundColor(Color::Green),
+                Print("✓ "),
+                SetAttribute(Attribute::Reset),
+                Print(format!("{} workflow(s) are valid\n", count)),
+            ),
+            false => execute!(
+                stdout(),
+                SetAttribute(Attribute::Bold),
+                Print(format!(
+                    "{} error(s) in {} of {} workflow(s)\n",
+                    diagnostics.len(),
+                    files.len(),
+                    count
+                )),
+                SetAttribute(Attribute::Reset),
+            ),
+        }
+        .unwrap();
+    }
+
+    /// Print mistakes in the workflows, with where they are.
+    pub fn print_diagnostics(diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            let rendered = diagnostic.render();
+            execute!(
+                stdout(),
+                SetForegroundColor(Color::Red),
+                SetAttribute(Attribute::Bold),
+                Print("error"),
+                SetAttribute(Attribute::Reset),
+                Print(rendered.strip_prefix("error").unwrap_or(&rendered)),
+                Print("\n"),
+            )
+            .unwrap();
+        }
+    }
+}
@@ -1,12 +1,23 @@
-use crate::{ui::TerminalUi, WebhookResponse};
+use crate::ui::TerminalUi;
 use crossterm::{
     execute,
     style::{Attribute, Color, Print, SetAttribute, SetForegroundColor},
 };
+use serde::Deserialize;
 use std::io::stdout;
 
+/// What the webhook responds with after a run is posted.
+#[derive(Deserialize)]
+pub struct WebhookResponse {
+    url: String,
+}
+
 impl TerminalUi {
     pub fn dry_run(&self) {
+        if self.is_silent {
+            return;
+        }
+
         execute!(
             stdout(),
             SetAttribute(Attribute::Dim),
@@ -19,6 +30,10 @@ impl TerminalUi {
     }
 
     pub fn webhook_start(&self) {
+        if self.is_silent {
+            return;
+        }
+
         execute!(
             stdout(),
             SetAttribute(Attribute::Dim),
@@ -30,6 +45,10 @@ impl TerminalUi {
     }
 
     pub fn webhook_done(&self, webhook_response: Option<WebhookResponse>) {
+        if self.is_silent {
+            return;
+        }
+
         execute!(
             stdout(),
             SetForegroundColor(Color::Green),
@@ -59,6 +78,10 @@ impl TerminalUi {
         }
     }
     pub fn webhook_error(&self, error: &str) {
+        if self.is_silent {
+            return;
+        }
+
         execute!(
             stdout(),
             SetForegroundColor(Color::Red),
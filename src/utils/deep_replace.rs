@@ -18,14 +18,14 @@ pub fn deep_replace(value: &Value, mask: &Vec<String>) -> Value {
                 }
             }
 
-            return new_map.into();
+            new_map.into()
         }
         Value::Array(vec) => {
             let mut new_vec = vec![];
             for v in vec {
                 new_vec.push(deep_replace(v, mask));
             }
-            return new_vec.into();
+            new_vec.into()
         }
         _ => value.clone(),
     }
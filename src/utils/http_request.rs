@@ -63,13 +63,14 @@ impl HttpRequest {
     }
 
     /// Make the request and return a response or an error.
-    pub fn call(&mut self) -> Result<ureq::Response, ureq::Error> {
+    /// The error is boxed, since `ureq::Error` is large.
+    pub fn call(&mut self) -> Result<ureq::Response, Box<ureq::Error>> {
         // reset timer
         self.timer = Instant::now();
         let request = self.request.to_owned();
 
         // make the request
-        match &self.body {
+        let result = match &self.body {
             // call with body
             Some(body) => match body {
                 Value::String(str) => request.send_string(str),
@@ -77,7 +78,9 @@ impl HttpRequest {
             },
             // or without body
             _ => request.call(),
-        }
+        };
+
+        result.map_err(Box::new)
     }
 
     /// Time elapsed since `.call()` started.
@@ -87,7 +90,7 @@ impl HttpRequest {
 }
 
 /// Utility to parse key value pairs for headers and query.
-fn parse_key_value(key: &serde_yaml::Value, value: &serde_yaml::Value) -> (String, String) {
+pub fn parse_key_value(key: &serde_yaml::Value, value: &serde_yaml::Value) -> (String, String) {
     let value = match &value {
         &serde_yaml::Value::String(val) => val.to_string(),
         val => json!(val).to_string(),
@@ -3,6 +3,7 @@ use fs::OpenOptions;
 use serde_json::json;
 use std::{fs, io::Write};
 
+#[derive(Default)]
 pub struct Logger {}
 
 impl Logger {
@@ -27,14 +28,16 @@ impl Logger {
             let mut file = OpenOptions::new()
                 .write(true)
                 .create(true)
+                .truncate(true)
                 .open(file_path)
                 .unwrap();
 
-            // empty file
-            file.set_len(0).unwrap();
-
             // title
             write!(file, "{} [{}]\n\n", workflow.name, &workflow_path).unwrap();
+
+            if let Some(error) = &workflow.error {
+                write!(file, "Error:\n\n  {}\n\n", error.replace('\n', "\n  ")).unwrap();
+            }
             write!(file, "Steps:\n\n").unwrap();
 
             for request in &workflow.requests {
@@ -44,7 +47,7 @@ impl Logger {
                     .unwrap()
                     .assertion_results
                     .iter()
-                    .any(|r| r.passed == false);
+                    .any(|r| !r.passed);
 
                 write!(file, "  Name:\n    {}\n\n", request.name).unwrap();
                 write!(file, "  Passed:\n    {}\n\n", step_passed).unwrap();
@@ -77,7 +80,7 @@ impl Logger {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{workflow::WorkflowConfig, CliOptions};
+    use crate::workflow::{RunOptions, WorkflowConfig};
     use indoc::formatdoc;
     use mockito::mock;
     use std::fs::File;
@@ -101,10 +104,10 @@ mod tests {
             ",
             url = url,
         };
-        let workflow_config = WorkflowConfig::from_yaml(yaml.into());
+        let workflow_config = WorkflowConfig::from_yaml(yaml);
 
         let result =
-            WorkflowResult::from_config(&CliOptions::default(), &workflow_config.unwrap(), |_| {})
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config.unwrap(), |_| {})
                 .unwrap();
         let mut logger = Logger::new();
         logger.log_workflow_results(&vec![result]);
@@ -112,7 +115,7 @@ mod tests {
         let mut file = File::open(".capter/logs/logger-test.yml.log").unwrap();
         let mut contents = String::new();
         file.read_to_string(&mut contents).unwrap();
-        assert_eq!(contents.contains("test [./capter/logger-test.yml]"), true);
+        assert!(contents.contains("test [./capter/logger-test.yml]"));
 
         fs::remove_file(".capter/logs/logger-test.yml.log").unwrap();
     }
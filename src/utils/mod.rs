@@ -2,8 +2,10 @@ pub mod deep_replace;
 pub mod exit;
 pub mod http_request;
 pub mod logger;
+pub mod slugify;
 
 pub use deep_replace::deep_replace;
 pub use exit::exit_with_code;
 pub use http_request::HttpRequest;
 pub use logger::Logger;
+pub use slugify::{slugify, unique_slugs};
@@ -0,0 +1,55 @@
+/// Turn a name like `User Accounts` in to `user-accounts`,
+/// so it can be used as a file name.
+pub fn slugify(name: &str) -> String {
+    let slug: Vec<String> = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(String::from)
+        .collect();
+
+    match slug.is_empty() {
+        true => "default".to_string(),
+        false => slug.join("-"),
+    }
+}
+
+/// A slug for every name, with a number added when two names get
+/// the same slug, like `users` and `users-2`, so every name can
+/// get its own file.
+pub fn unique_slugs(names: &[&str]) -> Vec<String> {
+    let mut slugs: Vec<String> = vec![];
+
+    for name in names {
+        let slug = slugify(name);
+        let mut unique = slug.to_owned();
+        let mut number = 1;
+        while slugs.contains(&unique) {
+            number += 1;
+            unique = format!("{}-{}", slug, number);
+        }
+        slugs.push(unique);
+    }
+
+    slugs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("User Accounts"), "user-accounts");
+        assert_eq!(slugify("  /v1/users!"), "v1-users");
+        assert_eq!(slugify("--"), "default");
+    }
+
+    #[test]
+    fn test_unique_slugs() {
+        assert_eq!(
+            unique_slugs(&["Users", "users", "Posts", "users 2", "USERS"]),
+            vec!["users", "users-2", "posts", "users-2-2", "users-3"]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::workflow::Span;
+use std::fmt;
+
+/// A mistake in a workflow file, with where it is.
+///
+/// It's shown like the errors from `rustc`, with the line
+/// from the file and the mistake underlined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub span: Option<Span>,
+    /// How many characters to underline.
+    pub len: usize,
+    pub message: String,
+    /// The line the span is on.
+    pub source_line: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(file: &str, message: String) -> Diagnostic {
+        Diagnostic {
+            file: file.to_string(),
+            span: None,
+            len: 1,
+            message,
+            source_line: None,
+        }
+    }
+
+    /// Point at `len` characters at `span` in `source`,
+    /// which is the content of the file.
+    pub fn at(mut self, source: &str, span: Option<Span>, len: usize) -> Diagnostic {
+        self.span = span;
+        self.len = len.max(1);
+        self.source_line = span
+            .and_then(|span| source.lines().nth(span.line - 1))
+            .map(String::from);
+        self
+    }
+
+    /// Render the diagnostic, like:
+    ///
+    /// ```text
+    /// error: Could not parse assertion: `status to_equl 200`
+    ///  --> .capter/users.yml:6:17
+    ///   |
+    /// 6 |       - !expect status to_equl 200
+    ///   |                 ^^^^^^^^^^^^^^^^^^^
+    /// ```
+    pub fn render(&self) -> String {
+        let mut rendered = format!("error: {}\n", self.message);
+
+        let span = match self.span {
+            Some(span) => span,
+            None => {
+                rendered.push_str(&format!(" --> {}\n", self.file));
+                return rendered;
+            }
+        };
+
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        rendered.push_str(&format!(
+            "{}--> {}:{}:{}\n",
+            gutter, self.file, span.line, span.column
+        ));
+
+        if let Some(source_line) = &self.source_line {
+            let rest = source_line.chars().count().saturating_sub(span.column - 1);
+            rendered.push_str(&format!("{} |\n", gutter));
+            rendered.push_str(&format!("{} | {}\n", number, source_line));
+            rendered.push_str(&format!(
+                "{} | {}{}\n",
+                gutter,
+                " ".repeat(span.column - 1),
+                "^".repeat(self.len.min(rest).max(1))
+            ));
+        }
+
+        rendered
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.file, span.line, span.column, self.message
+            ),
+            None => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_render() {
+        let source = "name: test\nsteps:\n  - name: ${{ 'open }}\n";
+        let span = Span {
+            line: 3,
+            column: 11,
+            index: 28,
+        };
+        let diagnostic = Diagnostic::new("test.yml", "unterminated string".to_string()).at(
+            source,
+            Some(span),
+            12,
+        );
+
+        assert_eq!(
+            diagnostic.render(),
+            indoc! {"
+                error: unterminated string
+                 --> test.yml:3:11
+                  |
+                3 |   - name: ${{ 'open }}
+                  |           ^^^^^^^^^^^^
+            "}
+        );
+        assert_eq!(diagnostic.to_string(), "test.yml:3:11: unterminated string");
+    }
+}
@@ -0,0 +1,5 @@
+pub mod diagnostic;
+pub mod workflow;
+
+pub use diagnostic::Diagnostic;
+pub use workflow::{check_workflow, file_error, validate_files};
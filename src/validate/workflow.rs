@@ -0,0 +1,445 @@
+use crate::{
+    assert::assert::parse_assertion_string,
+    compile::expression::references,
+    error::CapterError,
+    validate::Diagnostic,
+    workflow::{Span, WorkflowConfig, WorkflowConfigAssertion, WorkflowConfigStep},
+};
+use path_clean::PathClean;
+use regex::Regex;
+use serde_json::Value;
+use std::{fs::read_to_string, path::PathBuf};
+
+/// Names that are always in the data, next to the step ids.
+const DATA_NAMES: &[&str] = &["env", "vars", "schemas"];
+
+/// Names that are only in the data in steps with `for_each`.
+const FOR_EACH_NAMES: &[&str] = &["item", "index"];
+
+/// Validate every workflow in `paths`, without sending any requests.
+///
+/// Files used with `extends` or `use` are checked as part of
+/// the workflows that use them. Returns the number of workflows
+/// that were checked, and every mistake that was found.
+pub fn validate_files(paths: &[PathBuf]) -> (usize, Vec<Diagnostic>) {
+    let results: Vec<Result<WorkflowConfig, CapterError>> =
+        paths.iter().map(WorkflowConfig::read_yaml_file).collect();
+    let includes: Vec<String> = results
+        .iter()
+        .flatten()
+        .flat_map(|config| config.includes.to_owned())
+        .collect();
+
+    let mut count = 0;
+    let mut diagnostics = vec![];
+
+    for (path, result) in paths.iter().zip(results) {
+        let file = path.clean().to_string_lossy().to_string();
+        if includes.contains(&file) {
+            continue;
+        }
+
+        count += 1;
+        match result {
+            Ok(config) => {
+                let yaml = read_to_string(path).unwrap_or_default();
+                diagnostics.append(&mut validate_workflow(&config, &file, &yaml));
+            }
+            Err(err) => diagnostics.push(file_error(path, &err)),
+        }
+    }
+
+    (count, diagnostics)
+}
+
+/// The mistake in a file that can't be read as a workflow.
+///
+/// `error` is from `WorkflowConfig::read_yaml_file`, but if the
+/// YAML can't be parsed, we point at where it went wrong instead.
+pub fn file_error(path: &PathBuf, error: &CapterError) -> Diagnostic {
+    let file = path.clean().to_string_lossy().to_string();
+    let yaml = read_to_string(path).unwrap_or_default();
+
+    let err = match WorkflowConfig::from_yaml(yaml.to_owned()) {
+        Err(err) => err,
+        Ok(_) => return Diagnostic::new(&file, error.to_string()),
+    };
+
+    // the location is shown with the line instead
+    let location = Regex::new(r" at line \d+ column \d+$").unwrap();
+    let message = location.replace(&err.to_string(), "").to_string();
+    let span = err.location().map(|location| Span {
+        line: location.line(),
+        column: location.column(),
+        index: location.index(),
+    });
+
+    Diagnostic::new(&file, message).at(&yaml, span, 1)
+}
+
+/// Find the mistakes that would stop a run half way, like an
+/// assertion or a template that can't be parsed, so they can be
+/// shown before any request is sent.
+///
+/// The workflow is read from `config.file` to show where they are.
+pub fn check_workflow(config: &WorkflowConfig) -> Vec<Diagnostic> {
+    let file = config.file.to_owned().unwrap_or_default();
+    let yaml = read_to_string(&file).unwrap_or_default();
+
+    config
+        .steps
+        .iter()
+        .flat_map(|step| check_step(step, &file, &yaml))
+        .collect()
+}
+
+/// Check a parsed workflow for mistakes that would otherwise only
+/// show up when it runs. On top of `check_workflow`, this checks that
+/// templates only use steps that have run, and that every step has a
+/// url and a unique id.
+///
+/// `yaml` is the content of `file`, used to show where the mistakes are.
+pub fn validate_workflow(config: &WorkflowConfig, file: &str, yaml: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let ids: Vec<Option<&String>> = config.steps.iter().map(|step| step.id.as_ref()).collect();
+
+    for (index, step) in config.steps.iter().enumerate() {
+        diagnostics.append(&mut check_step(step, file, yaml));
+        let mut error = |path: &str, text: &str, message: String| {
+            diagnostics.push(diagnostic(step, file, yaml, path, text, message));
+        };
+
+        if let Some(id) = &step.id {
+            if ids[..index].contains(&Some(id)) {
+                error(
+                    "id",
+                    id,
+                    format!(
+                        "step `{}` has the same id as an earlier step: `{}`",
+                        step.name, id
+                    ),
+                );
+            }
+        }
+
+        if step.url.is_none() && config.url.is_none() {
+            error("", "", format!("step `{}` has no url", step.name));
+        }
+
+        for (need_index, need) in step.needs.iter().flatten().enumerate() {
+            if !ids[..index].contains(&Some(need)) {
+                error(
+                    &format!("needs.{}", need_index),
+                    need,
+                    format!(
+                        "step `{}` needs `{}`, which isn't the id of an earlier step",
+                        step.name, need
+                    ),
+                );
+            }
+        }
+
+        for (path, template, expression) in step_templates(step) {
+            // templates that can't be parsed are found by `check_step`
+            let names = references(&expression).unwrap_or_default();
+
+            for name in names {
+                let is_id = ids[..=index].contains(&Some(&name));
+                let is_for_each = step.for_each.is_some() && FOR_EACH_NAMES.contains(&&*name);
+
+                if is_id || is_for_each || DATA_NAMES.contains(&&*name) {
+                    continue;
+                }
+
+                let message = match () {
+                    _ if FOR_EACH_NAMES.contains(&&*name) => {
+                        format!("`{}` is only set in steps with `for_each`", name)
+                    }
+                    _ if ids.contains(&Some(&name)) => {
+                        format!("`{}` is a step that runs after this one", name)
+                    }
+                    _ => format!(
+                        "`{}` isn't the id of an earlier step, or one of: {}",
+                        name,
+                        DATA_NAMES.join(", ")
+                    ),
+                };
+                error(&path, &template, message);
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// The mistakes in a step that would stop the run.
+fn check_step(step: &WorkflowConfigStep, file: &str, yaml: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    let assertions = step
+        .assertions
+        .iter()
+        .enumerate()
+        .map(|(index, assertion)| (format!("assertions.{}", index), assertion));
+    let until = step
+        .until
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, assertion)| (format!("until.{}", index), assertion));
+
+    for (path, assertion) in assertions.chain(until) {
+        let (assertion, not) = match assertion {
+            WorkflowConfigAssertion::expect(assertion) => (assertion, false),
+            WorkflowConfigAssertion::expect_not(assertion) => (assertion, true),
+        };
+        if let Err(err) = parse_assertion_string(assertion, not) {
+            diagnostics.push(diagnostic(
+                step,
+                file,
+                yaml,
+                &path,
+                assertion,
+                err.to_string(),
+            ));
+        }
+    }
+
+    for (path, template, expression) in step_templates(step) {
+        if let Err(err) = references(&expression) {
+            let message = format!("invalid template `{}`: {}", template, err);
+            diagnostics.push(diagnostic(step, file, yaml, &path, &template, message));
+        }
+    }
+
+    diagnostics
+}
+
+/// A diagnostic that points at `text` in the value at `path` in the step.
+///
+/// Values from a step in `use` point at the file they're in.
+fn diagnostic(
+    step: &WorkflowConfigStep,
+    file: &str,
+    yaml: &str,
+    path: &str,
+    text: &str,
+    message: String,
+) -> Diagnostic {
+    match step.span(path) {
+        Some((span, Some(used_file))) => {
+            let used_yaml = read_to_string(used_file).unwrap_or_default();
+            let span = span.find(&used_yaml, text);
+            Diagnostic::new(used_file, message).at(&used_yaml, Some(span), text.chars().count())
+        }
+        span => {
+            let span = span.map(|(span, _)| span.find(yaml, text));
+            Diagnostic::new(file, message).at(yaml, span, text.chars().count())
+        }
+    }
+}
+
+/// Every template in the step, with its path in the step
+/// and the expression inside of it.
+///
+/// `extract` is left out, since it's run on the response and
+/// not on the data, and `if` is an expression even without `${{ }}`.
+fn step_templates(step: &WorkflowConfigStep) -> Vec<(String, String, String)> {
+    let mut value = serde_json::to_value(step).unwrap_or_default();
+    if let Some(step) = value.as_object_mut() {
+        step.remove("extract");
+    }
+
+    let search = Regex::new(r"\$\{\{(.*?)}}").unwrap();
+    let mut templates = vec![];
+    let mut strings = vec![];
+    collect_strings(&value, String::new(), &mut strings);
+
+    for (path, string) in strings {
+        for hit in search.captures_iter(string) {
+            let expression = hit[1].trim();
+            let expression = expression.strip_prefix("mask ").unwrap_or(expression);
+            templates.push((path.to_owned(), hit[0].to_string(), expression.to_string()));
+        }
+    }
+
+    if let Some(condition) = &step.condition {
+        if !condition.contains("${{") {
+            templates.push(("if".to_string(), condition.to_owned(), condition.to_owned()));
+        }
+    }
+
+    templates
+}
+
+/// Every string in `value`, with its path, like `body.users.0.name`.
+fn collect_strings<'a>(value: &'a Value, path: String, strings: &mut Vec<(String, &'a str)>) {
+    let child = |key: &str| match path.is_empty() {
+        true => key.to_string(),
+        false => format!("{}.{}", path, key),
+    };
+
+    match value {
+        Value::String(string) => strings.push((path, string)),
+        Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                collect_strings(value, child(&index.to_string()), strings);
+            }
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                collect_strings(value, child(key), strings);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn validate(yaml: &str) -> Vec<String> {
+        let config = WorkflowConfig::from_yaml(yaml.to_string()).unwrap();
+        validate_workflow(&config, "test.yml", yaml)
+            .iter()
+            .map(Diagnostic::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let errors = validate(indoc! {"
+            name: test
+            steps:
+              - name: login
+                id: login
+                url: POST http://localhost/login
+                assertions:
+                  - !expect status to_equal 200
+              - name: users
+                needs: [login]
+                for_each: [1, 2]
+                url: http://localhost/users/${{ item }}?token=${{ mask login.response.body.token }}
+                if: env.USERS == true
+                extract:
+                  name: body.name
+                assertions:
+                  - !!expect body.name to_equal ${{ vars.name | upper }}
+        "});
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_errors() {
+        let errors = validate(indoc! {"
+            name: test
+            steps:
+              - name: login
+                id: login
+                url: POST http://localhost/login?user=${{ user.id }}
+                assertions:
+                  - !expect status to_be 200
+              - name: users
+                id: login
+                needs: [profile]
+                body:
+                  token: ${{ profile.response.body.token }}
+                assertions:
+                  - !expect body to_contain ${{ item }}
+              - name: profile
+                id: profile
+                url: http://localhost/profile/${{ 'open }}
+                assertions: []
+        "});
+        assert_eq!(
+            errors,
+            vec![
+                "test.yml:7:17: Could not parse assertion: `status to_be 200`",
+                "test.yml:5:43: `user` isn't the id of an earlier step, or one of: env, vars, schemas",
+                "test.yml:9:9: step `users` has the same id as an earlier step: `login`",
+                "test.yml:8:5: step `users` has no url",
+                "test.yml:10:13: step `users` needs `profile`, which isn't the id of an earlier step",
+                "test.yml:14:33: `item` is only set in steps with `for_each`",
+                "test.yml:12:14: `profile` is a step that runs after this one",
+                "test.yml:17:35: invalid template `${{ 'open }}`: unterminated string",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let path = std::env::temp_dir().join("capter_validate_parse_error.yml");
+        std::fs::write(
+            &path,
+            indoc! {"
+                name: test
+                steps:
+                  - name: login
+                    url: http://localhost
+            "},
+        )
+        .unwrap();
+
+        let (count, diagnostics) = validate_files(&[path]);
+        assert_eq!(count, 1);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "steps[0]: missing field `assertions`"
+        );
+        assert_eq!(diagnostics[0].span.map(|span| span.line), Some(3));
+    }
+
+    #[test]
+    fn test_used_step() {
+        let dir = std::env::temp_dir().join("capter_validate_use");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("login.yml"),
+            indoc! {"
+                name: login
+                url: POST http://localhost/login?user=${{ user.id }}
+                assertions:
+                  - !expect status to_equal 200
+                  - !expect body to_be 200
+            "},
+        )
+        .unwrap();
+        let path = dir.join("users.yml");
+        std::fs::write(
+            &path,
+            indoc! {"
+                name: users
+                steps:
+                  - use: ./login.yml
+                    assertions:
+                      - !expect body.token to_be_there
+            "},
+        )
+        .unwrap();
+
+        let (count, diagnostics) = validate_files(&[path]);
+        let errors: Vec<String> = diagnostics.iter().map(Diagnostic::to_string).collect();
+        let login = dir.join("login.yml").to_string_lossy().to_string();
+        let users = dir.join("users.yml").to_string_lossy().to_string();
+
+        // the mistakes in login.yml are shown there, not in users.yml
+        assert_eq!(count, 1);
+        assert_eq!(
+            errors,
+            vec![
+                format!("{}:5:13: Could not parse assertion: `body to_be 200`", login),
+                format!(
+                    "{}:5:17: Could not parse assertion: `body.token to_be_there`",
+                    users
+                ),
+                format!(
+                    "{}:2:39: `user` isn't the id of an earlier step, or one of: env, vars, schemas",
+                    login
+                ),
+            ]
+        );
+    }
+}
@@ -1,11 +1,15 @@
-use crate::utils::exit_with_code;
+use crate::{
+    error::CapterError,
+    workflow::span::{find_spans, Span},
+};
 use path_clean::PathClean;
-use serde::{Deserialize, Serialize};
+use regex::Regex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-    collections::{BTreeMap, HashMap},
-    fs::{create_dir_all, read_to_string, OpenOptions},
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, write, OpenOptions},
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 const EXAMPLE_WORKFLOW: &str = r#"name: example
@@ -22,36 +26,102 @@ steps:
 /// A `WorkflowConfig` is the struct we convert the yaml files into.
 ///
 /// When the CLI runs, it will convert every yaml into a `WorkflowConfig`,
-/// and run them synchronously. Each `WorkflowConfig` includes one or more
+/// and run them on a `WorkflowPool`. Each `WorkflowConfig` includes one or more
 /// *requests* that will be called when running it.
 ///
 /// This struct is used by `serde-yaml` to parse the files, so every property
 /// in this struct will be a valid value in those files.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct WorkflowConfig {
     pub file: Option<String>,
     pub name: String,
     pub url: Option<String>,
+    /// Steps with a relative url, like `/users`, are added to this.
+    pub base_url: Option<String>,
     pub method: Option<String>,
     pub headers: Option<BTreeMap<String, serde_yaml::Value>>,
     pub env: Option<BTreeMap<String, serde_yaml::Value>>,
+    pub schemas: Option<BTreeMap<String, serde_yaml::Value>>,
+    /// Path to an OpenAPI spec that every response is checked against.
+    pub openapi: Option<String>,
     pub steps: Vec<WorkflowConfigStep>,
     pub skip: Option<bool>,
+    pub tags: Option<Vec<String>>,
+    /// Skip the rest of the steps once a step fails.
+    pub fail_fast: Option<bool>,
+    /// A file to inherit `url`, `method`, `headers`, `env`,
+    /// `schemas`, `openapi` and `fail_fast` from.
+    pub extends: Option<String>,
+    /// Files used with `extends` and `use`, so they
+    /// aren't run as workflows on their own.
+    #[serde(skip)]
+    pub includes: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct WorkflowConfigStep {
+    /// Can be left out with `use`, to keep the name in the used file.
+    #[serde(default)]
     pub name: String,
     pub id: Option<String>,
     pub url: Option<String>,
     pub method: Option<String>,
-    pub query: Option<HashMap<String, String>>,
+    pub query: Option<BTreeMap<String, String>>,
     pub headers: Option<BTreeMap<String, serde_yaml::Value>>,
     pub body: Option<serde_yaml::Value>,
     pub assertions: Vec<WorkflowConfigAssertion>,
     pub options: Option<WorkflowConfigStepOptions>,
     pub graphql: Option<WorkflowConfigGraphQlConfig>,
     pub skip: Option<bool>,
+    pub tags: Option<Vec<String>>,
+    /// Only run the step if this expression is true, like `${{ vars.id != null }}`.
+    #[serde(rename = "if")]
+    pub condition: Option<String>,
+    /// Ids of steps that have to pass before this step runs.
+    pub needs: Option<Vec<String>>,
+    /// Run the step once for every item in a list, a template or a
+    /// CSV or JSON file, with `${{ item }}` and `${{ index }}` set.
+    /// With an `id`, only the last request and response are saved.
+    pub for_each: Option<serde_yaml::Value>,
+    /// A file with a step to use, like `./steps/login.yml`. Everything
+    /// else on this step overrides what's in the file, and the
+    /// assertions are added to the ones in the file.
+    #[serde(rename = "use")]
+    pub uses: Option<String>,
+    pub retry: Option<WorkflowConfigStepRetry>,
+    pub until: Option<Vec<WorkflowConfigAssertion>>,
+    pub schemas: Option<BTreeMap<String, serde_yaml::Value>>,
+    /// Variables set before the request, used like `${{ vars.name }}`.
+    pub set: Option<BTreeMap<String, serde_yaml::Value>>,
+    /// Variables taken from the response, used like `${{ vars.name }}`.
+    pub extract: Option<BTreeMap<String, WorkflowConfigExtract>>,
+    /// Where the step and its values are in the file, by their
+    /// path in the step, like `url` or `assertions.0`.
+    #[serde(skip)]
+    pub spans: BTreeMap<String, Span>,
+    /// The file a span is in, by the same path, for values
+    /// from another file, like a step used with `use`.
+    #[serde(skip)]
+    pub files: BTreeMap<String, String>,
+}
+
+impl WorkflowConfigStep {
+    /// Where the value at `path` is, like `body.user`, with the file
+    /// it's in if that's not the workflow. Falls back to the closest
+    /// parent we know, and then the step.
+    pub fn span(&self, path: &str) -> Option<(Span, Option<&str>)> {
+        let mut path = path;
+        loop {
+            if let Some(span) = self.spans.get(path) {
+                return Some((*span, self.files.get(path).map(String::as_str)));
+            }
+            match path.rfind('.') {
+                Some(index) => path = &path[..index],
+                None if !path.is_empty() => path = "",
+                None => return None,
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -66,6 +136,44 @@ pub struct WorkflowConfigStepOptions {
     pub mask: Option<Vec<String>>,
 }
 
+/// Makes a step re-issue its request until the assertions pass,
+/// or until `until` passes if that is set on the step.
+///
+/// `delay` is the number of milliseconds to wait before the first retry,
+/// and it's multiplied with `backoff` after every attempt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowConfigStepRetry {
+    pub attempts: u32,
+    pub delay: Option<u64>,
+    pub backoff: Option<f64>,
+}
+
+impl Default for WorkflowConfigStepRetry {
+    fn default() -> WorkflowConfigStepRetry {
+        WorkflowConfigStepRetry {
+            attempts: 3,
+            delay: Some(1000),
+            backoff: Some(1.0),
+        }
+    }
+}
+
+/// Takes a value from the response, either with an expression
+/// like `body.token`, or with a regex like `token=(\w+)` on the text.
+///
+/// The value is read from `text` when `regex` is set, and
+/// from `body` otherwise. Use `mask` to hide secrets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WorkflowConfigExtract {
+    Expression(String),
+    Options {
+        from: Option<String>,
+        regex: Option<String>,
+        mask: Option<bool>,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowConfigGraphQlConfig {
     pub query: String,
@@ -73,45 +181,110 @@ pub struct WorkflowConfigGraphQlConfig {
 }
 
 impl WorkflowConfig {
+    /// The directory of the workflow file. Files in `for_each`
+    /// and `to_match_schema` are relative to it.
+    pub fn dir(&self) -> PathBuf {
+        self.file
+            .as_ref()
+            .and_then(|file| Path::new(file).parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
     /// Create a WorfklowConfig from a path to a yaml file.
     ///
-    /// This will read the file and parse it, and exit if
-    /// something goes wrong.
-    pub fn from_yaml_file(path: &PathBuf) -> WorkflowConfig {
-        let path = match path.clean().into_os_string().into_string() {
+    /// `extends` and `use` in the file are resolved too.
+    pub fn read_yaml_file(path: impl AsRef<Path>) -> Result<WorkflowConfig, CapterError> {
+        let path = match path
+            .as_ref()
+            .to_path_buf()
+            .clean()
+            .into_os_string()
+            .into_string()
+        {
             Ok(path) => path,
-            _ => exit_with_code(exitcode::CONFIG, Some(&format!("Invalid path"))),
+            _ => return Err(CapterError::Config("Invalid path".to_string())),
         };
 
         let yaml = match read_to_string(&path) {
             Ok(val) => val,
-            _ => exit_with_code(exitcode::CONFIG, Some(&format!("Failed to read {}", path))),
+            _ => return Err(CapterError::Config(format!("Failed to read {}", path))),
         };
 
-        let result = WorkflowConfig::from_yaml(yaml);
-
-        match result {
-            Ok(mut workflow_config) => {
-                // if the user didn't set any file, add the actual file name
-                if workflow_config.file.is_none() {
-                    workflow_config.file = Some(path.to_owned());
-                }
+        let mut workflow_config = WorkflowConfig::from_yaml(yaml).map_err(|err| {
+            CapterError::Config(format!("Failed to parse {}: {}", path, &err.to_string()))
+        })?;
+        workflow_config
+            .resolve_includes(Path::new(&path))
+            .map_err(CapterError::Config)?;
 
-                workflow_config
-            }
-            Err(err) => {
-                let message = format!("Failed to parse {}: {}", path, &err.to_string());
-                exit_with_code(exitcode::CONFIG, Some(&message));
-            }
+        // if the user didn't set any file, add the actual file name
+        if workflow_config.file.is_none() {
+            workflow_config.file = Some(path.to_owned());
         }
+
+        Ok(workflow_config)
     }
 
     pub fn from_yaml(yaml: String) -> Result<WorkflowConfig, serde_yaml::Error> {
-        // we use a custom tag !!expect, which yaml-serde currently can't parse
-        // so we need to replace it with !expect_not manually before parsing
-        let yaml = str::replace(&yaml, "!!expect", "!expect_not");
+        let mut workflow_config: WorkflowConfig = parse_yaml(&yaml)?;
+
+        let spans = find_spans(&yaml);
+        for (index, step) in workflow_config.steps.iter_mut().enumerate() {
+            step.spans = spans_under(&spans, &format!("steps.{}", index));
+        }
+
+        Ok(workflow_config)
+    }
+
+    /// Convert the config back to yaml, in the same format we parse.
+    ///
+    /// Properties that are not set are left out, and assertions
+    /// are written with the `!expect` and `!!expect` tags.
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        let mut value = serde_yaml::to_value(self)?;
+
+        // leave out empty properties on the workflow and the steps,
+        // but keep everything in bodies and headers as it is
+        remove_nulls(&mut value);
+        if let Some(steps) = value
+            .get_mut("steps")
+            .and_then(serde_yaml::Value::as_sequence_mut)
+        {
+            steps.iter_mut().for_each(remove_nulls);
+        }
+
+        let yaml = serde_yaml::to_string(&value)?;
+
+        // serde-yaml writes enums as maps, like `- expect: status to_equal 200`
+        let expect = Regex::new(r"(?m)^(\s*)- expect(_not)?: ").unwrap();
+        let yaml = expect.replace_all(&yaml, |captures: &regex::Captures| match captures.get(2) {
+            Some(_) => format!("{}- !!expect ", &captures[1]),
+            None => format!("{}- !expect ", &captures[1]),
+        });
 
-        serde_yaml::from_str(&yaml)
+        Ok(yaml.to_string())
+    }
+
+    /// Write the config to `path`, unless the file already exists.
+    ///
+    /// Returns false if the file was already there.
+    pub fn create_file(&self, path: &str) -> Result<bool, CapterError> {
+        if PathBuf::from(path).exists() {
+            return Ok(false);
+        }
+
+        let error =
+            |err: String| CapterError::Config(format!("Failed to create {}: {}", path, err));
+
+        if let Some(parent) = PathBuf::from(path).parent() {
+            create_dir_all(parent).map_err(|err| error(err.to_string()))?;
+        }
+
+        let yaml = self.to_yaml().map_err(|err| error(err.to_string()))?;
+        write(path, yaml).map_err(|err| error(err.to_string()))?;
+
+        Ok(true)
     }
 
     pub fn create_example() {
@@ -122,6 +295,7 @@ impl WorkflowConfig {
         let mut file = OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(".capter/example.test.yml")
             .unwrap();
 
@@ -133,6 +307,45 @@ impl WorkflowConfig {
     }
 }
 
+/// Parse a workflow, or a part of one, like a step used with `use`.
+///
+/// We use a custom tag !!expect, which yaml-serde currently can't parse
+/// so we need to replace it with !expect_not manually before parsing.
+pub(crate) fn parse_yaml<T: DeserializeOwned>(yaml: &str) -> Result<T, serde_yaml::Error> {
+    serde_yaml::from_str(&str::replace(yaml, "!!expect", "!expect_not"))
+}
+
+/// The spans of the value at `prefix` and everything in it, by their
+/// path in that value. The spans are from the original YAML, before
+/// `parse_yaml`, so the columns are right.
+pub(crate) fn spans_under(spans: &BTreeMap<String, Span>, prefix: &str) -> BTreeMap<String, Span> {
+    if prefix.is_empty() {
+        return spans.to_owned();
+    }
+
+    spans
+        .range(prefix.to_string()..)
+        .take_while(|(path, _)| path.starts_with(prefix))
+        .filter_map(|(path, span)| match &path[prefix.len()..] {
+            "" => Some((String::new(), *span)),
+            rest => rest.strip_prefix('.').map(|rest| (rest.to_string(), *span)),
+        })
+        .collect()
+}
+
+/// Remove every property set to null in a mapping, keeping the order.
+fn remove_nulls(value: &mut serde_yaml::Value) {
+    if let Some(mapping) = value.as_mapping() {
+        let mapping: serde_yaml::Mapping = mapping
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+
+        *value = serde_yaml::Value::Mapping(mapping);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,7 +353,7 @@ mod tests {
 
     #[test]
     fn test_file() {
-        let config = WorkflowConfig::from_yaml_file(&PathBuf::from("./.capter/posts.yml"));
+        let config = WorkflowConfig::read_yaml_file(PathBuf::from("./.capter/posts.yml")).unwrap();
         assert_eq!(config.name, "posts");
     }
 
@@ -194,4 +407,42 @@ mod tests {
         };
         WorkflowConfig::from_yaml(yaml.into()).unwrap();
     }
+
+    #[test]
+    fn test_to_yaml() {
+        let yaml = indoc! {"
+            ---
+            name: test
+            steps:
+              - name: step 1
+                url: http://localhost:3002/test
+                body:
+                  id: ~
+                assertions:
+                  - !expect status to_equal 200
+                  - !!expect body to_be_empty
+            "
+        };
+        let config = WorkflowConfig::from_yaml(yaml.into()).unwrap();
+
+        let yaml = config.to_yaml().unwrap();
+        assert_eq!(
+            yaml,
+            indoc! {"
+                ---
+                name: test
+                steps:
+                  - name: step 1
+                    url: \"http://localhost:3002/test\"
+                    body:
+                      id: ~
+                    assertions:
+                      - !expect status to_equal 200
+                      - !!expect body to_be_empty
+            "}
+        );
+
+        let config = WorkflowConfig::from_yaml(yaml).unwrap();
+        assert_eq!(config.steps[0].assertions.len(), 2);
+    }
 }
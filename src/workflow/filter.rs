@@ -0,0 +1,161 @@
+use crate::workflow::WorkflowConfig;
+use regex::Regex;
+
+/// Picks which workflows and steps to run, from `--tag`,
+/// `--exclude-tag`, `--grep` and `--only`.
+///
+/// Everything that's filtered out is set to `skip`, so it
+/// shows up as skipped in the run like any other skipped step.
+///
+/// A step has the tags of its workflow, on top of its own tags.
+/// A workflow is skipped if all of its steps are filtered out.
+#[derive(Debug, Default)]
+pub struct WorkflowFilter {
+    /// Only run steps with one of these tags.
+    pub tags: Vec<String>,
+    /// Never run steps with one of these tags.
+    pub exclude_tags: Vec<String>,
+    /// Only run steps where the workflow or step name matches.
+    pub grep: Option<Regex>,
+    /// Only run workflows or steps with one of these names,
+    /// or workflows in one of these files.
+    pub only: Vec<String>,
+}
+
+impl WorkflowFilter {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+            && self.exclude_tags.is_empty()
+            && self.grep.is_none()
+            && self.only.is_empty()
+    }
+
+    /// Set `skip` on every step in the workflow that's filtered out,
+    /// and on the workflow if there's nothing left to run.
+    pub fn apply(&self, config: &mut WorkflowConfig) {
+        if self.is_empty() {
+            return;
+        }
+
+        let workflow_tags = config.tags.to_owned().unwrap_or_default();
+        let is_workflow_only = self
+            .only
+            .iter()
+            .any(|only| *only == config.name || Some(only) == config.file.as_ref());
+
+        for step in config.steps.iter_mut() {
+            let tags: Vec<&String> = workflow_tags
+                .iter()
+                .chain(step.tags.iter().flatten())
+                .collect();
+
+            let has_tag = self.tags.is_empty() || self.tags.iter().any(|tag| tags.contains(&tag));
+            let is_excluded = self.exclude_tags.iter().any(|tag| tags.contains(&tag));
+            let is_grep_match = self
+                .grep
+                .as_ref()
+                .is_none_or(|grep| grep.is_match(&config.name) || grep.is_match(&step.name));
+            let is_only =
+                self.only.is_empty() || is_workflow_only || self.only.contains(&step.name);
+
+            if !has_tag || is_excluded || !is_grep_match || !is_only {
+                step.skip = Some(true);
+            }
+        }
+
+        if config.steps.iter().all(|step| step.skip == Some(true)) {
+            config.skip = Some(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn workflow() -> WorkflowConfig {
+        let yaml = indoc! {"
+            ---
+            name: users
+            tags: [users]
+            steps:
+              - name: login
+                tags: [smoke]
+                url: http://localhost/login
+                assertions: []
+              - name: list users
+                url: http://localhost/users
+                assertions: []
+              - name: export users
+                tags: [slow]
+                url: http://localhost/users/export
+                assertions: []
+        "};
+        WorkflowConfig::from_yaml(yaml.to_string()).unwrap()
+    }
+
+    fn skipped(config: &WorkflowConfig) -> Vec<bool> {
+        config
+            .steps
+            .iter()
+            .map(|step| step.skip == Some(true))
+            .collect()
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut config = workflow();
+        let filter = WorkflowFilter {
+            tags: vec!["smoke".to_string()],
+            ..Default::default()
+        };
+        filter.apply(&mut config);
+        assert_eq!(skipped(&config), vec![false, true, true]);
+        assert_eq!(config.skip, None);
+
+        let mut config = workflow();
+        let filter = WorkflowFilter {
+            tags: vec!["users".to_string()],
+            exclude_tags: vec!["slow".to_string()],
+            ..Default::default()
+        };
+        filter.apply(&mut config);
+        assert_eq!(skipped(&config), vec![false, false, true]);
+
+        let mut config = workflow();
+        let filter = WorkflowFilter {
+            tags: vec!["other".to_string()],
+            ..Default::default()
+        };
+        filter.apply(&mut config);
+        assert_eq!(config.skip, Some(true));
+    }
+
+    #[test]
+    fn test_grep_and_only() {
+        let mut config = workflow();
+        let filter = WorkflowFilter {
+            grep: Some(Regex::new("(?i)LIST").unwrap()),
+            ..Default::default()
+        };
+        filter.apply(&mut config);
+        assert_eq!(skipped(&config), vec![true, false, true]);
+
+        let mut config = workflow();
+        let filter = WorkflowFilter {
+            grep: Some(Regex::new("users").unwrap()),
+            ..Default::default()
+        };
+        filter.apply(&mut config);
+        assert_eq!(skipped(&config), vec![false, false, false]);
+
+        let mut config = workflow();
+        let filter = WorkflowFilter {
+            only: vec!["login".to_string()],
+            ..Default::default()
+        };
+        filter.apply(&mut config);
+        assert_eq!(skipped(&config), vec![false, true, true]);
+    }
+}
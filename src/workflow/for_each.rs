@@ -0,0 +1,205 @@
+use crate::{
+    compile::{compile_value, expression::evaluate},
+    error::CapterError,
+    workflow::WorkflowConfigStep,
+};
+use serde_json::{Map, Value};
+use std::{fs::read_to_string, path::Path};
+
+/// The items to run a step with, from `for_each` on the step,
+/// or `None` if the step only runs once.
+///
+/// `for_each` can be:
+///
+/// - a list, like `[en, de, fr]`, where the items can use templates
+/// - a template, like `${{ products.response.body }}`
+/// - a CSV or JSON file, like `fixtures/users.csv`, relative to `dir`
+///
+/// Every row in a CSV file is an object, with the keys from the first row.
+pub fn for_each_items(
+    step: &WorkflowConfigStep,
+    workflow_data: &Value,
+    dir: &Path,
+) -> Result<Option<Vec<Value>>, CapterError> {
+    let for_each = match &step.for_each {
+        Some(for_each) => for_each,
+        None => return Ok(None),
+    };
+
+    let items = match for_each {
+        serde_yaml::Value::String(value) => {
+            let value = value.trim();
+            match value
+                .strip_prefix("${{")
+                .and_then(|expression| expression.strip_suffix("}}"))
+            {
+                Some(expression) => evaluate(expression, workflow_data)
+                    .map_err(|err| CapterError::Template(format!("invalid `for_each`: {}", err)))?,
+                None => read_items(&dir.join(value)).map_err(CapterError::Config)?,
+            }
+        }
+        value => {
+            let compiled = compile_value(Some(value.to_owned()), workflow_data)?;
+            serde_json::to_value(compiled.raw).unwrap_or_default()
+        }
+    };
+
+    match items {
+        Value::Array(items) => Ok(Some(items)),
+        value => Err(CapterError::Config(format!(
+            "`for_each` has to be a list, got {}",
+            value
+        ))),
+    }
+}
+
+/// Read the items from a CSV or JSON file.
+fn read_items(path: &Path) -> Result<Value, String> {
+    let path_name = path.to_string_lossy();
+    let content =
+        read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path_name, err))?;
+
+    let is_csv = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+
+    match is_csv {
+        true => parse_csv(&content).map(Value::Array),
+        false => serde_json::from_str(&content)
+            .map_err(|err| format!("Failed to parse {}: {}", path_name, err)),
+    }
+}
+
+/// Parse a CSV file in to one object per row. Values can be
+/// quoted, like `"Doe, John"`, and `""` is a quote in a quoted value.
+fn parse_csv(content: &str) -> Result<Vec<Value>, String> {
+    let mut rows = parse_csv_rows(content)?.into_iter();
+    let headers = rows.next().unwrap_or_default();
+
+    rows.enumerate()
+        .map(|(index, row)| {
+            if row.len() != headers.len() {
+                return Err(format!(
+                    "row {} has {} values, but there are {} columns",
+                    index + 2,
+                    row.len(),
+                    headers.len()
+                ));
+            }
+
+            let item: Map<String, Value> = headers
+                .iter()
+                .cloned()
+                .zip(row.into_iter().map(Value::String))
+                .collect();
+            Ok(Value::Object(item))
+        })
+        .collect()
+}
+
+fn parse_csv_rows(content: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut is_quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match (char, is_quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => is_quoted = false,
+            ('"', false) if field.is_empty() => is_quoted = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) => (),
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (char, _) => field.push(char),
+        }
+    }
+
+    if is_quoted {
+        return Err("a quoted value is never closed".to_string());
+    }
+
+    // the last line doesn't need a line break
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    // empty lines are ignored
+    rows.retain(|row| !(row.len() == 1 && row[0].is_empty()));
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use serde_json::json;
+
+    fn step(for_each: &str) -> WorkflowConfigStep {
+        let yaml = format!(
+            "{{ name: step, url: http://localhost, for_each: {}, assertions: [] }}",
+            for_each
+        );
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    #[test]
+    fn test_for_each_items() {
+        let data = json!({ "locale": "en", "products": { "body": [1, 2] } });
+
+        let items = for_each_items(&step("['${{ locale }}', de, 3]"), &data, Path::new(""));
+        assert_eq!(items, Ok(Some(vec![json!("en"), json!("de"), json!(3)])));
+
+        let items = for_each_items(&step("'${{ products.body }}'"), &data, Path::new(""));
+        assert_eq!(items, Ok(Some(vec![json!(1), json!(2)])));
+
+        let items = for_each_items(&step("[]"), &data, Path::new(""));
+        assert_eq!(items, Ok(Some(vec![])));
+    }
+
+    #[test]
+    fn test_not_a_list() {
+        let items = for_each_items(
+            &step("'${{ locale }}'"),
+            &json!({ "locale": "en" }),
+            Path::new(""),
+        );
+        assert_eq!(
+            items,
+            Err(CapterError::Config(
+                "`for_each` has to be a list, got \"en\"".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let items = parse_csv(indoc! {r#"
+            id,name
+            1,"Doe, John"
+            2,"say ""hi"""
+
+        "#})
+        .unwrap();
+
+        assert_eq!(
+            items,
+            vec![
+                json!({ "id": "1", "name": "Doe, John" }),
+                json!({ "id": "2", "name": "say \"hi\"" }),
+            ]
+        );
+
+        assert!(parse_csv("id,name\n1").is_err());
+        assert!(parse_csv("id\n\"1").is_err());
+    }
+}
@@ -0,0 +1,408 @@
+use crate::workflow::{
+    config::{parse_yaml, spans_under},
+    span::find_spans,
+    Span, WorkflowConfig, WorkflowConfigStep,
+};
+use path_clean::PathClean;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+/// What a workflow gets from the file in `extends`.
+///
+/// Any workflow can be extended, but only these properties are
+/// inherited, so a base file doesn't need `name` or `steps`.
+#[derive(Debug, Deserialize)]
+struct WorkflowConfigBase {
+    extends: Option<String>,
+    url: Option<String>,
+    method: Option<String>,
+    headers: Option<BTreeMap<String, serde_yaml::Value>>,
+    env: Option<BTreeMap<String, serde_yaml::Value>>,
+    schemas: Option<BTreeMap<String, serde_yaml::Value>>,
+    openapi: Option<String>,
+    fail_fast: Option<bool>,
+}
+
+impl WorkflowConfig {
+    /// Resolve `extends` on the workflow, and `use` on the steps.
+    ///
+    /// Paths are relative to the file they are in, and `file` is
+    /// the file this workflow was read from. Every file that's used
+    /// is added to `includes`.
+    pub fn resolve_includes(&mut self, file: &Path) -> Result<(), String> {
+        let file = file.to_path_buf().clean();
+
+        // every base is inherited in turn, so the closest one wins
+        let mut chain = vec![file.to_owned()];
+        let mut extends = self.extends.take();
+        while let Some(include) = extends {
+            let from = chain[chain.len() - 1].to_owned();
+            let mut base = read_base(&from, &include, &mut chain, &mut self.includes)?;
+            extends = base.extends.take();
+            self.inherit(base);
+        }
+
+        for (index, step) in self.steps.iter_mut().enumerate() {
+            if let Some(uses) = step.uses.take() {
+                let mut chain = vec![file.to_owned()];
+                let used = read_step(&file, &uses, &mut chain, &mut self.includes)?;
+                *step = merge_step(used, step.to_owned());
+            }
+
+            if step.name.is_empty() {
+                return Err(format!(
+                    "step {} in {} has no name",
+                    index + 1,
+                    file.to_string_lossy()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Properties set on the workflow win over the ones in `base`.
+    fn inherit(&mut self, base: WorkflowConfigBase) {
+        self.url = self.url.take().or(base.url);
+        self.method = self.method.take().or(base.method);
+        self.openapi = self.openapi.take().or(base.openapi);
+        self.fail_fast = self.fail_fast.take().or(base.fail_fast);
+        self.headers = merge(base.headers, self.headers.take());
+        self.env = merge(base.env, self.env.take());
+        self.schemas = merge(base.schemas, self.schemas.take());
+    }
+}
+
+/// Read the file in `extends`. What that file extends is
+/// left in `extends` on the base, and added to `chain`.
+fn read_base(
+    from: &Path,
+    extends: &str,
+    chain: &mut Vec<PathBuf>,
+    includes: &mut Vec<String>,
+) -> Result<WorkflowConfigBase, String> {
+    let path = include_path(from, extends, chain, includes)?;
+    let yaml = read_include(&path, from, "extended")?;
+
+    serde_yaml::from_str(&yaml)
+        .map_err(|err| format!("Failed to parse {}: {}", path.to_string_lossy(), err))
+}
+
+/// Read the step in `use`, and whatever step that step uses.
+fn read_step(
+    from: &Path,
+    uses: &str,
+    chain: &mut Vec<PathBuf>,
+    includes: &mut Vec<String>,
+) -> Result<WorkflowConfigStep, String> {
+    let path = include_path(from, uses, chain, includes)?;
+    let yaml = read_include(&path, from, "used")?;
+
+    let mut step: WorkflowConfigStep = parse_yaml(&yaml)
+        .map_err(|err| format!("Failed to parse {}: {}", path.to_string_lossy(), err))?;
+
+    // mistakes in the step are shown in this file
+    let file = path.to_string_lossy().to_string();
+    step.spans = spans_under(&find_spans(&yaml), "");
+    step.files = step
+        .spans
+        .keys()
+        .map(|key| (key.to_owned(), file.to_owned()))
+        .collect();
+
+    if let Some(uses) = step.uses.take() {
+        let used = read_step(&path, &uses, chain, includes)?;
+        step = merge_step(used, step);
+    }
+
+    Ok(step)
+}
+
+/// The path to an included file, relative to the file it's in.
+///
+/// Returns an error if the file is already in `chain`,
+/// since including it again would never end.
+fn include_path(
+    from: &Path,
+    include: &str,
+    chain: &mut Vec<PathBuf>,
+    includes: &mut Vec<String>,
+) -> Result<PathBuf, String> {
+    let dir = from.parent().unwrap_or_else(|| Path::new(""));
+    let path = dir.join(include).clean();
+
+    if chain.contains(&path) {
+        let files: Vec<String> = chain
+            .iter()
+            .chain([&path])
+            .map(|file| file.to_string_lossy().to_string())
+            .collect();
+        return Err(format!(
+            "{} includes itself: {}",
+            files[0],
+            files.join(" → ")
+        ));
+    }
+
+    chain.push(path.to_owned());
+    includes.push(path.to_string_lossy().to_string());
+    Ok(path)
+}
+
+fn read_include(path: &Path, from: &Path, verb: &str) -> Result<String, String> {
+    read_to_string(path).map_err(|_| {
+        format!(
+            "Failed to read {}, {} in {}",
+            path.to_string_lossy(),
+            verb,
+            from.to_string_lossy()
+        )
+    })
+}
+
+/// Properties that are merged by `merge_step`, instead of replaced.
+const MERGED: &[&str] = &[
+    "query",
+    "headers",
+    "schemas",
+    "set",
+    "extract",
+    "assertions",
+];
+
+/// Use a step from another file, with `overrides` from the step
+/// that uses it. Maps like `headers` and `set` are merged, and
+/// the assertions are added to the ones in the used step.
+fn merge_step(used: WorkflowConfigStep, overrides: WorkflowConfigStep) -> WorkflowConfigStep {
+    let (spans, files) = merge_spans(&used, &overrides);
+    let mut assertions = used.assertions;
+    assertions.extend(overrides.assertions);
+
+    WorkflowConfigStep {
+        name: match overrides.name.is_empty() {
+            true => used.name,
+            false => overrides.name,
+        },
+        id: overrides.id.or(used.id),
+        url: overrides.url.or(used.url),
+        method: overrides.method.or(used.method),
+        query: merge(used.query, overrides.query),
+        headers: merge(used.headers, overrides.headers),
+        body: overrides.body.or(used.body),
+        assertions,
+        options: overrides.options.or(used.options),
+        graphql: overrides.graphql.or(used.graphql),
+        skip: overrides.skip.or(used.skip),
+        tags: overrides.tags.or(used.tags),
+        condition: overrides.condition.or(used.condition),
+        needs: overrides.needs.or(used.needs),
+        for_each: overrides.for_each.or(used.for_each),
+        uses: None,
+        retry: overrides.retry.or(used.retry),
+        until: overrides.until.or(used.until),
+        schemas: merge(used.schemas, overrides.schemas),
+        set: merge(used.set, overrides.set),
+        extract: merge(used.extract, overrides.extract),
+        spans,
+        files,
+    }
+}
+
+/// Where the values of the merged step are. Values from `used` keep
+/// their place in its file, unless they're replaced by `overrides`,
+/// and the assertions in `overrides` come after the ones in `used`.
+fn merge_spans(
+    used: &WorkflowConfigStep,
+    overrides: &WorkflowConfigStep,
+) -> (BTreeMap<String, Span>, BTreeMap<String, String>) {
+    let property = |path: &str| path.split('.').next().unwrap_or_default().to_string();
+    let replaced: Vec<String> = overrides
+        .spans
+        .keys()
+        .map(|path| property(path))
+        .filter(|property| !property.is_empty() && !MERGED.contains(&property.as_str()))
+        .collect();
+
+    let mut spans = used.spans.to_owned();
+    let mut files = used.files.to_owned();
+    spans.retain(|path, _| !replaced.contains(&property(path)));
+    files.retain(|path, _| !replaced.contains(&property(path)));
+
+    for (path, span) in &overrides.spans {
+        let merged_path = match path
+            .strip_prefix("assertions.")
+            .and_then(|rest| rest.split_once('.').or(Some((rest, ""))))
+            .and_then(|(index, rest)| Some((index.parse::<usize>().ok()?, rest)))
+        {
+            Some((index, "")) => format!("assertions.{}", index + used.assertions.len()),
+            Some((index, rest)) => {
+                format!("assertions.{}.{}", index + used.assertions.len(), rest)
+            }
+            None => path.to_owned(),
+        };
+
+        match overrides.files.get(path) {
+            Some(file) => files.insert(merged_path.to_owned(), file.to_owned()),
+            None => files.remove(&merged_path),
+        };
+        spans.insert(merged_path, *span);
+    }
+
+    (spans, files)
+}
+
+/// Merge two maps, where `overrides` wins.
+fn merge<M, T>(base: Option<M>, overrides: Option<M>) -> Option<M>
+where
+    M: Extend<T> + IntoIterator<Item = T>,
+{
+    match (base, overrides) {
+        (Some(mut base), Some(overrides)) => {
+            base.extend(overrides);
+            Some(base)
+        }
+        (base, overrides) => overrides.or(base),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::{env::temp_dir, fs::create_dir_all, fs::write};
+
+    #[test]
+    fn test_extends_and_use() {
+        let dir = temp_dir().join("capter_include");
+        create_dir_all(dir.join("steps")).unwrap();
+        write(
+            dir.join("base.yml"),
+            indoc! {"
+                extends: ./root.yml
+                headers:
+                  authorization: Bearer ${{ vars.token }}
+                env:
+                  USER: base
+            "},
+        )
+        .unwrap();
+        write(
+            dir.join("root.yml"),
+            indoc! {"
+                url: http://localhost
+                env:
+                  USER: root
+                  PASSWORD: root
+            "},
+        )
+        .unwrap();
+        write(
+            dir.join("steps/login.yml"),
+            indoc! {"
+                name: login
+                url: POST /login
+                body:
+                  user: ${{ env.USER }}
+                set:
+                  user: test
+                  role: admin
+                assertions:
+                  - !expect status to_equal 200
+            "},
+        )
+        .unwrap();
+
+        let file = dir.join("users.yml");
+        write(
+            &file,
+            indoc! {"
+                name: users
+                extends: base.yml
+                env:
+                  USER: users
+                steps:
+                  - use: ./steps/login.yml
+                    set:
+                      user: other
+                    assertions:
+                      - !!expect body.token to_be_null
+                  - name: list users
+                    url: /users
+                    assertions: []
+            "},
+        )
+        .unwrap();
+
+        let config = WorkflowConfig::read_yaml_file(&file).unwrap();
+
+        assert_eq!(config.url, Some("http://localhost".to_string()));
+        let env = config.env.as_ref().unwrap();
+        assert_eq!(env["USER"], "users");
+        assert_eq!(env["PASSWORD"], "root");
+        assert!(config.headers.as_ref().unwrap()["authorization"].is_string());
+
+        let login = &config.steps[0];
+        assert_eq!(login.name, "login");
+        assert_eq!(login.url, Some("POST /login".to_string()));
+        let set = login.set.as_ref().unwrap();
+        assert_eq!(set["user"], "other");
+        assert_eq!(set["role"], "admin");
+        assert_eq!(login.assertions.len(), 2);
+        assert!(login.uses.is_none());
+
+        let dir = dir.to_string_lossy().to_string();
+        assert_eq!(
+            config.includes,
+            vec![
+                format!("{}/base.yml", dir),
+                format!("{}/root.yml", dir),
+                format!("{}/steps/login.yml", dir),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cycle() {
+        let dir = temp_dir().join("capter_include_cycle");
+        create_dir_all(&dir).unwrap();
+        write(dir.join("a.yml"), "extends: b.yml").unwrap();
+        write(dir.join("b.yml"), "extends: a.yml").unwrap();
+
+        let mut config =
+            WorkflowConfig::from_yaml("{ name: a, extends: b.yml, steps: [] }".to_string())
+                .unwrap();
+        let err = config.resolve_includes(&dir.join("a.yml")).unwrap_err();
+
+        let dir = dir.to_string_lossy();
+        assert_eq!(
+            err,
+            format!(
+                "{dir}/a.yml includes itself: {dir}/a.yml → {dir}/b.yml → {dir}/a.yml",
+                dir = dir
+            )
+        );
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let mut config = WorkflowConfig::from_yaml(
+            "{ name: a, steps: [{ use: ./nope.yml, assertions: [] }] }".to_string(),
+        )
+        .unwrap();
+        let err = config
+            .resolve_includes(Path::new("tests/a.yml"))
+            .unwrap_err();
+        assert_eq!(err, "Failed to read tests/nope.yml, used in tests/a.yml");
+
+        let mut config = WorkflowConfig::from_yaml(
+            "{ name: a, steps: [{ url: /users, assertions: [] }] }".to_string(),
+        )
+        .unwrap();
+        let err = config.resolve_includes(Path::new("a.yml")).unwrap_err();
+        assert_eq!(err, "step 1 in a.yml has no name");
+    }
+}
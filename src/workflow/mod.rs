@@ -1,12 +1,24 @@
 pub mod config;
+pub mod filter;
+pub mod for_each;
+pub mod include;
+pub mod pool;
 pub mod request;
 pub mod response;
+pub mod run_options;
 pub mod run_source;
+pub mod span;
+pub mod vars;
 pub mod workflow_result;
 
 pub use config::{
-    WorkflowConfig, WorkflowConfigAssertion, WorkflowConfigStep, WorkflowConfigStepOptions,
+    WorkflowConfig, WorkflowConfigAssertion, WorkflowConfigExtract, WorkflowConfigGraphQlConfig,
+    WorkflowConfigStep, WorkflowConfigStepOptions, WorkflowConfigStepRetry,
 };
+pub use filter::WorkflowFilter;
+pub use pool::WorkflowPool;
 pub use request::{Request, RequestData};
 pub use response::ResponseData;
+pub use run_options::{RunOptions, RunOptionsBuilder};
 pub use run_source::RunSource;
+pub use span::Span;
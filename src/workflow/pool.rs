@@ -0,0 +1,525 @@
+use crate::{
+    error::CapterError,
+    workflow::{
+        workflow_result::{CallbackEvent, SkipReason, WorkflowResult},
+        RequestData, RunOptions, WorkflowConfig,
+    },
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+/// Runs workflows on a pool of worker threads.
+///
+/// Every `WorkflowConfig` is independent, so we can run several of them
+/// at the same time. The events from the workers are sent back to the
+/// thread calling `.run()` and replayed in the same order as the configs,
+/// so the callback sees the same thing it would if the workflows ran one
+/// after another. Events for the first unfinished workflow are passed on
+/// as they arrive, everything else is buffered until it's its turn.
+pub struct WorkflowPool<'a> {
+    run_options: &'a RunOptions,
+    jobs: usize,
+}
+
+/// An owned version of `CallbackEvent`, so it can be sent between threads.
+enum WorkerEvent {
+    RunStart,
+    RunDone(bool),
+    RunSkipped,
+    RunErrored(CapterError),
+    StepStart(i32),
+    StepDone(i32, Box<RequestData>, bool),
+    StepSkipped(i32, SkipReason),
+    StepRetry(i32, u32),
+}
+
+enum WorkerMessage {
+    Event(usize, WorkerEvent),
+    Done(usize, Option<Box<WorkflowResult>>),
+}
+
+impl<'a> WorkflowPool<'a> {
+    pub fn new(run_options: &'a RunOptions) -> WorkflowPool<'a> {
+        WorkflowPool {
+            run_options,
+            jobs: run_options.jobs.max(1),
+        }
+    }
+
+    /// Runs `setup` first, then the workflows in `configs`, and `teardown`
+    /// last, even if something before it failed. The variables from setup
+    /// are shared with every workflow after it, and if setup fails there's
+    /// no point in running the workflows, so they're skipped.
+    ///
+    /// Returns the results in the order the workflows ran.
+    pub fn run_with_setup(
+        &self,
+        setup: Option<&WorkflowConfig>,
+        configs: &[WorkflowConfig],
+        teardown: Option<&WorkflowConfig>,
+        mut callback: impl FnMut(CallbackEvent),
+    ) -> Vec<WorkflowResult> {
+        let mut results = vec![];
+        let mut run_options = self.run_options.to_owned();
+
+        if let Some(setup) = setup {
+            let result = WorkflowResult::from_config(&run_options, setup, &mut callback)
+                .unwrap_or_else(|err| WorkflowResult::from_error(&run_options, setup, &err));
+            run_options = run_options.to_builder().setup_vars(&result).build();
+            results.push(result);
+        }
+
+        let skipped: Vec<WorkflowConfig>;
+        let configs = match results.iter().all(|result| result.passed) {
+            true => configs,
+            false => {
+                skipped = configs
+                    .iter()
+                    .cloned()
+                    .map(|mut config| {
+                        config.skip = Some(true);
+                        config
+                    })
+                    .collect();
+                &skipped
+            }
+        };
+
+        let pool = WorkflowPool {
+            run_options: &run_options,
+            jobs: self.jobs,
+        };
+        results.append(&mut pool.run(configs, &mut callback));
+
+        if let Some(teardown) = teardown {
+            let result = WorkflowResult::from_config(&run_options, teardown, &mut callback)
+                .unwrap_or_else(|err| WorkflowResult::from_error(&run_options, teardown, &err));
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Runs all workflows in `configs` and returns the results in the
+    /// same order as the configs. Skipped workflows get a skipped result,
+    /// and workflows that errored get one with the error.
+    ///
+    /// The callback is always called from the current thread.
+    pub fn run(
+        &self,
+        configs: &[WorkflowConfig],
+        mut callback: impl FnMut(CallbackEvent),
+    ) -> Vec<WorkflowResult> {
+        let jobs = self.jobs.min(configs.len()).max(1);
+        let next_job = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+
+        let mut results: Vec<Option<Box<WorkflowResult>>> = configs.iter().map(|_| None).collect();
+
+        thread::scope(|scope| {
+            for _ in 0..jobs {
+                let sender = sender.clone();
+                let next_job = &next_job;
+                let run_options = self.run_options;
+
+                scope.spawn(move || loop {
+                    let index = next_job.fetch_add(1, Ordering::SeqCst);
+                    let config = match configs.get(index) {
+                        Some(config) => config,
+                        None => break,
+                    };
+
+                    // setting `skip: true` in the workflow will stop
+                    // it from running, but it's still in the results
+                    if config.skip.is_some() {
+                        let result = WorkflowResult::from_skipped(run_options, config);
+                        sender
+                            .send(WorkerMessage::Event(index, WorkerEvent::RunSkipped))
+                            .ok();
+                        sender
+                            .send(WorkerMessage::Done(index, Some(Box::new(result))))
+                            .ok();
+                        continue;
+                    }
+
+                    let result = WorkflowResult::from_config(run_options, config, |event| {
+                        sender.send(WorkerMessage::Event(index, event.into())).ok();
+                    })
+                    .unwrap_or_else(|err| WorkflowResult::from_error(run_options, config, &err));
+
+                    sender
+                        .send(WorkerMessage::Done(index, Some(Box::new(result))))
+                        .ok();
+                });
+            }
+
+            // only the workers should hold a sender, otherwise
+            // the loop below would never end
+            drop(sender);
+
+            // events for workflows that are not first in line yet
+            let mut buffered: Vec<Vec<WorkerEvent>> = configs.iter().map(|_| vec![]).collect();
+            let mut finished: Vec<bool> = configs.iter().map(|_| false).collect();
+            let mut current = 0;
+
+            for message in receiver {
+                match message {
+                    WorkerMessage::Event(index, event) => {
+                        if index == current {
+                            callback(event.as_callback_event(&configs[index]));
+                        } else {
+                            buffered[index].push(event);
+                        }
+                    }
+                    WorkerMessage::Done(index, result) => {
+                        results[index] = result;
+                        finished[index] = true;
+
+                        // move on to the next workflow in line, and replay
+                        // everything it has done while it was waiting
+                        while current < configs.len() && finished[current] {
+                            current += 1;
+                            if let Some(events) = buffered.get_mut(current) {
+                                for event in events.drain(..) {
+                                    callback(event.as_callback_event(&configs[current]));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        results
+            .into_iter()
+            .flatten()
+            .map(|result| *result)
+            .collect()
+    }
+}
+
+impl WorkerEvent {
+    fn as_callback_event<'a>(&'a self, config: &'a WorkflowConfig) -> CallbackEvent<'a> {
+        match self {
+            WorkerEvent::RunStart => CallbackEvent::RunStart(config),
+            WorkerEvent::RunDone(passed) => CallbackEvent::RunDone(config, *passed),
+            WorkerEvent::RunSkipped => CallbackEvent::RunSkipped(config),
+            WorkerEvent::RunErrored(error) => CallbackEvent::RunErrored(config, error),
+            WorkerEvent::StepStart(index) => CallbackEvent::StepStart(config, *index),
+            WorkerEvent::StepDone(index, request_data, passed) => {
+                CallbackEvent::StepDone(config, *index, request_data, *passed)
+            }
+            WorkerEvent::StepSkipped(index, reason) => {
+                CallbackEvent::StepSkipped(config, *index, reason)
+            }
+            WorkerEvent::StepRetry(index, attempt) => {
+                CallbackEvent::StepRetry(config, *index, *attempt)
+            }
+        }
+    }
+}
+
+impl From<CallbackEvent<'_>> for WorkerEvent {
+    fn from(event: CallbackEvent) -> Self {
+        match event {
+            CallbackEvent::RunStart(_) => WorkerEvent::RunStart,
+            CallbackEvent::RunDone(_, passed) => WorkerEvent::RunDone(passed),
+            CallbackEvent::RunSkipped(_) => WorkerEvent::RunSkipped,
+            CallbackEvent::RunErrored(_, error) => WorkerEvent::RunErrored(error.to_owned()),
+            CallbackEvent::StepStart(_, index) => WorkerEvent::StepStart(index),
+            CallbackEvent::StepDone(_, index, request_data, passed) => {
+                WorkerEvent::StepDone(index, Box::new(request_data.to_owned()), passed)
+            }
+            CallbackEvent::StepSkipped(_, index, reason) => {
+                WorkerEvent::StepSkipped(index, reason.to_owned())
+            }
+            CallbackEvent::StepRetry(_, index, attempt) => WorkerEvent::StepRetry(index, attempt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::{formatdoc, indoc};
+    use mockito::mock;
+
+    #[test]
+    fn test_ordered_results_and_events() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/pool")
+            .with_status(200)
+            .with_body(r#"{"hello": "world"}"#)
+            .create();
+
+        let configs: Vec<WorkflowConfig> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| {
+                let yaml = formatdoc! {"
+                    ---
+                    name: {name}
+                    skip: {skip}
+                    steps:
+                      - name: step 1
+                        url: {url}/pool
+                        assertions:
+                          - !expect status to_equal 200
+                      - name: step 2
+                        url: {url}/pool
+                        assertions:
+                          - !expect body.hello to_equal world
+                    ",
+                    name = name,
+                    url = url,
+                    skip = match *name == "c" {
+                        true => "true",
+                        false => "null",
+                    },
+                };
+                WorkflowConfig::from_yaml(yaml).unwrap()
+            })
+            .collect();
+
+        let run_options = RunOptions::builder().jobs(3).build();
+
+        let mut events = vec![];
+        let results = WorkflowPool::new(&run_options).run(&configs, |event| {
+            let event = match event {
+                CallbackEvent::RunStart(config) => format!("{} start", config.name),
+                CallbackEvent::RunDone(config, _) => format!("{} done", config.name),
+                CallbackEvent::RunSkipped(config) => format!("{} skipped", config.name),
+                CallbackEvent::RunErrored(config, _) => format!("{} errored", config.name),
+                CallbackEvent::StepStart(config, index) => {
+                    format!("{} step {} start", config.name, index)
+                }
+                CallbackEvent::StepDone(config, index, _, _) => {
+                    format!("{} step {} done", config.name, index)
+                }
+                CallbackEvent::StepSkipped(config, index, _) => {
+                    format!("{} step {} skipped", config.name, index)
+                }
+                CallbackEvent::StepRetry(config, index, _) => {
+                    format!("{} step {} retry", config.name, index)
+                }
+            };
+            events.push(event);
+        });
+
+        // the skipped workflow is in the results too, so reports can show it
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c", "d"]);
+        assert!(results.iter().all(|r| r.passed));
+        assert!(results[2].skipped);
+        assert!(results[2].requests.is_empty());
+        let reasons: Vec<&str> = results[2]
+            .skipped_steps
+            .iter()
+            .map(|step| step.reason.as_str())
+            .collect();
+        assert_eq!(reasons, vec!["skipped", "skipped"]);
+
+        let expected: Vec<String> = ["a", "b", "c", "d"]
+            .iter()
+            .flat_map(|name| match *name == "c" {
+                true => vec![format!("{} skipped", name)],
+                false => vec![
+                    format!("{} start", name),
+                    format!("{} step 0 start", name),
+                    format!("{} step 0 done", name),
+                    format!("{} step 1 start", name),
+                    format!("{} step 1 done", name),
+                    format!("{} done", name),
+                ],
+            })
+            .collect();
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn test_errored_workflow() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/pool/errored/ok").with_status(200).create();
+
+        let configs: Vec<WorkflowConfig> = ["${{ nope() }}", "ok"]
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let yaml = formatdoc! {"
+                    ---
+                    name: workflow {index}
+                    steps:
+                      - name: step 1
+                        url: {url}/pool/errored/{path}
+                        assertions:
+                          - !expect status to_equal 200
+                    ",
+                    index = index,
+                    url = url,
+                    path = path,
+                };
+                WorkflowConfig::from_yaml(yaml).unwrap()
+            })
+            .collect();
+
+        let mut errored = vec![];
+        let results = WorkflowPool::new(&RunOptions::default()).run(&configs, |event| {
+            if let CallbackEvent::RunErrored(config, _) = event {
+                errored.push(config.name.to_owned());
+            }
+        });
+
+        // the other workflows still run
+        assert_eq!(errored, vec!["workflow 0"]);
+        assert_eq!(results.len(), 2);
+        assert!(results[0].error.is_some());
+        assert!(!results[0].passed);
+        assert!(results[1].error.is_none());
+        assert!(results[1].passed);
+    }
+
+    #[test]
+    fn test_setup_and_teardown() {
+        let url = &mockito::server_url();
+        let _m1 = mock("POST", "/pool/setup/login")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"token": "secret", "tenant": "t1"}"#)
+            .create();
+        let _m2 = mock("GET", "/pool/setup/tenants/t1/eu")
+            .match_header("authorization", "secret")
+            .with_status(200)
+            .create();
+        let m3 = mock("DELETE", "/pool/setup/tenants/t1")
+            .match_header("authorization", "secret")
+            .with_status(204)
+            .expect(1)
+            .create();
+
+        // the step is indented to go under `- name:`
+        let config = |name: &str, step: &str| {
+            let step: Vec<String> = step
+                .lines()
+                .map(|line| format!("    {}", line.replace("{url}", url)))
+                .collect();
+            let yaml = format!(
+                "name: {}\nsteps:\n  - name: {}\n{}\n",
+                name,
+                name,
+                step.join("\n")
+            );
+            WorkflowConfig::from_yaml(yaml).unwrap()
+        };
+        let setup = config(
+            "setup",
+            indoc! {"
+                url: POST {url}/pool/setup/login
+                set:
+                  region: eu
+                extract:
+                  tenant: body.tenant
+                  token:
+                    from: body.token
+                    mask: true
+                assertions:
+                  - !expect status to_equal 200
+            "},
+        );
+        let configs = vec![config(
+            "test",
+            indoc! {"
+                url: {url}/pool/setup/tenants/${{ vars.tenant }}/${{ vars.region }}
+                headers:
+                  authorization: ${{ vars.token }}
+                assertions:
+                  - !expect status to_equal 201
+            "},
+        )];
+        let teardown = config(
+            "teardown",
+            indoc! {"
+                url: DELETE {url}/pool/setup/tenants/${{ vars.tenant }}
+                headers:
+                  authorization: ${{ vars.token }}
+                assertions:
+                  - !expect status to_equal 204
+            "},
+        );
+
+        let results = WorkflowPool::new(&RunOptions::default()).run_with_setup(
+            Some(&setup),
+            &configs,
+            Some(&teardown),
+            |_| {},
+        );
+
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["setup", "test", "teardown"]);
+        assert!(results[0].passed);
+
+        // the variables from setup are used, and the token stays masked
+        let request = &results[1].requests[0];
+        assert_eq!(request.url, format!("{}/pool/setup/tenants/t1/eu", url));
+        assert_eq!(request.headers.as_ref().unwrap()["authorization"], "****");
+        assert!(!results[1].passed);
+
+        // teardown still runs after the test failed
+        assert!(results[2].passed);
+        m3.assert();
+    }
+
+    #[test]
+    fn test_failed_setup() {
+        let url = &mockito::server_url();
+        let _m1 = mock("POST", "/pool/setup/fail").with_status(500).create();
+        let _m2 = mock("DELETE", "/pool/setup/cleanup")
+            .with_status(204)
+            .create();
+
+        let config = |name: &str, method: &str, path: &str, status: u16| {
+            let yaml = formatdoc! {"
+                ---
+                name: {name}
+                steps:
+                  - name: {name}
+                    url: {method} {url}{path}
+                    assertions:
+                      - !expect status to_equal {status}
+                ",
+                name = name,
+                method = method,
+                url = url,
+                path = path,
+                status = status,
+            };
+            WorkflowConfig::from_yaml(yaml).unwrap()
+        };
+        let setup = config("setup", "POST", "/pool/setup/fail", 200);
+        let configs = vec![config("test", "GET", "/pool/setup/never", 200)];
+        let teardown = config("teardown", "DELETE", "/pool/setup/cleanup", 204);
+
+        let mut skipped = vec![];
+        let results = WorkflowPool::new(&RunOptions::default()).run_with_setup(
+            Some(&setup),
+            &configs,
+            Some(&teardown),
+            |event| {
+                if let CallbackEvent::RunSkipped(config) = event {
+                    skipped.push(config.name.to_owned());
+                }
+            },
+        );
+
+        // the tests are skipped, but teardown runs
+        assert_eq!(skipped, vec!["test"]);
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["setup", "test", "teardown"]);
+        assert!(!results[0].passed);
+        assert!(results[1].skipped);
+        assert!(results[2].passed);
+    }
+}
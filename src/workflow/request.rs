@@ -2,15 +2,18 @@ use std::collections::BTreeMap;
 
 use super::{response::ResponseData, WorkflowConfigAssertion};
 use crate::{
-    assert::AssertionResultData,
+    assert::{assert::AssertionTest, AssertionResultData},
     compile::CompiledString,
     compile::{compile_string, compile_value, CompiledValue},
-    utils::{exit_with_code, HttpRequest},
+    error::CapterError,
+    openapi::OpenApiSpec,
+    utils::{http_request::parse_key_value, HttpRequest},
     workflow::{WorkflowConfig, WorkflowConfigStep},
 };
 use chrono::{DateTime, Utc};
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{json, Value};
+use url::Url;
 
 pub const HTTP_METHODS: &[&str] = &[
     "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
@@ -37,6 +40,7 @@ pub struct Request {
     query: CompiledValue,
     body: CompiledValue,
     response: Option<ResponseData>,
+    attempts: Vec<ResponseData>,
     timeout: u64,
 }
 
@@ -52,6 +56,8 @@ pub struct RequestData {
     pub order: i32,
     pub is_graphql: bool,
     pub response: Option<ResponseData>,
+    /// Responses from earlier attempts, if the step was retried.
+    pub attempts: Vec<ResponseData>,
 }
 
 impl Request {
@@ -59,28 +65,31 @@ impl Request {
     /// and make sure we have all the properties set, like
     /// `url`, `body` etc. You can use `.call()` to make the
     /// request and then assert on it using `.assert_on_response()`.
+    ///
+    /// Returns an error if the step has no url, or if
+    /// any of its templates can't be compiled.
     pub fn new(
         workflow_config: &WorkflowConfig,
         step_index: i32,
         workflow_data: &Value,
         timeout: u64,
-    ) -> Request {
+    ) -> Result<Request, CapterError> {
         let step = workflow_config
             .steps
             .get(step_index as usize)
             .expect("Step index out of bounds");
 
-        let (url, mut method) = get_url(step, workflow_data, workflow_config);
-        let body = get_body(step, workflow_data);
-        let query = get_query(step, workflow_data);
-        let headers = get_headers(step, workflow_data, &workflow_config);
+        let (url, mut method) = get_url(step, workflow_data, workflow_config)?;
+        let body = get_body(step, workflow_data)?;
+        let query = get_query(step, workflow_data)?;
+        let headers = get_headers(step, workflow_data, workflow_config)?;
         // if method is missing in the url string
         // we figure it out here
         if method.is_none() {
             method = Some(get_method(step, workflow_config));
         }
 
-        Request {
+        Ok(Request {
             url,
             query,
             body,
@@ -93,7 +102,8 @@ impl Request {
             workflow_data: workflow_data.to_owned(),
             workflow_config: workflow_config.to_owned(),
             response: None,
-        }
+            attempts: vec![],
+        })
     }
 
     /// Makes the requests and set the response. This needs to
@@ -128,16 +138,94 @@ impl Request {
     pub fn assert_on_response(
         &mut self,
         assertions: &Vec<WorkflowConfigAssertion>,
-    ) -> Vec<AssertionResultData> {
+    ) -> Result<Vec<AssertionResultData>, CapterError> {
         if let Some(mut response) = self.response.to_owned() {
-            let result = response.assert(assertions, &self.workflow_data);
+            let result =
+                response.assert(assertions, &self.workflow_data, &self.workflow_config.dir())?;
             response.assertion_results = result.clone();
             self.response = Some(response);
 
-            return result.to_owned();
+            return Ok(result.to_owned());
+        }
+
+        Ok(vec![])
+    }
+
+    /// Check the response against an OpenAPI spec. The results are
+    /// added after the results from `.assert_on_response()`.
+    pub fn assert_on_spec(&mut self, spec: &OpenApiSpec) -> Vec<AssertionResultData> {
+        if let Some(response) = &mut self.response {
+            let result = spec.check(&self.method, &self.url.raw, response);
+            response.assertion_results.append(&mut result.clone());
+
+            return result;
+        }
+
+        vec![]
+    }
+
+    /// Add a failed result for `until`, when it still didn't pass
+    /// after the last attempt. It's added after the other results.
+    pub fn fail_until(
+        &mut self,
+        until: &[WorkflowConfigAssertion],
+        attempts: u32,
+    ) -> AssertionResultData {
+        let conditions: Vec<String> = until
+            .iter()
+            .map(|assertion| match assertion {
+                WorkflowConfigAssertion::expect(value) => value.to_owned(),
+                WorkflowConfigAssertion::expect_not(value) => format!("not {}", value),
+            })
+            .collect();
+
+        let result = AssertionResultData {
+            passed: false,
+            message: Some(format!(
+                "`until` didn't pass after {} attempt{}: {}",
+                attempts,
+                match attempts {
+                    1 => "",
+                    _ => "s",
+                },
+                conditions.join(", ")
+            )),
+            assertion: AssertionTest {
+                test: "until".to_string(),
+                property: "until".to_string(),
+                value: json!(conditions),
+                not: false,
+            },
+        };
+
+        if let Some(response) = &mut self.response {
+            response.assertion_results.push(result.clone());
+        }
+
+        result
+    }
+
+    /// Check if the response passes `assertions`, without
+    /// saving the results on the response.
+    pub fn check_response(
+        &self,
+        assertions: &Vec<WorkflowConfigAssertion>,
+    ) -> Result<bool, CapterError> {
+        match self.response.to_owned() {
+            Some(mut response) => Ok(response
+                .assert(assertions, &self.workflow_data, &self.workflow_config.dir())?
+                .iter()
+                .all(|result| result.passed)),
+            None => Ok(false),
         }
+    }
 
-        return vec![];
+    /// Prepare the request to be called again. The current
+    /// response is saved as an earlier attempt.
+    pub fn retry(&mut self) {
+        if let Some(response) = self.response.take() {
+            self.attempts.push(response);
+        }
     }
 
     /// Return the copy data for this request.
@@ -153,11 +241,13 @@ impl Request {
             order: self.step_index,
             is_graphql: self.step.graphql.is_some(),
             response: self.response.to_owned(),
+            attempts: self.attempts.to_owned(),
         }
     }
 
     /// Return the copy data for this request,
-    /// where sensitive data is masked. This will
+    /// where sensitive data is masked. Templates with `mask` are
+    /// masked in the url, query, headers and body, and this will
     /// return a masked version of response too.
     pub fn data_masked(&self) -> RequestData {
         let data = self.data();
@@ -165,17 +255,63 @@ impl Request {
             Some(response) => response.into_masked(&self.step.options),
             _ => None,
         };
+        let attempts = data
+            .attempts
+            .iter()
+            .filter_map(|attempt| attempt.into_masked(&self.step.options))
+            .collect();
 
         RequestData {
             url: self.url.masked.to_owned(),
             query: Some(self.query.masked.to_owned()),
             headers: Some(self.headers.masked.to_owned()),
+            body: Some(self.body.masked.to_owned()),
             response,
+            attempts,
             ..data
         }
     }
 }
 
+impl RequestData {
+    /// The url with the query added, the same way we add it when
+    /// making the request.
+    pub fn full_url(&self) -> String {
+        let query = self.query_pairs();
+        if query.is_empty() {
+            return self.url.to_owned();
+        }
+
+        match Url::parse(&self.url) {
+            Ok(mut url) => {
+                url.query_pairs_mut().extend_pairs(query);
+                url.to_string()
+            }
+            Err(_) => self.url.to_owned(),
+        }
+    }
+
+    /// The query as a list of names and values.
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        to_pairs(&self.query)
+    }
+
+    /// The headers as a list of names and values.
+    pub fn header_pairs(&self) -> Vec<(String, String)> {
+        to_pairs(&self.headers)
+    }
+}
+
+fn to_pairs(value: &Option<serde_yaml::Value>) -> Vec<(String, String)> {
+    match value.as_ref().and_then(|value| value.as_mapping()) {
+        Some(mapping) => mapping
+            .iter()
+            .map(|(key, value)| parse_key_value(key, value))
+            .collect(),
+        None => vec![],
+    }
+}
+
 /// Get the method for a request.
 ///
 /// It will use the step method if set, and fallback to the workflow if not.
@@ -206,7 +342,7 @@ fn get_headers(
     step: &WorkflowConfigStep,
     workflow_data: &Value,
     workflow_config: &WorkflowConfig,
-) -> CompiledValue {
+) -> Result<CompiledValue, CapterError> {
     let step_headers = step.headers.clone();
     let workflow_headers = workflow_config.headers.clone();
     let mut headers: BTreeMap<String, serde_yaml::Value> = BTreeMap::new();
@@ -228,20 +364,26 @@ fn get_headers(
 
     let headers = serde_yaml::to_value(&headers).unwrap();
 
-    compile_value(Some(headers), &workflow_data)
+    compile_value(Some(headers), workflow_data)
 }
 
 /// Get the query for a request.
-fn get_query(step: &WorkflowConfigStep, workflow_data: &Value) -> CompiledValue {
+fn get_query(
+    step: &WorkflowConfigStep,
+    workflow_data: &Value,
+) -> Result<CompiledValue, CapterError> {
     let query = Some(serde_yaml::to_value(&step.query).unwrap());
-    compile_value(query, &workflow_data)
+    compile_value(query, workflow_data)
 }
 
 /// Get the body for a request.
 ///
 /// If the step has the `graphql` property set, this will
 /// return a body that works with a graphql request.
-fn get_body(step: &WorkflowConfigStep, workflow_data: &Value) -> CompiledValue {
+fn get_body(
+    step: &WorkflowConfigStep,
+    workflow_data: &Value,
+) -> Result<CompiledValue, CapterError> {
     if let Some(graphql) = &step.graphql {
         let mut graphql_yaml = serde_yaml::Mapping::new();
         graphql_yaml.insert(
@@ -253,10 +395,10 @@ fn get_body(step: &WorkflowConfigStep, workflow_data: &Value) -> CompiledValue {
             graphql_yaml.insert("variables".to_string().into(), variables.clone());
         }
 
-        return compile_value(Some(graphql_yaml.into()), &workflow_data);
+        return compile_value(Some(graphql_yaml.into()), workflow_data);
     }
 
-    compile_value(step.body.clone(), &workflow_data)
+    compile_value(step.body.clone(), workflow_data)
 }
 
 /// Get the url for a request.
@@ -267,20 +409,13 @@ fn get_body(step: &WorkflowConfigStep, workflow_data: &Value) -> CompiledValue {
 /// The second part of the return is an optional method that can be passed like
 /// `POST https://api.com`
 ///
-/// This exit if no url is found, because it's required.
+/// Returns an error if no url is found, because it's required.
 fn get_url(
     step: &WorkflowConfigStep,
     workflow_data: &Value,
     workflow_config: &WorkflowConfig,
-) -> (CompiledString, Option<String>) {
-    let mut url = step.url.to_owned();
-    if let Some(step_url) = &url {
-        url = Some(step_url.to_owned());
-    };
-
-    if let Some(workflow_url) = &workflow_config.url {
-        url = Some(workflow_url.to_owned());
-    };
+) -> Result<(CompiledString, Option<String>), CapterError> {
+    let url = step.url.to_owned().or(workflow_config.url.to_owned());
 
     if let Some(url) = url {
         // remove method if it's set in the URL
@@ -290,7 +425,7 @@ fn get_url(
 
         // if there's just one part, return it
         if parts.len() == 1 {
-            return (compile_string(&url, &workflow_data), None);
+            return Ok((compile_url(&url, workflow_data, workflow_config)?, None));
         }
 
         // if there's 2 parts more we might have
@@ -301,18 +436,42 @@ fn get_url(
                 let method = parts[0].to_owned();
                 parts.remove(0);
                 let url = parts.join(" ");
-                return (compile_string(&url, &workflow_data), Some(method));
+                return Ok((
+                    compile_url(&url, workflow_data, workflow_config)?,
+                    Some(method),
+                ));
             }
 
             // if first part is not a method, just return the whole url
-            return (compile_string(&url, &workflow_data), None);
+            return Ok((compile_url(&url, workflow_data, workflow_config)?, None));
         }
     }
 
-    exit_with_code(
-        exitcode::CONFIG,
-        Some(&format!("No url found for step: `{}`", step.name)),
-    );
+    Err(CapterError::Request(format!(
+        "No url found for step: `{}`",
+        step.name
+    )))
+}
+
+/// Compile the url, and add it to the `base_url` of
+/// the workflow if it's relative, like `/users`.
+fn compile_url(
+    url: &str,
+    workflow_data: &Value,
+    workflow_config: &WorkflowConfig,
+) -> Result<CompiledString, CapterError> {
+    let url = compile_string(url, workflow_data)?;
+
+    match &workflow_config.base_url {
+        Some(base_url) if url.raw.starts_with('/') => {
+            let base_url = compile_string(base_url, workflow_data)?;
+            Ok(CompiledString {
+                raw: format!("{}{}", base_url.raw.trim_end_matches('/'), url.raw),
+                masked: format!("{}{}", base_url.masked.trim_end_matches('/'), url.masked),
+            })
+        }
+        _ => Ok(url),
+    }
 }
 
 #[cfg(test)]
@@ -343,15 +502,71 @@ mod tests {
         let step1 = workflow_config.steps[0].clone();
         let step2 = workflow_config.steps[1].clone();
 
-        let (url, method) = get_url(&step1, &workflow_data, &workflow_config);
+        let (url, method) = get_url(&step1, &workflow_data, &workflow_config).unwrap();
         assert_eq!(url.raw, "https://fake-api.cater.io/users");
         assert_eq!(method, Some("POST".to_string()));
 
-        let (url, method) = get_url(&step2, &workflow_data, &workflow_config);
+        let (url, method) = get_url(&step2, &workflow_data, &workflow_config).unwrap();
         assert_eq!(url.raw, "https://fake-api.cater.io/users");
         assert_eq!(method, None);
     }
 
+    #[test]
+    fn test_step_url_wins() {
+        let yaml = indoc! {"
+            ---
+            name: test
+            url: http://localhost/health
+            steps:
+              - name: step 1
+                url: POST http://localhost/users
+                assertions: []
+              - name: step 2
+                assertions: []
+        "};
+        let workflow_config = WorkflowConfig::from_yaml(yaml.into()).unwrap();
+
+        let step1 = workflow_config.steps[0].clone();
+        let step2 = workflow_config.steps[1].clone();
+
+        let (url, method) = get_url(&step1, &json!({}), &workflow_config).unwrap();
+        assert_eq!(url.raw, "http://localhost/users");
+        assert_eq!(method, Some("POST".to_string()));
+
+        let (url, _) = get_url(&step2, &json!({}), &workflow_config).unwrap();
+        assert_eq!(url.raw, "http://localhost/health");
+    }
+
+    #[test]
+    fn test_base_url() {
+        let yaml = indoc! {"
+            ---
+            name: test
+            base_url: ${{ mask env.URL }}/
+            steps:
+              - name: step 1
+                url: POST /users
+                assertions:
+                  - !expect status to_equal 200
+              - name: step 2
+                url: http://localhost/users
+                assertions:
+                  - !expect status to_equal 200
+        "};
+        let workflow_config = WorkflowConfig::from_yaml(yaml.into()).unwrap();
+        let workflow_data = json!({ "env": { "URL": "https://fake-api.cater.io" }});
+
+        let step1 = workflow_config.steps[0].clone();
+        let step2 = workflow_config.steps[1].clone();
+
+        let (url, _) = get_url(&step1, &workflow_data, &workflow_config).unwrap();
+        assert_eq!(url.raw, "https://fake-api.cater.io/users");
+        assert_eq!(url.masked, "****/users");
+
+        let (url, _) = get_url(&step2, &workflow_data, &workflow_config).unwrap();
+        assert_eq!(url.raw, "http://localhost/users");
+    }
+
     #[test]
     fn test_merging_headers() {
         let yaml = indoc! {"
@@ -371,8 +586,51 @@ mod tests {
 
         let step1 = workflow_config.steps[0].clone();
 
-        let headers = get_headers(&step1, &json!({}), &workflow_config);
+        let headers = get_headers(&step1, &json!({}), &workflow_config).unwrap();
         assert_eq!(headers.raw["a"], "b");
         assert_eq!(headers.raw["b"], "c");
     }
+
+    #[test]
+    fn test_data_masked() {
+        let yaml = indoc! {"
+            ---
+            name: test
+            steps:
+              - name: step 1
+                url: POST http://localhost/login
+                body:
+                  user: test
+                  password: ${{ mask env.PASSWORD }}
+                assertions: []
+        "};
+        let workflow_config = WorkflowConfig::from_yaml(yaml.into()).unwrap();
+        let workflow_data = json!({ "env": { "PASSWORD": "secret" }});
+
+        let request = Request::new(&workflow_config, 0, &workflow_data, 30).unwrap();
+        let body = json!(request.data().body);
+        assert_eq!(body, json!({ "user": "test", "password": "secret" }));
+
+        // the body sent to the webhook and in exports is masked
+        let body = json!(request.data_masked().body);
+        assert_eq!(body, json!({ "user": "test", "password": "****" }));
+    }
+
+    #[test]
+    fn test_no_url() {
+        let yaml = indoc! {"
+            ---
+            name: test
+            steps:
+              - name: step 1
+                assertions: []
+        "};
+        let workflow_config = WorkflowConfig::from_yaml(yaml.into()).unwrap();
+
+        let error = Request::new(&workflow_config, 0, &json!({}), 30).unwrap_err();
+        assert_eq!(
+            error,
+            CapterError::Request("No url found for step: `step 1`".to_string())
+        );
+    }
 }
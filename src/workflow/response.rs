@@ -1,5 +1,6 @@
 use crate::{
     assert::{assert, AssertionData, AssertionResultData},
+    error::CapterError,
     utils::deep_replace,
     workflow::{WorkflowConfigAssertion, WorkflowConfigStepOptions},
 };
@@ -7,6 +8,8 @@ use assert::Assertion;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{io::Read, path::Path};
 use ureq::ErrorKind;
 
 /// The result from a request. You can run assertions on it
@@ -18,6 +21,16 @@ pub struct ResponseData {
     pub status_text: Option<String>,
     pub headers: serde_json::Value,
     pub body: Option<serde_json::Value>,
+    /// The raw body, if the response is text, for assertions on `text`.
+    /// It's left out when serialized so it isn't sent twice, but a text
+    /// response is in `body` as a string too, where `mask` can't hide it,
+    /// since it only masks values by their key.
+    #[serde(skip_serializing)]
+    pub text: Option<String>,
+    /// Size of the body in bytes.
+    pub size: Option<usize>,
+    /// A sha256 hash of the body, if the response is binary.
+    pub hash: Option<String>,
     pub response_time: i64,
     pub assertion_results: Vec<AssertionResultData>,
 }
@@ -31,6 +44,9 @@ impl Default for ResponseData {
             response_time: 0,
             status: None,
             body: None,
+            text: None,
+            size: None,
+            hash: None,
             assertion_results: vec![],
         }
     }
@@ -46,46 +62,19 @@ impl ResponseData {
     /// Sometimes an error is what we're expecting so it's
     /// important that we handle that correctly.
     pub fn from_result(
-        result: Result<ureq::Response, ureq::Error>,
+        result: Result<ureq::Response, Box<ureq::Error>>,
         response_time: i64,
     ) -> ResponseData {
-        match result {
-            Ok(response) => {
-                let status = response.status();
-                let headers_names = response.headers_names();
-
-                // create a Value from headers
-                let mut headers = serde_json::Map::new();
-                for name in headers_names {
-                    let value = response.header(&name);
-                    headers.insert(name, json!(value));
-                }
-
-                let body: Value = response.into_json().unwrap_or(Value::Null);
-
-                ResponseData {
-                    response_time,
-                    status: Some(status),
-                    status_text: None,
-                    body: Some(body),
-                    headers: headers.into(),
-                    ..Default::default()
-                }
-            }
-            Err(ureq::Error::Status(code, response)) => {
-                let body: Value = response.into_json().unwrap_or(Value::Null);
-
-                ResponseData {
-                    response_time,
-                    status: Some(code),
-                    body: Some(body),
-                    ..Default::default()
-                }
+        match result.map_err(|error| *error) {
+            // a status error still has a response, so we
+            // handle it the same way as a successful one
+            Ok(response) | Err(ureq::Error::Status(_, response)) => {
+                ResponseData::from_response(response, response_time)
             }
             Err(error) => {
                 // ending up here means there were NO response
                 let status_text = match &error.kind() {
-                    ErrorKind::Dns => format!("Could not connect to URL"),
+                    ErrorKind::Dns => "Could not connect to URL".to_string(),
                     _ => error.kind().to_string(),
                 };
 
@@ -98,13 +87,42 @@ impl ResponseData {
         }
     }
 
+    /// Create a response from any `ureq` response we got back,
+    /// no matter what the status code is.
+    fn from_response(response: ureq::Response, response_time: i64) -> ResponseData {
+        let status = response.status();
+        let status_text = response.status_text().to_string();
+        let headers_names = response.headers_names();
+
+        // create a Value from headers
+        let mut headers = serde_json::Map::new();
+        for name in headers_names {
+            let value = response.header(&name);
+            headers.insert(name, json!(value));
+        }
+
+        let body = ResponseBody::from_response(response);
+
+        ResponseData {
+            response_time,
+            status: Some(status),
+            status_text: Some(status_text),
+            headers: headers.into(),
+            ..body.into()
+        }
+    }
+
     /// Run assertions on the response.
     /// Populates `.assertion_results`.
+    ///
+    /// Returns an error if any of the assertions can't be parsed.
+    /// Schema files are relative to `dir`, see `Assertion::relative_to`.
     pub fn assert(
         &mut self,
         assertions: &Vec<WorkflowConfigAssertion>,
         workflow_data: &Value,
-    ) -> Vec<AssertionResultData> {
+        dir: &Path,
+    ) -> Result<Vec<AssertionResultData>, CapterError> {
         let mut assertions_results: Vec<AssertionResultData> = vec![];
 
         // loop through the assertions and run them
@@ -113,17 +131,21 @@ impl ResponseData {
                 status: self.status,
                 duration: self.response_time,
                 body: self.body.to_owned().unwrap_or(Value::Null),
+                text: self.text.to_owned(),
+                size: self.size,
+                hash: self.hash.to_owned(),
                 headers: self.headers.to_owned(),
             };
 
-            let assertion = Assertion::from_assertion(assertion_string, workflow_data);
+            let assertion =
+                Assertion::from_assertion(assertion_string, workflow_data)?.relative_to(dir);
             let result = assertion.assert(&assertion_data);
 
             assertions_results.push(result);
         }
 
         self.assertion_results = assertions_results;
-        self.assertion_results.to_owned()
+        Ok(self.assertion_results.to_owned())
     }
 
     /// Return a masked version of the response.
@@ -144,7 +166,7 @@ impl ResponseData {
     pub fn mask(&self, options: &WorkflowConfigStepOptions) -> ResponseData {
         let mut response_result = self.clone();
         if let Some(mask) = &options.mask {
-            if mask.len() == 0 {
+            if mask.is_empty() {
                 return response_result;
             }
 
@@ -161,6 +183,112 @@ impl ResponseData {
     }
 }
 
+/// The decoded body of a response.
+///
+/// How we decode it depends on the `Content-Type`. JSON is parsed,
+/// text (including HTML, XML and CSV) is kept as a string, and for
+/// anything else we only keep the size and a hash of the bytes.
+struct ResponseBody {
+    body: Value,
+    text: Option<String>,
+    size: usize,
+    hash: Option<String>,
+}
+
+impl ResponseBody {
+    fn from_response(response: ureq::Response) -> ResponseBody {
+        let content_type = response
+            .header("content-type")
+            .map(|content_type| content_type.to_lowercase());
+
+        let mut bytes = vec![];
+        if response.into_reader().read_to_end(&mut bytes).is_err() {
+            return ResponseBody::from_bytes(&[], content_type.as_deref());
+        }
+
+        ResponseBody::from_bytes(&bytes, content_type.as_deref())
+    }
+
+    fn from_bytes(bytes: &[u8], content_type: Option<&str>) -> ResponseBody {
+        let size = bytes.len();
+
+        if size == 0 {
+            return ResponseBody {
+                body: Value::Null,
+                text: None,
+                size,
+                hash: None,
+            };
+        }
+
+        let text = std::str::from_utf8(bytes).ok();
+
+        // without a content type we try JSON first, then text
+        let is_json = content_type.is_none_or(|content_type| content_type.contains("json"));
+        let is_text = content_type.map_or(text.is_some(), is_text_content_type);
+
+        if let (true, Some(text)) = (is_json, text) {
+            if let Ok(body) = serde_json::from_str(text) {
+                return ResponseBody {
+                    body,
+                    text: Some(text.to_string()),
+                    size,
+                    hash: None,
+                };
+            }
+        }
+
+        if is_json || is_text {
+            let text = String::from_utf8_lossy(bytes).to_string();
+            return ResponseBody {
+                body: Value::String(text.to_owned()),
+                text: Some(text),
+                size,
+                hash: None,
+            };
+        }
+
+        let hash = Sha256::digest(bytes)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        ResponseBody {
+            body: Value::Null,
+            text: None,
+            size,
+            hash: Some(hash),
+        }
+    }
+}
+
+impl From<ResponseBody> for ResponseData {
+    fn from(body: ResponseBody) -> Self {
+        ResponseData {
+            body: Some(body.body),
+            text: body.text,
+            size: Some(body.size),
+            hash: body.hash,
+            ..Default::default()
+        }
+    }
+}
+
+/// Check if a content type is something we can show as text.
+fn is_text_content_type(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || [
+            "xml",
+            "html",
+            "javascript",
+            "csv",
+            "yaml",
+            "x-www-form-urlencoded",
+        ]
+        .iter()
+        .any(|text_type| content_type.contains(text_type))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,7 +303,9 @@ mod tests {
             .with_header("test-header", "test-value")
             .create();
 
-        let result = ureq::request("GET", &format!("{}/test", url)).call();
+        let result = ureq::request("GET", &format!("{}/test", url))
+            .call()
+            .map_err(Box::new);
         let response = ResponseData::from_result(result, 0);
 
         assert_eq!(response.status, Some(200));
@@ -190,13 +320,46 @@ mod tests {
             .with_body(r#"{"error": "error 500"}"#)
             .create();
 
-        let result = ureq::request("GET", &format!("{}/500", url)).call();
+        let result = ureq::request("GET", &format!("{}/500", url))
+            .call()
+            .map_err(Box::new);
         let response = ResponseData::from_result(result, 0);
 
         assert_eq!(response.status, Some(500));
         assert_eq!(response.body, Some(json!({ "error": "error 500" })));
     }
 
+    #[test]
+    fn test_error_response_headers() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/429")
+            .with_status(429)
+            .with_header("retry-after", "30")
+            .with_header("x-request-id", "abc")
+            .with_body(r#"{"error": "too many requests"}"#)
+            .create();
+
+        let result = ureq::request("GET", &format!("{}/429", url))
+            .call()
+            .map_err(Box::new);
+        let mut response = ResponseData::from_result(result, 0);
+
+        assert_eq!(response.status, Some(429));
+        assert_eq!(response.status_text, Some("Too Many Requests".to_string()));
+        assert_eq!(response.headers["retry-after"], "30");
+        assert_eq!(response.body, Some(json!({ "error": "too many requests" })));
+
+        let assertions = vec![
+            WorkflowConfigAssertion::expect("status to_equal 429".to_string()),
+            WorkflowConfigAssertion::expect("headers.retry-after to_equal 30".to_string()),
+            WorkflowConfigAssertion::expect("headers.x-request-id to_exist".to_string()),
+        ];
+        let assertion_results = response
+            .assert(&assertions, &json!({}), Path::new(""))
+            .unwrap();
+        assert!(assertion_results.iter().all(|result| result.passed));
+    }
+
     #[test]
     fn test_custom_status_code() {
         let url = &mockito::server_url();
@@ -205,7 +368,9 @@ mod tests {
             .with_body(r#"{"error": "error 453"}"#)
             .create();
 
-        let result = ureq::request("GET", &format!("{}/453", url)).call();
+        let result = ureq::request("GET", &format!("{}/453", url))
+            .call()
+            .map_err(Box::new);
         let response = ResponseData::from_result(result, 0);
 
         assert_eq!(response.status, Some(453));
@@ -214,7 +379,9 @@ mod tests {
 
     #[test]
     fn test_no_response() {
-        let result = ureq::request("GET", "http://bad-url").call();
+        let result = ureq::request("GET", "http://bad-url")
+            .call()
+            .map_err(Box::new);
         let response = ResponseData::from_result(result, 0);
 
         assert_eq!(response.status, None);
@@ -255,7 +422,9 @@ mod tests {
             .with_header("test-header", "test-value")
             .create();
 
-        let result = ureq::request("GET", &format!("{}/test", url)).call();
+        let result = ureq::request("GET", &format!("{}/test", url))
+            .call()
+            .map_err(Box::new);
         let mut response = ResponseData::from_result(result, 1000);
 
         let assertions = vec![
@@ -265,15 +434,83 @@ mod tests {
             WorkflowConfigAssertion::expect("duration to_equal 500".to_string()),
         ];
 
-        let assertion_results = response.assert(&assertions, &json!({}));
+        let assertion_results = response
+            .assert(&assertions, &json!({}), Path::new(""))
+            .unwrap();
         assert_eq!(assertion_results.len(), 4);
-        assert_eq!(assertion_results[0].passed, true);
-        assert_eq!(assertion_results[1].passed, true);
-        assert_eq!(assertion_results[2].passed, true);
-        assert_eq!(assertion_results[3].passed, false);
+        assert!(assertion_results[0].passed);
+        assert!(assertion_results[1].passed);
+        assert!(assertion_results[2].passed);
+        assert!(!assertion_results[3].passed);
         assert_eq!(
             assertion_results[3].message,
             Some("expected 1000 to equal 500".to_string())
         );
     }
+
+    #[test]
+    fn test_text_response() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/text")
+            .with_status(200)
+            .with_header("content-type", "text/plain; charset=utf-8")
+            .with_body("hello world")
+            .create();
+        let _m2 = mock("GET", "/html")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body("<h1>Hello</h1>")
+            .create();
+
+        let result = ureq::request("GET", &format!("{}/text", url))
+            .call()
+            .map_err(Box::new);
+        let response = ResponseData::from_result(result, 0);
+        assert_eq!(response.body, Some(json!("hello world")));
+        assert_eq!(response.text, Some("hello world".to_string()));
+        assert_eq!(response.size, Some(11));
+        assert_eq!(response.hash, None);
+
+        let result = ureq::request("GET", &format!("{}/html", url))
+            .call()
+            .map_err(Box::new);
+        let mut response = ResponseData::from_result(result, 0);
+        assert_eq!(response.body, Some(json!("<h1>Hello</h1>")));
+
+        let assertions = vec![
+            WorkflowConfigAssertion::expect("body to_contain Hello".to_string()),
+            WorkflowConfigAssertion::expect("text to_match <h1>.*</h1>".to_string()),
+            WorkflowConfigAssertion::expect("size to_equal 14".to_string()),
+        ];
+        let assertion_results = response
+            .assert(&assertions, &json!({}), Path::new(""))
+            .unwrap();
+        assert!(assertion_results.iter().all(|result| result.passed));
+    }
+
+    #[test]
+    fn test_json_body_that_is_not_json() {
+        let body = ResponseBody::from_bytes(b"not json", Some("application/json"));
+        assert_eq!(body.body, json!("not json"));
+
+        let body = ResponseBody::from_bytes(b"[1, 2]", Some("application/problem+json"));
+        assert_eq!(body.body, json!([1, 2]));
+
+        let body = ResponseBody::from_bytes(b"", Some("application/json"));
+        assert_eq!(body.body, Value::Null);
+        assert_eq!(body.size, 0);
+    }
+
+    #[test]
+    fn test_binary_response() {
+        let body = ResponseBody::from_bytes(&[0, 159, 146, 150], Some("image/png"));
+
+        assert_eq!(body.body, Value::Null);
+        assert_eq!(body.text, None);
+        assert_eq!(body.size, 4);
+        assert_eq!(
+            body.hash,
+            Some("b02a591131217cb579165aeccf0d94569acffb9934c84d6c813d77e3abedd233".to_string())
+        );
+    }
 }
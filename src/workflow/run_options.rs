@@ -0,0 +1,153 @@
+use crate::{
+    compile::expression::MASKED_KEY, openapi::OpenApiSpec,
+    workflow::workflow_result::WorkflowResult,
+};
+use serde_json::{json, Map, Value};
+
+/// How workflows are run, shared by every workflow in a run.
+///
+/// Made with [`RunOptions::builder`], or [`RunOptions::default`]
+/// to run like `capter test` without any flags.
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    pub(crate) is_debug: bool,
+    pub(crate) timeout: u64,
+    pub(crate) jobs: usize,
+    pub(crate) reporter: Option<String>,
+    pub(crate) output: Option<String>,
+    pub(crate) openapi: Option<OpenApiSpec>,
+    pub(crate) seed: Option<u64>,
+    /// Variables shared with every workflow, like from the setup
+    /// workflow. Made by `shared_vars`, with the masked ones in it.
+    pub(crate) vars: Option<Value>,
+    pub(crate) environment: Option<String>,
+}
+
+impl Default for RunOptions {
+    fn default() -> RunOptions {
+        RunOptions {
+            is_debug: false,
+            timeout: 30,
+            jobs: 1,
+            reporter: None,
+            output: None,
+            openapi: None,
+            seed: None,
+            vars: None,
+            environment: None,
+        }
+    }
+}
+
+impl RunOptions {
+    pub fn builder() -> RunOptionsBuilder {
+        RunOptionsBuilder::default()
+    }
+
+    /// A builder that starts from these options, like to add
+    /// the variables from setup.
+    pub fn to_builder(&self) -> RunOptionsBuilder {
+        RunOptionsBuilder {
+            options: self.clone(),
+        }
+    }
+}
+
+/// Builds [`RunOptions`], anything that's not set is the same
+/// as the default for `capter test`.
+///
+/// ```
+/// use capter::RunOptions;
+///
+/// let options = RunOptions::builder().timeout(5).seed(42).build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RunOptionsBuilder {
+    options: RunOptions,
+}
+
+impl RunOptionsBuilder {
+    /// Seconds to wait for a response, defaults to 30.
+    pub fn timeout(mut self, timeout: u64) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    /// How many workflows run at the same time, defaults to 1.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.options.jobs = jobs;
+        self
+    }
+
+    /// Seed for generated values, a new one is picked if it's not set.
+    /// `now()` and `timestamp()` aren't seeded, see `functions::set_seed`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.options.seed = Some(seed);
+        self
+    }
+
+    /// Check every response against this OpenAPI spec, unless
+    /// the workflow has its own. It's loaded once for the whole run.
+    pub fn openapi(mut self, spec: OpenApiSpec) -> Self {
+        self.options.openapi = Some(spec);
+        self
+    }
+
+    /// Variables every workflow starts with, so `base_url` here
+    /// is `${{ vars.base_url }}` in a workflow.
+    pub fn vars(mut self, vars: Map<String, Value>) -> Self {
+        let shared = self.options.vars.get_or_insert_with(|| json!({}));
+        for (name, value) in vars {
+            shared["vars"][name] = value;
+        }
+        self
+    }
+
+    /// Share the variables from a setup workflow with every workflow,
+    /// the masked ones stay masked.
+    pub fn setup_vars(mut self, setup: &WorkflowResult) -> Self {
+        let shared = self.options.vars.get_or_insert_with(|| json!({}));
+        if let Some(vars) = setup.vars["vars"].as_object() {
+            for (name, value) in vars {
+                shared["vars"][name] = value.to_owned();
+            }
+        }
+        if let Some(masked) = setup.vars[MASKED_KEY].as_array() {
+            if !shared[MASKED_KEY].is_array() {
+                shared[MASKED_KEY] = json!([]);
+            }
+            if let Some(shared_masked) = shared[MASKED_KEY].as_array_mut() {
+                shared_masked.extend(masked.iter().cloned());
+            }
+        }
+        self
+    }
+
+    /// The environment the run uses, like `staging`.
+    pub fn environment(mut self, environment: &str) -> Self {
+        self.options.environment = Some(environment.to_string());
+        self
+    }
+
+    /// Print what's happening instead of updating the terminal.
+    pub fn debug(mut self, is_debug: bool) -> Self {
+        self.options.is_debug = is_debug;
+        self
+    }
+
+    /// Write a report in this format, like `junit` or `json`.
+    pub fn reporter(mut self, reporter: &str) -> Self {
+        self.options.reporter = Some(reporter.to_string());
+        self
+    }
+
+    /// Where to write the report, defaults to stdout.
+    pub fn output(mut self, output: &str) -> Self {
+        self.options.output = Some(output.to_string());
+        self
+    }
+
+    pub fn build(self) -> RunOptions {
+        self.options
+    }
+}
@@ -1,19 +1,15 @@
-use crate::CliOptions;
+use crate::workflow::RunOptions;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize)]
 #[allow(non_camel_case_types)]
+#[derive(Default)]
 pub enum Source {
+    #[default]
     cli,
     ci,
 }
 
-impl Default for Source {
-    fn default() -> Self {
-        Source::cli
-    }
-}
-
 /// Ref information.
 #[derive(Deserialize, Serialize, Debug)]
 pub struct RunSourceRef {
@@ -54,13 +50,16 @@ pub struct RunSource {
     pub commit: Option<RunSourceCommit>,
     pub pr: Option<RunSourcePullRequest>,
     pub meta: Option<serde_json::Value>,
+    /// The environment the run used, like `staging`.
+    pub environment: Option<String>,
 }
 
 impl RunSource {
-    pub fn new(_cli_option: &CliOptions) -> RunSource {
+    pub fn new(run_options: &RunOptions) -> RunSource {
         let ci_info = ci_info::get();
 
         let mut run_source = RunSource {
+            environment: run_options.environment.to_owned(),
             ..Default::default()
         };
 
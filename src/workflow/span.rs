@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use yaml_rust::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+/// A place in a workflow file.
+///
+/// `line` and `column` start at 1, and `index` is the
+/// number of characters before it in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub index: usize,
+}
+
+impl Span {
+    /// Find `needle` in `source`, starting at this span. Returns
+    /// this span if it's not there, like in a file that's changed.
+    pub fn find(&self, source: &str, needle: &str) -> Span {
+        let start = match source.char_indices().nth(self.index) {
+            Some((start, _)) => start,
+            None => return *self,
+        };
+
+        match source[start..].find(needle) {
+            Some(offset) => Span::at(source, start + offset),
+            None => *self,
+        }
+    }
+
+    /// The span at byte `offset` in `source`.
+    fn at(source: &str, offset: usize) -> Span {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+
+        Span {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            index: before.chars().count(),
+        }
+    }
+}
+
+/// Where every value in a YAML file is, by its path, like
+/// `steps.0.url` or `steps.1.assertions.0`. The whole file is `""`.
+///
+/// Returns what was found until the YAML couldn't be parsed.
+pub fn find_spans(yaml: &str) -> BTreeMap<String, Span> {
+    let mut receiver = SpanReceiver::default();
+    let _ = Parser::new(yaml.chars()).load(&mut receiver, false);
+    receiver.spans
+}
+
+enum Frame {
+    /// The key is set while we wait for its value.
+    Mapping {
+        path: String,
+        key: Option<String>,
+        has_keys: bool,
+    },
+    Sequence {
+        path: String,
+        index: usize,
+    },
+}
+
+#[derive(Default)]
+struct SpanReceiver {
+    spans: BTreeMap<String, Span>,
+    stack: Vec<Frame>,
+}
+
+impl SpanReceiver {
+    /// The path of the next value, or `None` if it's a key.
+    fn next_path(&mut self, event: &Event) -> Option<String> {
+        let (parent, segment) = match self.stack.last_mut() {
+            None => return Some(String::new()),
+            Some(Frame::Sequence { path, index }) => {
+                *index += 1;
+                (path, (*index - 1).to_string())
+            }
+            Some(Frame::Mapping { path, key, .. }) => match key.take() {
+                Some(key) => (path, key),
+                None => {
+                    *key = Some(match event {
+                        Event::Scalar(value, ..) => value.to_owned(),
+                        _ => "?".to_string(),
+                    });
+                    return None;
+                }
+            },
+        };
+
+        Some(match parent.is_empty() {
+            true => segment,
+            false => format!("{}.{}", parent, segment),
+        })
+    }
+}
+
+impl MarkedEventReceiver for SpanReceiver {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        let is_node = matches!(
+            event,
+            Event::Scalar(..) | Event::Alias(_) | Event::MappingStart(_) | Event::SequenceStart(_)
+        );
+
+        if is_node {
+            let path = self.next_path(&event);
+
+            let span = Span {
+                line: marker.line(),
+                column: marker.col() + 1,
+                index: marker.index(),
+            };
+
+            match (&path, self.stack.last_mut()) {
+                (Some(path), _) => {
+                    self.spans.insert(path.to_owned(), span);
+                }
+                // block mappings start at the first `:`, so
+                // they're moved to the first key instead
+                (None, Some(Frame::Mapping { path, has_keys, .. })) if !*has_keys => {
+                    *has_keys = true;
+                    self.spans.insert(path.to_owned(), span);
+                }
+                _ => (),
+            }
+
+            // a mapping can be a key too, but we don't look inside it
+            let path = path.unwrap_or_else(|| "?".to_string());
+            match event {
+                Event::MappingStart(_) => self.stack.push(Frame::Mapping {
+                    path,
+                    key: None,
+                    has_keys: false,
+                }),
+                Event::SequenceStart(_) => self.stack.push(Frame::Sequence { path, index: 0 }),
+                _ => (),
+            }
+        }
+
+        if matches!(event, Event::MappingEnd | Event::SequenceEnd) {
+            self.stack.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_find_spans() {
+        let yaml = indoc! {"
+            name: test
+            steps:
+              - name: step 1
+                url: http://localhost/${{ env.ID }}
+                assertions:
+                  - !expect status to_equal 200
+                  - !!expect body to_be_null
+        "};
+        let spans = find_spans(yaml);
+
+        let span = |path: &str| {
+            let span = spans[path];
+            (span.line, span.column)
+        };
+        assert_eq!(span("name"), (1, 7));
+        assert_eq!(span("steps.0"), (3, 5));
+        assert_eq!(span("steps.0.url"), (4, 10));
+        assert_eq!(span("steps.0.assertions.1"), (7, 18));
+
+        let template = spans["steps.0.url"].find(yaml, "${{ env.ID }}");
+        assert_eq!((template.line, template.column), (4, 27));
+        let assertion = spans["steps.0.assertions.1"].find(yaml, "body to_be_null");
+        assert_eq!((assertion.line, assertion.column), (7, 18));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    compile::{
+        compile_value,
+        expression::{evaluate, MASKED_KEY},
+    },
+    error::CapterError,
+    workflow::{ResponseData, WorkflowConfigExtract, WorkflowConfigStep},
+};
+use regex::Regex;
+use serde_json::{json, Value};
+
+/// Save the variables in `set` on the step. This is done before
+/// the request is created, so the step can use them too.
+///
+/// A variable is masked if any of its templates are masked,
+/// like `${{ mask env.TOKEN }}`.
+pub fn set_vars(step: &WorkflowConfigStep, workflow_data: &mut Value) -> Result<(), CapterError> {
+    let vars = match &step.set {
+        Some(vars) => vars,
+        None => return Ok(()),
+    };
+
+    for (name, value) in vars {
+        let compiled = compile_value(Some(value.to_owned()), workflow_data)?;
+        let is_masked = compiled.raw != compiled.masked;
+        let value = serde_json::to_value(compiled.raw).unwrap_or(Value::Null);
+
+        save_var(workflow_data, name, value, is_masked);
+    }
+
+    Ok(())
+}
+
+/// Save the variables in `extract` on the step, from the response.
+///
+/// Expressions are run on `status`, `headers`, `body` and `text`
+/// of the response, so `body.token` gets the token in the body.
+pub fn extract_vars(
+    step: &WorkflowConfigStep,
+    response: Option<&ResponseData>,
+    workflow_data: &mut Value,
+) -> Result<(), CapterError> {
+    let extract = match &step.extract {
+        Some(extract) => extract,
+        None => return Ok(()),
+    };
+
+    let default = ResponseData::default();
+    let response = response.unwrap_or(&default);
+    let data = json!({
+        "status": response.status,
+        "headers": response.headers,
+        "body": response.body,
+        "text": response.text,
+    });
+
+    for (name, extract) in extract {
+        let result = match extract {
+            WorkflowConfigExtract::Expression(expression) => {
+                evaluate(expression, &data).map(|value| (value, false))
+            }
+            WorkflowConfigExtract::Options { from, regex, mask } => {
+                let from = match (from, regex) {
+                    (Some(from), _) => from.as_str(),
+                    (None, Some(_)) => "text",
+                    (None, None) => "body",
+                };
+
+                evaluate(from, &data)
+                    .and_then(|value| match regex {
+                        Some(regex) => capture(regex, &value),
+                        None => Ok(value),
+                    })
+                    .map(|value| (value, mask.unwrap_or(false)))
+            }
+        };
+
+        match result {
+            Ok((value, is_masked)) => save_var(workflow_data, name, value, is_masked),
+            Err(err) => {
+                return Err(CapterError::Template(format!(
+                    "invalid extract `{}`: {}",
+                    name, err
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The first group of the regex, or the whole match if there are no groups.
+fn capture(regex: &str, value: &Value) -> Result<Value, String> {
+    let regex = Regex::new(regex).map_err(|err| err.to_string())?;
+    let text = match value {
+        Value::String(text) => text.to_owned(),
+        Value::Null => return Ok(Value::Null),
+        value => value.to_string(),
+    };
+
+    let captured = regex
+        .captures(&text)
+        .and_then(|captures| captures.get(1).or_else(|| captures.get(0)))
+        .map(|capture| Value::String(capture.as_str().to_string()));
+
+    Ok(captured.unwrap_or(Value::Null))
+}
+
+fn save_var(workflow_data: &mut Value, name: &str, value: Value, is_masked: bool) {
+    workflow_data["vars"][name] = value;
+
+    if !is_masked {
+        return;
+    }
+
+    // the masked paths are checked every time a template uses them
+    let path = json!(format!("vars.{}", name));
+    if !workflow_data[MASKED_KEY].is_array() {
+        workflow_data[MASKED_KEY] = json!([]);
+    }
+    if let Some(masked) = workflow_data[MASKED_KEY].as_array_mut() {
+        if !masked.contains(&path) {
+            masked.push(path);
+        }
+    }
+}
+
+/// The variables in `workflow_data`, and which of them are masked,
+/// so they can be shared with other workflows.
+pub fn shared_vars(workflow_data: &Value) -> Value {
+    let mut shared = json!({ "vars": workflow_data["vars"] });
+    shared[MASKED_KEY] = workflow_data[MASKED_KEY].to_owned();
+    shared
+}
+
+/// Add variables from `shared_vars` to `workflow_data`.
+pub fn add_shared_vars(workflow_data: &mut Value, shared: &Value) {
+    if shared["vars"].is_object() {
+        workflow_data["vars"] = shared["vars"].to_owned();
+    }
+    if shared[MASKED_KEY].is_array() {
+        workflow_data[MASKED_KEY] = shared[MASKED_KEY].to_owned();
+    }
+}
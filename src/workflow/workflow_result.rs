@@ -1,13 +1,21 @@
-use crate::CliOptions;
-use crate::{
-    assert::AssertionResultData,
-    workflow::{Request, RequestData, WorkflowConfig},
+use crate::compile::{expression, functions};
+use crate::error::CapterError;
+use crate::openapi::OpenApiSpec;
+use crate::validate::check_workflow;
+use crate::workflow::{
+    for_each::for_each_items,
+    vars::{add_shared_vars, extract_vars, set_vars, shared_vars},
+    Request, RequestData, RunOptions, WorkflowConfig, WorkflowConfigStep, WorkflowConfigStepRetry,
 };
 use chrono::DateTime;
 use chrono::Utc;
 use serde::Serialize;
-use serde_json::json;
-use std::{env, time::Instant};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    env, fmt, thread,
+    time::{Duration, Instant},
+};
 
 /// The result of a workflow is saved in to this struct.
 /// It can be serialized to JSON and we pass it to the webhook
@@ -21,17 +29,75 @@ pub struct WorkflowResult {
     pub run_time: i64,
     pub passed: bool,
     pub requests: Vec<RequestData>,
+    /// The seed for generated values, like `uuid()`. Pass it
+    /// with `--seed` to get the same values again.
+    pub seed: u64,
+    /// The variables at the end of the run, from `set` and `extract`.
+    /// They are not masked, so they are never sent anywhere.
+    #[serde(skip)]
+    pub vars: serde_json::Value,
+    /// Why the workflow couldn't run, if it errored.
+    pub error: Option<String>,
+    /// If the workflow didn't run, because of `skip`, a filter
+    /// like `--tag`, or a setup that failed.
+    pub skipped: bool,
+    /// The steps that didn't run, and why.
+    pub skipped_steps: Vec<SkippedStep>,
+}
+
+/// A step that didn't run. It's in the results
+/// so reports can show it as skipped.
+#[derive(Debug, Serialize, Clone)]
+pub struct SkippedStep {
+    pub name: String,
+    pub order: i32,
+    /// Why it didn't run, from `SkipReason`.
+    pub reason: String,
 }
 
 /// The `CallbackEvents` are called during the workflows lifetime.
 /// Use the callback argument in `WorkflowResult::from_config` to
 /// react to updates during the run.
+#[derive(Clone, Copy)]
 pub enum CallbackEvent<'a> {
     RunStart(&'a WorkflowConfig),
     RunDone(&'a WorkflowConfig, bool),
+    RunSkipped(&'a WorkflowConfig),
+    /// The workflow stopped because of a mistake in it. This is
+    /// sent instead of `RunDone`.
+    RunErrored(&'a WorkflowConfig, &'a CapterError),
     StepStart(&'a WorkflowConfig, i32),
-    StepDone(&'a WorkflowConfig, i32, &'a Vec<AssertionResultData>, bool),
-    StepSkipped(&'a WorkflowConfig, i32),
+    StepDone(&'a WorkflowConfig, i32, &'a RequestData, bool),
+    StepSkipped(&'a WorkflowConfig, i32, &'a SkipReason),
+    /// The step is about to be retried, with the number of the attempt that failed.
+    StepRetry(&'a WorkflowConfig, i32, u32),
+}
+
+/// Why a step didn't run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// The step has `skip`, or was filtered out.
+    Skip,
+    /// The `if` expression on the step was false.
+    Condition(String),
+    /// A step in `needs` failed, was skipped, or hasn't run.
+    Needs(String),
+    /// An earlier step failed, and the workflow has `fail_fast`.
+    FailFast,
+    /// The list in `for_each` is empty.
+    NoItems,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkipReason::Skip => write!(f, "skipped"),
+            SkipReason::Condition(condition) => write!(f, "`{}` was false", condition),
+            SkipReason::Needs(id) => write!(f, "needs `{}` to pass", id),
+            SkipReason::FailFast => write!(f, "an earlier step failed"),
+            SkipReason::NoItems => write!(f, "`for_each` has no items"),
+        }
+    }
 }
 
 impl WorkflowResult {
@@ -39,11 +105,88 @@ impl WorkflowResult {
     /// and returns a result when all requests are done and asserted on.
     ///
     /// Use the callback argument to get continous updates from the run.
+    ///
+    /// Returns an error if the workflow can't run, like when a template
+    /// or an assertion can't be parsed. The run stops at the first one.
     pub fn from_config(
-        cli_options: &CliOptions,
+        run_options: &RunOptions,
         config: &WorkflowConfig,
         mut callback: impl FnMut(CallbackEvent),
-    ) -> Result<WorkflowResult, Box<dyn std::error::Error>> {
+    ) -> Result<WorkflowResult, CapterError> {
+        callback(CallbackEvent::RunStart(config));
+
+        let result = WorkflowResult::run(run_options, config, &mut callback);
+        match &result {
+            Ok(result) => callback(CallbackEvent::RunDone(config, result.passed)),
+            Err(err) => callback(CallbackEvent::RunErrored(config, err)),
+        }
+
+        result
+    }
+
+    /// The result of a workflow that errored. It
+    /// has no requests, and it didn't pass.
+    pub fn from_error(
+        run_options: &RunOptions,
+        config: &WorkflowConfig,
+        error: &CapterError,
+    ) -> WorkflowResult {
+        WorkflowResult {
+            name: config.name.to_owned(),
+            file: config.file.to_owned(),
+            workflow: config.to_owned(),
+            created_at: Utc::now(),
+            requests: vec![],
+            run_time: 0,
+            passed: false,
+            seed: run_options.seed.unwrap_or_default(),
+            vars: json!({}),
+            error: Some(error.to_string()),
+            skipped: false,
+            skipped_steps: vec![],
+        }
+    }
+
+    /// The result of a workflow that was skipped. Every step is
+    /// skipped too, and a skipped workflow doesn't fail the run.
+    pub fn from_skipped(run_options: &RunOptions, config: &WorkflowConfig) -> WorkflowResult {
+        WorkflowResult {
+            name: config.name.to_owned(),
+            file: config.file.to_owned(),
+            workflow: config.to_owned(),
+            created_at: Utc::now(),
+            requests: vec![],
+            run_time: 0,
+            passed: true,
+            seed: run_options.seed.unwrap_or_default(),
+            vars: json!({}),
+            error: None,
+            skipped: true,
+            skipped_steps: config
+                .steps
+                .iter()
+                .enumerate()
+                .map(|(index, step)| SkippedStep {
+                    name: step.name.to_owned(),
+                    order: index as i32,
+                    reason: SkipReason::Skip.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn run(
+        run_options: &RunOptions,
+        config: &WorkflowConfig,
+        callback: &mut impl FnMut(CallbackEvent),
+    ) -> Result<WorkflowResult, CapterError> {
+        // mistakes like an assertion that can't be parsed are
+        // found before any request is sent, with where they are
+        let diagnostics = check_workflow(config);
+        if !diagnostics.is_empty() {
+            return Err(CapterError::Invalid(diagnostics));
+        }
+
         // this is where we save all data available when creating the requests
         // if a workflow step as an `id` property, we will save the data like
         // `workflow_data.{id}.request`, `workflow_data.{id}.response`
@@ -51,6 +194,11 @@ impl WorkflowResult {
         // to build up requests based on previous data
         let mut workflow_data = json!({});
 
+        // generated values are seeded per workflow, so the same seed
+        // gives the same values no matter how many jobs we run with
+        let seed = run_options.seed.unwrap_or_else(functions::random_seed);
+        functions::set_seed(seed, config.file.as_ref().unwrap_or(&config.name));
+
         // add env to workflow data
         for (key, value) in env::vars() {
             workflow_data["env"][key] = value.into();
@@ -61,62 +209,217 @@ impl WorkflowResult {
             }
         }
 
-        callback(CallbackEvent::RunStart(config));
+        // variables from the setup workflow
+        if let Some(vars) = &run_options.vars {
+            add_shared_vars(&mut workflow_data, vars);
+        }
+
+        // inline JSON schemas, used like `!expect body to_match_schema ${{ schemas.user }}`
+        if let Some(schemas) = &config.schemas {
+            for (key, value) in schemas {
+                workflow_data["schemas"][key] = json!(value);
+            }
+        }
+
+        // every response is checked against the spec, if there is one.
+        // the one for the run is loaded once, a workflow can have its own
+        let workflow_openapi = match &config.openapi {
+            Some(path) => Some(OpenApiSpec::from_file(path).map_err(CapterError::Config)?),
+            None => None,
+        };
+        let openapi = workflow_openapi.as_ref().or(run_options.openapi.as_ref());
 
         // all requests are saved here
         // these are added to the WorkflowResult
         let mut requests: Vec<RequestData> = Vec::with_capacity(config.steps.len());
 
+        // and the steps that didn't run
+        let mut skipped_steps: Vec<SkippedStep> = vec![];
+
         // keeps track of what step we are processing
         let mut step_index = 0;
 
         let mut workflow_passed = true;
 
+        // if the steps with an id passed, for `needs`
+        let mut passed_steps: HashMap<&String, bool> = HashMap::new();
+
         // this is used to know for how long the workflow has run
         let timer = Instant::now();
 
         for step in config.steps.iter() {
             callback(CallbackEvent::StepStart(config, step_index));
 
-            if step.skip.is_some() {
-                callback(CallbackEvent::StepSkipped(config, step_index));
+            let in_step = |err: CapterError| err.in_step(&step.name);
+
+            if let Some(reason) =
+                skip_reason(config, step, &workflow_data, &passed_steps, workflow_passed)
+                    .map_err(in_step)?
+            {
+                if let Some(id) = &step.id {
+                    passed_steps.insert(id, false);
+                }
+                skipped_steps.push(SkippedStep {
+                    name: step.name.to_owned(),
+                    order: step_index,
+                    reason: reason.to_string(),
+                });
+                callback(CallbackEvent::StepSkipped(config, step_index, &reason));
                 step_index += 1;
                 continue;
             }
 
-            let mut request = Request::new(config, step_index, &workflow_data, cli_options.timeout);
+            // schemas on the step are added on top of the ones in the workflow
+            if let Some(schemas) = &step.schemas {
+                for (key, value) in schemas {
+                    workflow_data["schemas"][key] = json!(value);
+                }
+            }
 
-            // add it to workflow_data if id is set
-            if let Some(id) = &step.id {
-                workflow_data[&id]["request"] = serde_json::to_value(request.data())?;
+            // steps with `for_each` run once for every item
+            let items: Vec<Option<Value>> =
+                match for_each_items(step, &workflow_data, &config.dir()).map_err(in_step)? {
+                    Some(items) => items.into_iter().map(Some).collect(),
+                    None => vec![None],
+                };
+            if items.is_empty() {
+                if let Some(id) = &step.id {
+                    passed_steps.insert(id, false);
+                }
+                skipped_steps.push(SkippedStep {
+                    name: step.name.to_owned(),
+                    order: step_index,
+                    reason: SkipReason::NoItems.to_string(),
+                });
+                callback(CallbackEvent::StepSkipped(
+                    config,
+                    step_index,
+                    &SkipReason::NoItems,
+                ));
+                step_index += 1;
+                continue;
             }
 
-            // do the request
-            let response_data = request.call();
+            let mut step_passed = true;
 
-            // assert on response
-            let assertion_result_data = request.assert_on_response(&step.assertions);
+            for (item_index, item) in items.into_iter().enumerate() {
+                if let Some(item) = &item {
+                    workflow_data["item"] = item.to_owned();
+                    workflow_data["index"] = json!(item_index);
 
-            // check if step passed
-            let step_passed = !assertion_result_data.iter().any(|r| r.passed == false);
+                    // the first item was started with the step
+                    if item_index > 0 {
+                        callback(CallbackEvent::StepStart(config, step_index));
+                    }
+                }
 
-            callback(CallbackEvent::StepDone(
-                config,
-                step_index,
-                &assertion_result_data,
-                step_passed,
-            ));
+                // variables from `set` can be used in the step itself
+                set_vars(step, &mut workflow_data).map_err(in_step)?;
 
-            // add to workflow_data if the step has an id
-            if let Some(id) = &step.id {
-                workflow_data[&id]["response"] = serde_json::to_value(response_data).unwrap();
+                let mut request =
+                    Request::new(config, step_index, &workflow_data, run_options.timeout)
+                        .map_err(in_step)?;
+
+                // add it to workflow_data if id is set
+                if let Some(id) = &step.id {
+                    workflow_data[&id]["request"] =
+                        serde_json::to_value(request.data()).unwrap_or_default();
+                }
+
+                // steps with `retry` or `until` are called until they pass,
+                // or until we run out of attempts
+                let retry = match (&step.retry, &step.until) {
+                    (Some(retry), _) => retry.to_owned(),
+                    (None, Some(_)) => WorkflowConfigStepRetry::default(),
+                    (None, None) => WorkflowConfigStepRetry {
+                        attempts: 1,
+                        ..Default::default()
+                    },
+                };
+                let mut delay = retry.delay.unwrap_or(0) as f64;
+                let mut attempt = 1;
+
+                let (response_data, item_passed) = loop {
+                    // do the request
+                    let response_data = request.call();
+
+                    // assert on response
+                    let mut assertion_result_data = request
+                        .assert_on_response(&step.assertions)
+                        .map_err(in_step)?;
+                    if let Some(openapi) = &openapi {
+                        assertion_result_data.append(&mut request.assert_on_spec(openapi));
+                    }
+
+                    // check if step passed
+                    let step_passed = !assertion_result_data.iter().any(|r| !r.passed);
+
+                    // `until` decides when we're done polling, otherwise we
+                    // keep going until the assertions pass
+                    let is_done = match &step.until {
+                        Some(until) => request.check_response(until).map_err(in_step)?,
+                        None => step_passed,
+                    };
+
+                    if is_done {
+                        break (response_data, step_passed);
+                    }
+
+                    // the step fails if we never got what we polled for
+                    if attempt >= retry.attempts {
+                        if let Some(until) = &step.until {
+                            request.fail_until(until, attempt);
+                        }
+                        break (response_data, false);
+                    }
+
+                    callback(CallbackEvent::StepRetry(config, step_index, attempt));
+
+                    request.retry();
+                    thread::sleep(Duration::from_millis(delay as u64));
+                    delay *= retry.backoff.unwrap_or(1.0);
+                    attempt += 1;
+                };
+
+                // masked data is what we pass on, both to the callback
+                // and to our request array
+                let mut request_data = request.data_masked();
+                if item.is_some() {
+                    request_data.name = format!("{} [{}]", step.name, item_index);
+                }
+
+                callback(CallbackEvent::StepDone(
+                    config,
+                    step_index,
+                    &request_data,
+                    item_passed,
+                ));
+
+                // variables from `extract` can be used in the following steps
+                extract_vars(step, response_data.as_ref(), &mut workflow_data).map_err(in_step)?;
+
+                // add to workflow_data if the step has an id
+                if let Some(id) = &step.id {
+                    workflow_data[&id]["response"] = serde_json::to_value(response_data).unwrap();
+                }
+
+                requests.push(request_data);
+
+                step_passed = step_passed && item_passed;
             }
 
-            // push masked data to our request array
-            requests.push(request.data_masked());
+            // `item` and `index` are only set inside the loop
+            if let Some(workflow_data) = workflow_data.as_object_mut() {
+                workflow_data.remove("item");
+                workflow_data.remove("index");
+            }
+
+            if let Some(id) = &step.id {
+                passed_steps.insert(id, step_passed);
+            }
 
             // is one step fails, the whole worklfow is set to fail too
-            if workflow_passed == true && step_passed == false {
+            if workflow_passed && !step_passed {
                 workflow_passed = false;
             }
 
@@ -126,8 +429,6 @@ impl WorkflowResult {
         // for how long it ran
         let run_time = timer.elapsed().as_millis() as i64;
 
-        callback(CallbackEvent::RunDone(config, workflow_passed));
-
         Ok(WorkflowResult {
             name: config.name.to_owned(),
             file: config.file.to_owned(),
@@ -136,15 +437,64 @@ impl WorkflowResult {
             requests,
             run_time,
             passed: workflow_passed,
+            seed,
+            vars: shared_vars(&workflow_data),
+            error: None,
+            skipped: false,
+            skipped_steps,
         })
     }
 }
 
+/// Returns why the step shouldn't run, if it shouldn't.
+fn skip_reason(
+    config: &WorkflowConfig,
+    step: &WorkflowConfigStep,
+    workflow_data: &Value,
+    passed_steps: &HashMap<&String, bool>,
+    workflow_passed: bool,
+) -> Result<Option<SkipReason>, CapterError> {
+    if step.skip.is_some() {
+        return Ok(Some(SkipReason::Skip));
+    }
+
+    if config.fail_fast == Some(true) && !workflow_passed {
+        return Ok(Some(SkipReason::FailFast));
+    }
+
+    // a step that hasn't run yet hasn't passed either
+    let failed_need = step
+        .needs
+        .iter()
+        .flatten()
+        .find(|id| passed_steps.get(id) != Some(&true));
+    if let Some(id) = failed_need {
+        return Ok(Some(SkipReason::Needs(id.to_owned())));
+    }
+
+    if let Some(condition) = &step.condition {
+        // both `vars.id != null` and `${{ vars.id != null }}` work
+        let expression = condition
+            .trim()
+            .strip_prefix("${{")
+            .and_then(|expression| expression.strip_suffix("}}"))
+            .unwrap_or(condition);
+
+        match expression::evaluate(expression, workflow_data) {
+            Ok(value) if expression::is_truthy(&value) => (),
+            Ok(_) => return Ok(Some(SkipReason::Condition(expression.trim().to_string()))),
+            Err(err) => return Err(CapterError::Template(format!("invalid `if`: {}", err))),
+        }
+    }
+
+    Ok(None)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use indoc::formatdoc;
-    use mockito::mock;
+    use indoc::{formatdoc, indoc};
+    use mockito::{mock, Matcher};
 
     #[test]
     /// Tests a full workflows, almost like an integration test.
@@ -195,20 +545,599 @@ mod tests {
             url = url,
             path = "${{ test.response.body.0.id }}"
         };
-        let workflow_config = WorkflowConfig::from_yaml(yaml.into());
+        let workflow_config = WorkflowConfig::from_yaml(yaml);
 
         let result =
-            WorkflowResult::from_config(&CliOptions::default(), &workflow_config.unwrap(), |_| {});
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config.unwrap(), |_| {});
         let result = result.unwrap();
         let response1 = result.requests[0].response.to_owned().unwrap();
         let response2 = result.requests[1].response.to_owned().unwrap();
 
         assert_eq!(result.requests.len(), 2);
-        assert_eq!(response1.assertion_results[0].passed, true);
+        assert!(response1.assertion_results[0].passed);
         assert_eq!(
             response1.assertion_results[2].message,
             Some("expected null to equal 5".to_string())
         );
         assert_eq!(response2.assertion_results[0].message, None);
     }
+
+    #[test]
+    fn test_retry_until() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/retry/poll")
+            .with_status(200)
+            .with_body(r#"{"status": "pending"}"#)
+            .expect(2)
+            .create();
+        let _m2 = mock("GET", "/retry/poll")
+            .with_status(200)
+            .with_body(r#"{"status": "done"}"#)
+            .create();
+        let _m3 = mock("GET", "/retry/fail").with_status(500).create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: test
+            steps:
+              - name: poll
+                url: {url}/retry/poll
+                retry:
+                  attempts: 5
+                  delay: 10
+                  backoff: 2
+                until:
+                  - !expect body.status to_equal done
+                assertions:
+                  - !expect status to_equal 200
+              - name: fail
+                url: {url}/retry/fail
+                retry:
+                  attempts: 2
+                  delay: 10
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+
+        let mut retries = vec![];
+        let result = WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |e| {
+            if let CallbackEvent::StepRetry(_, index, attempt) = e {
+                retries.push((index, attempt));
+            }
+        })
+        .unwrap();
+
+        assert_eq!(retries, vec![(0, 1), (0, 2), (1, 1)]);
+
+        let poll = &result.requests[0];
+        assert_eq!(poll.attempts.len(), 2);
+        assert_eq!(poll.attempts[0].body, Some(json!({"status": "pending"})));
+        assert_eq!(
+            poll.response.to_owned().unwrap().body,
+            Some(json!({"status": "done"}))
+        );
+
+        let fail = &result.requests[1];
+        assert_eq!(fail.attempts.len(), 1);
+        assert_eq!(fail.attempts[0].status, Some(500));
+        assert!(!result.passed);
+    }
+
+    #[test]
+    fn test_until_never_passes() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/retry/pending")
+            .with_status(200)
+            .with_body(r#"{"status": "pending"}"#)
+            .expect(3)
+            .create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: test
+            steps:
+              - name: poll
+                url: {url}/retry/pending
+                retry:
+                  attempts: 3
+                  delay: 10
+                until:
+                  - !expect body.status to_equal done
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+
+        let mut passed = vec![];
+        let result = WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |e| {
+            if let CallbackEvent::StepDone(_, _, _, step_passed) = e {
+                passed.push(step_passed);
+            }
+        })
+        .unwrap();
+
+        // the assertions pass, but we never got what we polled for
+        assert_eq!(passed, vec![false]);
+        assert!(!result.passed);
+
+        let response = result.requests[0].response.to_owned().unwrap();
+        let results: Vec<(&str, bool)> = response
+            .assertion_results
+            .iter()
+            .map(|r| (r.assertion.test.as_str(), r.passed))
+            .collect();
+        assert_eq!(results, vec![("to_equal", true), ("until", false)]);
+        assert_eq!(
+            response.assertion_results[1].message,
+            Some("`until` didn't pass after 3 attempts: body.status to_equal done".to_string())
+        );
+    }
+
+    #[test]
+    fn test_conditions() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/conditions/login").with_status(401).create();
+        let _m2 = mock("GET", "/conditions/status").with_status(200).create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: test
+            steps:
+              - name: status
+                id: status
+                url: {url}/conditions/status
+                assertions:
+                  - !expect status to_equal 200
+              - name: login
+                id: login
+                url: {url}/conditions/login
+                method: POST
+                assertions:
+                  - !expect status to_equal 200
+              - name: profile
+                needs: [login]
+                url: {url}/conditions/status
+                assertions: []
+              - name: maintenance
+                if: ${{{{ status.response.status == 503 }}}}
+                url: {url}/conditions/status
+                assertions: []
+              - name: up
+                if: status.response.status == 200 && env.MISSING == null
+                needs: [status]
+                url: {url}/conditions/status
+                assertions: []
+            ",
+            url = url,
+        };
+        let mut workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+
+        let mut skipped = vec![];
+        let result = WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |e| {
+            if let CallbackEvent::StepSkipped(_, index, reason) = e {
+                skipped.push((index, reason.to_owned()));
+            }
+        })
+        .unwrap();
+
+        assert_eq!(
+            skipped,
+            vec![
+                (2, SkipReason::Needs("login".to_string())),
+                (
+                    3,
+                    SkipReason::Condition("status.response.status == 503".to_string())
+                ),
+            ]
+        );
+        assert_eq!(result.requests.len(), 3);
+        assert!(!result.passed);
+
+        workflow_config.fail_fast = Some(true);
+        let mut skipped = vec![];
+        WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |e| {
+            if let CallbackEvent::StepSkipped(_, index, reason) = e {
+                skipped.push((index, reason.to_owned()));
+            }
+        })
+        .unwrap();
+
+        assert_eq!(
+            skipped,
+            vec![
+                (2, SkipReason::FailFast),
+                (3, SkipReason::FailFast),
+                (4, SkipReason::FailFast),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_for_each() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/for-each/products")
+            .with_status(200)
+            .with_body(r#"[{"id": 1}, {"id": 2}]"#)
+            .create();
+        let _m2 = mock("GET", "/for-each/products/1?locale=en")
+            .with_status(200)
+            .create();
+        let _m3 = mock("GET", "/for-each/products/2?locale=en")
+            .with_status(404)
+            .create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: test
+            steps:
+              - name: products
+                id: products
+                url: {url}/for-each/products
+                assertions: []
+              - name: product
+                id: product
+                for_each: ${{{{ products.response.body }}}}
+                url: {url}/for-each/products/${{{{ item.id }}}}
+                query:
+                  locale: en
+                assertions:
+                  - !expect status to_equal 200
+              - name: after
+                needs: [product]
+                url: {url}/for-each/products
+                assertions: []
+              - name: none
+                for_each: []
+                url: {url}/for-each/products
+                assertions: []
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+
+        let mut events = vec![];
+        let result =
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |e| match e {
+                CallbackEvent::StepDone(_, index, request, passed) => {
+                    events.push(format!("{} {} {}", index, request.name, passed))
+                }
+                CallbackEvent::StepSkipped(_, index, reason) => {
+                    events.push(format!("{} {}", index, reason))
+                }
+                _ => (),
+            })
+            .unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                "0 products true",
+                "1 product [0] true",
+                "1 product [1] false",
+                "2 needs `product` to pass",
+                "3 `for_each` has no items",
+            ]
+        );
+        assert_eq!(result.requests.len(), 3);
+        assert_eq!(
+            result.requests[2].response.as_ref().unwrap().status,
+            Some(404)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/errors").with_status(200).create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: test
+            steps:
+              - name: status
+                url: {url}/errors
+                assertions:
+                  - !expect status to_equal 200
+              - name: broken
+                url: {url}/errors/${{{{ nope() }}}}
+                assertions: []
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+
+        let mut events = vec![];
+        let result =
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |e| match e {
+                CallbackEvent::StepDone(_, index, _, passed) => {
+                    events.push(format!("{} {}", index, passed))
+                }
+                CallbackEvent::RunDone(..) => events.push("done".to_string()),
+                CallbackEvent::RunErrored(_, error) => events.push(error.to_string()),
+                _ => (),
+            });
+
+        let error = result.unwrap_err();
+        assert!(matches!(&error, CapterError::Step(name, _) if name == "broken"));
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], "0 true");
+        assert!(events[1]
+            .starts_with("in step `broken`: invalid template `${{ nope() }}`: unknown function"));
+
+        let result = WorkflowResult::from_error(&RunOptions::default(), &workflow_config, &error);
+        assert!(!result.passed);
+        assert!(result.requests.is_empty());
+        assert_eq!(result.error, Some(error.to_string()));
+
+        // mistakes that can be found before the run stop it before any request
+        let yaml = indoc! {"
+            ---
+            name: test
+            steps:
+              - name: status
+                url: http://localhost/errors
+                assertions:
+                  - !expect status to_bee 200
+        "};
+        let workflow_config = WorkflowConfig::from_yaml(yaml.into()).unwrap();
+        let error = WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |_| {})
+            .unwrap_err();
+
+        match error {
+            CapterError::Invalid(diagnostics) => {
+                assert_eq!(diagnostics.len(), 1);
+                assert_eq!(
+                    diagnostics[0].message,
+                    "Could not parse assertion: `status to_bee 200`"
+                );
+            }
+            error => panic!("expected diagnostics, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn test_inline_schemas() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/schemas/user")
+            .with_status(200)
+            .with_body(r#"{"id": 1, "name": "Test McTest"}"#)
+            .create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: test
+            schemas:
+              user:
+                type: object
+                required: [id, name]
+                properties:
+                  id:
+                    type: integer
+            steps:
+              - name: user
+                url: {url}/schemas/user
+                schemas:
+                  admin:
+                    type: object
+                    required: [role]
+                assertions:
+                  - !expect body to_match_schema ${{{{ schemas.user }}}}
+                  - !!expect body to_match_schema ${{{{ schemas.admin }}}}
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+        let result =
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |_| {}).unwrap();
+
+        assert!(result.passed);
+    }
+
+    #[test]
+    fn test_openapi() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/openapi/users/1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id": "1"}"#)
+            .create();
+
+        let spec = indoc! {"
+            openapi: 3.0.0
+            paths:
+              /openapi/users/{id}:
+                get:
+                  responses:
+                    '200':
+                      description: a user
+                      content:
+                        application/json:
+                          schema:
+                            type: object
+                            properties:
+                              id:
+                                type: integer
+        "};
+        let path = std::env::temp_dir().join("capter_workflow_openapi.yml");
+        std::fs::write(&path, spec).unwrap();
+
+        let yaml = formatdoc! {"
+            ---
+            name: test
+            openapi: {path}
+            steps:
+              - name: user
+                url: {url}/openapi/users/1
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = url,
+            path = path.to_str().unwrap(),
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+        let result =
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |_| {}).unwrap();
+
+        let response = result.requests[0].response.to_owned().unwrap();
+        let results: Vec<(&str, bool)> = response
+            .assertion_results
+            .iter()
+            .map(|r| (r.assertion.property.as_str(), r.passed))
+            .collect();
+        assert_eq!(
+            results,
+            vec![("status", true), ("status", true), ("body", false)]
+        );
+        assert!(!result.passed);
+
+        // the spec for the run is used by workflows without their own
+        let mut workflow_config = workflow_config;
+        workflow_config.openapi = None;
+        let run_options = RunOptions::builder()
+            .openapi(OpenApiSpec::from_yaml(spec).unwrap())
+            .build();
+        let result = WorkflowResult::from_config(&run_options, &workflow_config, |_| {}).unwrap();
+
+        let response = result.requests[0].response.to_owned().unwrap();
+        assert_eq!(response.assertion_results.len(), 3);
+        assert!(!result.passed);
+    }
+
+    #[test]
+    fn test_relative_files() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", Matcher::Regex(r"^/relative/users/\d$".to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id": 1}"#)
+            .create();
+
+        let dir = std::env::temp_dir().join("capter_relative").join(".capter");
+        std::fs::create_dir_all(dir.join("fixtures")).unwrap();
+        std::fs::write(dir.join("fixtures/users.csv"), "id\n1\n2\n").unwrap();
+        std::fs::write(dir.join("fixtures/user.yml"), "required: [id]\n").unwrap();
+
+        // files are relative to the workflow, not to where capter runs
+        let path = dir.join("users.yml");
+        std::fs::write(
+            &path,
+            formatdoc! {"
+                ---
+                name: test
+                steps:
+                  - name: user
+                    for_each: fixtures/users.csv
+                    url: {url}/relative/users/${{{{ item.id }}}}
+                    assertions:
+                      - !expect body to_match_schema ./fixtures/user.yml
+                ",
+                url = url,
+            },
+        )
+        .unwrap();
+        let workflow_config = WorkflowConfig::read_yaml_file(&path).unwrap();
+        let result =
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |_| {}).unwrap();
+
+        assert_eq!(result.requests.len(), 2);
+        assert!(result.passed);
+    }
+
+    #[test]
+    fn test_seed() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/seed").with_status(201).create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: test
+            steps:
+              - name: create user
+                url: POST {url}/seed
+                body:
+                  id: ${{{{ uuid() }}}}
+                  email: ${{{{ fake.email() }}}}
+                assertions:
+                  - !expect status to_equal 201
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+        let run_options = RunOptions::builder().seed(7).build();
+        let first = WorkflowResult::from_config(&run_options, &workflow_config, |_| {}).unwrap();
+        let second = WorkflowResult::from_config(&run_options, &workflow_config, |_| {}).unwrap();
+
+        assert_eq!(first.seed, 7);
+        assert_eq!(first.requests[0].body, second.requests[0].body);
+    }
+
+    #[test]
+    fn test_vars() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/vars/login")
+            .with_status(200)
+            .with_header("etag", "abc")
+            .with_body(r#"{"access_token": "secret"}"#)
+            .create();
+        let _m2 = mock("GET", "/vars/session")
+            .with_status(200)
+            .with_header("content-type", "text/plain")
+            .with_body("session=s123; path=/")
+            .create();
+        let _m3 = mock("GET", "/vars/me")
+            .match_header("authorization", "Bearer secret")
+            .match_header("etag", "abc")
+            .match_header("session", "s123")
+            .match_header("user", "test")
+            .with_status(200)
+            .create();
+
+        let yaml = formatdoc! {"
+            ---
+            name: test
+            steps:
+              - name: login
+                url: POST {url}/vars/login
+                extract:
+                  token:
+                    from: body.access_token
+                    mask: true
+                  etag: headers.etag
+                assertions:
+                  - !expect status to_equal 200
+              - name: session
+                url: GET {url}/vars/session
+                extract:
+                  session:
+                    regex: session=(\\w+)
+                assertions:
+                  - !expect status to_equal 200
+              - name: me
+                url: GET {url}/vars/me
+                set:
+                  user: test
+                headers:
+                  authorization: Bearer ${{{{ vars.token }}}}
+                  etag: ${{{{ vars.etag }}}}
+                  session: ${{{{ vars.session }}}}
+                  user: ${{{{ vars.user }}}}
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = url,
+        };
+        let workflow_config = WorkflowConfig::from_yaml(yaml).unwrap();
+        let result =
+            WorkflowResult::from_config(&RunOptions::default(), &workflow_config, |_| {}).unwrap();
+
+        assert!(result.passed);
+        let headers = result.requests[2].headers.to_owned().unwrap();
+        assert_eq!(headers["authorization"], "Bearer ****");
+        assert_eq!(headers["etag"], "abc");
+    }
 }
@@ -0,0 +1,108 @@
+use capter::{CallbackEvent, RunOptions, WorkflowConfig, WorkflowPool, WorkflowResult};
+use indoc::formatdoc;
+use mockito::{mock, server_url};
+use serde_json::{json, Map};
+use std::{env::temp_dir, fs};
+
+/// Write a workflow to a file, like the ones in `.capter`.
+fn workflow_file(name: &str, yaml: &str) -> WorkflowConfig {
+    let path = temp_dir().join(format!("capter-api-{}.yml", name));
+    fs::write(&path, yaml).unwrap();
+    WorkflowConfig::read_yaml_file(&path).unwrap()
+}
+
+#[test]
+fn test_run_with_vars() {
+    let _m = mock("GET", "/api/users/1")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"id": 1, "name": "Ada"}"#)
+        .create();
+
+    let config = workflow_file(
+        "vars",
+        &formatdoc! {"
+            name: users
+            steps:
+              - name: get user
+                url: ${{{{ vars.base_url }}}}/api/users/1
+                assertions:
+                  - !expect status to_equal 200
+                  - !expect body.name to_equal Ada
+        "},
+    );
+
+    let mut vars = Map::new();
+    vars.insert("base_url".to_string(), json!(server_url()));
+    let options = RunOptions::builder().timeout(5).vars(vars).build();
+
+    let mut steps = vec![];
+    let result = WorkflowResult::from_config(&options, &config, |event| {
+        if let CallbackEvent::StepDone(_, _, request, passed) = event {
+            steps.push((request.name.to_owned(), passed));
+        }
+    })
+    .unwrap();
+
+    assert!(result.passed);
+    assert_eq!(steps, vec![("get user".to_string(), true)]);
+}
+
+#[test]
+fn test_setup_vars() {
+    let _m1 = mock("POST", "/api/login")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"token": "secret"}"#)
+        .create();
+    let _m2 = mock("GET", "/api/me")
+        .match_header("authorization", "secret")
+        .with_status(200)
+        .create();
+
+    let setup = workflow_file(
+        "setup",
+        &formatdoc! {"
+            name: setup
+            steps:
+              - name: login
+                url: {url}/api/login
+                method: POST
+                extract:
+                  token:
+                    from: body.token
+                    mask: true
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = server_url(),
+        },
+    );
+    let me = workflow_file(
+        "me",
+        &formatdoc! {"
+            name: me
+            steps:
+              - name: get me
+                url: {url}/api/me
+                headers:
+                  authorization: ${{{{ vars.token }}}}
+                assertions:
+                  - !expect status to_equal 200
+            ",
+            url = server_url(),
+        },
+    );
+
+    let options = RunOptions::default();
+    let setup = WorkflowResult::from_config(&options, &setup, |_| {}).unwrap();
+    assert!(setup.passed);
+
+    let options = options.to_builder().setup_vars(&setup).jobs(2).build();
+    let results = WorkflowPool::new(&options).run(&[me], |_| {});
+
+    assert!(results[0].passed);
+    // the token is masked, like it was in setup
+    let headers = json!(results[0].requests[0].headers);
+    assert!(!headers.to_string().contains("secret"));
+}